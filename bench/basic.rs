@@ -106,7 +106,7 @@ impl DBIndex for Art<usize, usize> {
         results: &mut [(usize, usize)],
         guard: &Self::Guard<'a>,
     ) -> usize {
-        self.range(low_key, high_key, results, guard)
+        self.range(low_key..high_key, results, guard)
     }
 }
 
@@ -136,9 +136,7 @@ unsafe impl Sync for SingleThreadHashMap {}
 impl DBIndex for SingleThreadHashMap {
     type Guard<'a> = ();
 
-    fn pin(&self) -> Self::Guard<'_> {
-        ()
-    }
+    fn pin(&self) -> Self::Guard<'_> {}
     fn insert(&self, key: usize, v: usize, _guard: &Self::Guard<'_>) {
         unsafe {
             (*self.map.get()).insert(key, v);
@@ -183,11 +181,11 @@ impl DBIndex for flurry::HashMap<usize, usize> {
     }
 
     fn insert<'a>(&self, key: usize, v: usize, guard: &Self::Guard<'a>) {
-        self.insert(key, v, &guard);
+        self.insert(key, v, guard);
     }
 
     fn get<'a>(&self, key: &usize, guard: &Self::Guard<'a>) -> Option<usize> {
-        self.get(key, &guard).map(|v| *v)
+        self.get(key, guard).copied()
     }
 
     fn update<'a>(
@@ -59,7 +59,7 @@ impl ShumaiBench for TestBench {
 
             let scanned = self
                 .index
-                .range(&low_key_v, &high_key, &mut scan_buffer, &guard);
+                .range(low_key_v..high_key, &mut scan_buffer, &guard);
 
             for v in scan_buffer.iter().take(scanned) {
                 assert!(v.0 >= low_key_v);
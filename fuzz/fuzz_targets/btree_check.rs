@@ -3,15 +3,41 @@ use arbitrary::Arbitrary;
 use congee::Art;
 use libfuzzer_sys::fuzz_target;
 use std::collections::BTreeMap;
+use std::ops::Bound;
 
 /// Follow the tutorial from this post: https://tiemoko.com/blog/diff-fuzz/
 #[derive(Arbitrary, Debug)]
 enum MapMethod {
-    Get { key: usize },
-    Insert { key: usize, val: usize },
-    Update { key: usize, val: usize },
-    Range { low_v: usize, cnt: u8 },
-    Delete { key: usize },
+    Get {
+        key: usize,
+    },
+    Insert {
+        key: usize,
+        val: usize,
+    },
+    Update {
+        key: usize,
+        val: usize,
+    },
+    Range {
+        low_v: usize,
+        cnt: u8,
+        kind: RangeKind,
+        rev: bool,
+    },
+    Delete {
+        key: usize,
+    },
+}
+
+/// The range forms, `low..high`, `low..=high`, `low..`, `..high` and `..`.
+#[derive(Arbitrary, Debug)]
+enum RangeKind {
+    Exclusive,
+    Inclusive,
+    From,
+    To,
+    Full,
 }
 
 fuzz_target!(|methods: Vec<MapMethod>| {
@@ -61,7 +87,12 @@ fuzz_target!(|methods: Vec<MapMethod>| {
                     bt_map.remove(key);
                     art.remove(key, &guard);
                 }
-                MapMethod::Range { low_v, cnt } => {
+                MapMethod::Range {
+                    low_v,
+                    cnt,
+                    kind,
+                    rev,
+                } => {
                     let cnt = *cnt as usize;
 
                     // prevent integer overflow
@@ -71,8 +102,27 @@ fuzz_target!(|methods: Vec<MapMethod>| {
                         low_v + cnt
                     };
 
-                    let art_range = art.range(low_v, &high_key, &mut art_scan_buffer, &guard);
-                    let bt_range: Vec<(&usize, &usize)> = bt_map.range(*low_v..high_key).collect();
+                    let range = match kind {
+                        RangeKind::Exclusive => {
+                            (Bound::Included(*low_v), Bound::Excluded(high_key))
+                        }
+                        RangeKind::Inclusive => {
+                            (Bound::Included(*low_v), Bound::Included(high_key))
+                        }
+                        RangeKind::From => (Bound::Included(*low_v), Bound::Unbounded),
+                        RangeKind::To => (Bound::Unbounded, Bound::Excluded(high_key)),
+                        RangeKind::Full => (Bound::Unbounded, Bound::Unbounded),
+                    };
+
+                    let (art_range, bt_range): (usize, Vec<(&usize, &usize)>) = if *rev {
+                        let art_range = art.range_rev(range, &mut art_scan_buffer, &guard);
+                        let bt_range = bt_map.range(range).rev();
+                        (art_range, bt_range.take(art_scan_buffer.len()).collect())
+                    } else {
+                        let art_range = art.range(range, &mut art_scan_buffer, &guard);
+                        let bt_range = bt_map.range(range);
+                        (art_range, bt_range.take(art_scan_buffer.len()).collect())
+                    };
 
                     assert_eq!(bt_range.len(), art_range);
 
@@ -0,0 +1,187 @@
+use std::{marker::PhantomData, ops::RangeBounds};
+
+use crate::{
+    bytes_tree::BytesTree, epoch, error::OOMError, key::BytesKey, Allocator, BackoffPolicy,
+    DefaultAllocator,
+};
+
+/// The adaptive radix tree with variable length byte-string keys.
+///
+/// Keys are ordered byte-wise, a key that is a prefix of another key is ordered before it.
+/// Compared to [Art](crate::Art), every key is stored in a separately allocated leaf.
+pub struct ArtBytes<V: Clone + From<usize>, A: Allocator + Clone + 'static = DefaultAllocator>
+where
+    usize: From<V>,
+{
+    inner: BytesTree<BytesKey, A>,
+    pt_val: PhantomData<V>,
+}
+
+impl<V: Clone + From<usize>> Default for ArtBytes<V>
+where
+    usize: From<V>,
+{
+    fn default() -> Self {
+        Self::new(DefaultAllocator {})
+    }
+}
+
+impl<V: Clone + From<usize>, A: Allocator + Clone + Send> ArtBytes<V, A>
+where
+    usize: From<V>,
+{
+    /// Create an empty [ArtBytes] tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtBytes;
+    /// let tree = ArtBytes::<usize>::default();
+    /// ```
+    #[inline]
+    pub fn new(allocator: A) -> Self {
+        Self::with_backoff(allocator, BackoffPolicy::default())
+    }
+
+    /// Create an empty [ArtBytes] tree, whose operations wait with `backoff` before they restart after a conflict
+    /// with a concurrent writer. [ArtBytes::new] uses [BackoffPolicy::Spin].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{ArtBytes, BackoffPolicy, DefaultAllocator};
+    /// let tree = ArtBytes::<usize>::with_backoff(DefaultAllocator {}, BackoffPolicy::SpinThenYield);
+    /// let guard = tree.pin();
+    /// tree.insert(b"hello", 42, &guard).unwrap();
+    /// assert_eq!(tree.get(b"hello", &guard), Some(42));
+    /// ```
+    #[inline]
+    pub fn with_backoff(allocator: A, backoff: BackoffPolicy) -> Self {
+        ArtBytes {
+            inner: BytesTree::with_backoff(allocator, backoff),
+            pt_val: PhantomData,
+        }
+    }
+
+    /// Enters an epoch.
+    /// Note: this can be expensive, try to reuse it.
+    #[inline]
+    pub fn pin(&self) -> epoch::Guard {
+        crossbeam_epoch::pin()
+    }
+
+    /// Returns a copy of the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtBytes;
+    /// let tree = ArtBytes::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(b"hello", 42, &guard).unwrap();
+    /// assert_eq!(tree.get(b"hello", &guard), Some(42));
+    /// assert_eq!(tree.get(b"hell", &guard), None);
+    /// ```
+    #[inline]
+    pub fn get(&self, key: &[u8], guard: &epoch::Guard) -> Option<V> {
+        let v = self.inner.get(&BytesKey::new(key), guard)?;
+        Some(V::from(v))
+    }
+
+    /// Insert a key-value pair to the tree, returns the previous value if the key was already present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtBytes;
+    /// let tree = ArtBytes::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(b"a", 1, &guard).unwrap();
+    /// tree.insert(b"ab", 2, &guard).unwrap();
+    /// let old = tree.insert(b"a", 3, &guard).unwrap();
+    /// assert_eq!(old, Some(1));
+    /// assert_eq!(tree.get(b"ab", &guard), Some(2));
+    /// ```
+    #[inline]
+    pub fn insert(&self, key: &[u8], v: V, guard: &epoch::Guard) -> Result<Option<V>, OOMError> {
+        let old = self
+            .inner
+            .insert(&BytesKey::new(key), usize::from(v), guard)?;
+        Ok(old.map(|v| V::from(v)))
+    }
+
+    /// Removes key-value pair from the tree, returns the value if the key was found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtBytes;
+    /// let tree = ArtBytes::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(b"hello", 42, &guard).unwrap();
+    /// assert_eq!(tree.remove(b"hello", &guard), Some(42));
+    /// assert!(tree.get(b"hello", &guard).is_none());
+    /// ```
+    #[inline]
+    pub fn remove(&self, key: &[u8], guard: &epoch::Guard) -> Option<V> {
+        let (old, new) =
+            self.inner
+                .compute_if_present(&BytesKey::new(key), &mut |_v| None, guard)?;
+        debug_assert!(new.is_none());
+        Some(V::from(old))
+    }
+
+    /// Scan the tree with the key `range`, write the result to the `result` buffer in ascending key order.
+    /// It scans the length of `result` or the number of the keys within the range, whichever is smaller;
+    /// returns the number of the keys scanned.
+    ///
+    /// The range has the same meaning as in [Art::range](crate::Art::range), keys are compared byte-wise.
+    /// As a key can be arbitrarily long, e.g., `b"\xff\xff\xff"`, use an unbounded end such as `a..` or `..`
+    /// to reach the largest keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtBytes;
+    /// let tree = ArtBytes::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(b"apple", 1, &guard).unwrap();
+    /// tree.insert(b"app", 2, &guard).unwrap();
+    /// tree.insert(b"banana", 3, &guard).unwrap();
+    /// tree.insert(b"\xff\xff", 4, &guard).unwrap();
+    ///
+    /// let mut result = vec![(Vec::new(), 0); 4];
+    /// let scanned = tree.range(&b"a"[..]..&b"b"[..], &mut result, &guard);
+    /// assert_eq!(scanned, 2);
+    /// assert_eq!(result[0], (b"app".to_vec(), 2));
+    /// assert_eq!(result[1], (b"apple".to_vec(), 1));
+    ///
+    /// let scanned = tree.range(&b"apple"[..]..=&b"banana"[..], &mut result, &guard);
+    /// assert_eq!(scanned, 2);
+    ///
+    /// let scanned = tree.range(&b"b"[..].., &mut result, &guard);
+    /// assert_eq!(&result[..scanned], &[(b"banana".to_vec(), 3), (b"\xff\xff".to_vec(), 4)]);
+    /// assert_eq!(tree.range(.., &mut result, &guard), 4);
+    /// ```
+    #[inline]
+    pub fn range<'a>(
+        &self,
+        range: impl RangeBounds<&'a [u8]>,
+        result: &mut [(Vec<u8>, V)],
+        guard: &epoch::Guard,
+    ) -> usize {
+        self.inner.range(
+            &BytesKey::range(&range),
+            result,
+            &mut |slot, key, value| {
+                BytesKey::decode(key, &mut slot.0);
+                slot.1 = V::from(value);
+            },
+            guard,
+        )
+    }
+}
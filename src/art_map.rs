@@ -0,0 +1,244 @@
+use std::{marker::PhantomData, ops::RangeBounds};
+
+use crate::{
+    epoch, error::OOMError, key::FixedKey, tree::RawTree, Allocator, ArtKey, DefaultAllocator,
+};
+
+/// The adaptive radix tree with arbitrary owned values.
+///
+/// Every value is boxed and the leaf stores the pointer to it.
+/// Values returned from the tree are borrowed from both the tree and the [epoch::Guard]: a replaced or removed value
+/// is only dropped after every guard that may observe it is gone, the values left in the tree are dropped with it.
+///
+/// Keys are the same as [Art](crate::Art), see [ArtKey].
+pub struct ArtMap<
+    K: ArtKey,
+    V: Send + Sync + 'static,
+    A: Allocator + Clone + 'static = DefaultAllocator,
+    const K_LEN: usize = 8,
+> {
+    inner: RawTree<FixedKey<K_LEN>, usize, A, K_LEN>,
+    pt_key: PhantomData<K>,
+    pt_val: PhantomData<V>,
+}
+
+impl<K: ArtKey, V: Send + Sync + 'static> Default for ArtMap<K, V> {
+    fn default() -> Self {
+        Self::new(DefaultAllocator {})
+    }
+}
+
+impl<K: ArtKey, V: Send + Sync + 'static, A: Allocator + Clone + 'static, const K_LEN: usize> Drop
+    for ArtMap<K, V, A, K_LEN>
+{
+    fn drop(&mut self) {
+        self.inner
+            .for_each_value(|v| unsafe { drop(Box::from_raw(v as *mut V)) });
+    }
+}
+
+impl<K: ArtKey, V: Send + Sync + 'static, A: Allocator + Clone + Send, const K_LEN: usize>
+    ArtMap<K, V, A, K_LEN>
+{
+    /// Create an empty [ArtMap] tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtMap;
+    /// let tree = ArtMap::<usize, String>::default();
+    /// ```
+    #[inline]
+    pub fn new(allocator: A) -> Self {
+        const {
+            assert!(
+                K_LEN > 0 && K::LEN <= K_LEN,
+                "the key is longer than K_LEN bytes"
+            )
+        };
+        ArtMap {
+            inner: RawTree::new(allocator),
+            pt_key: PhantomData,
+            pt_val: PhantomData,
+        }
+    }
+
+    /// Enters an epoch.
+    /// Note: this can be expensive, try to reuse it.
+    #[inline]
+    pub fn pin(&self) -> epoch::Guard {
+        crossbeam_epoch::pin()
+    }
+
+    /// Returns a reference to the value corresponding to the key, it is valid as long as both the tree and the `guard`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtMap;
+    /// let tree = ArtMap::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, "hello".to_string(), &guard).unwrap();
+    /// assert_eq!(tree.get(&1, &guard).unwrap(), "hello");
+    /// ```
+    ///
+    /// Dropping the tree drops its values, so a reference cannot outlive it:
+    ///
+    /// ```compile_fail
+    /// use congee::ArtMap;
+    /// let tree = ArtMap::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, "hello".to_string(), &guard).unwrap();
+    /// let value = tree.get(&1, &guard).unwrap();
+    /// drop(tree);
+    /// println!("{value}");
+    /// ```
+    #[inline]
+    pub fn get<'g>(&'g self, key: &K, guard: &'g epoch::Guard) -> Option<&'g V> {
+        let v = self.inner.get(&FixedKey::new(key), guard)?;
+        Some(unsafe { Self::value_ref(v) })
+    }
+
+    /// Insert a key-value pair to the tree, returns the previous value if the key was already present.
+    ///
+    /// The previous value is dropped once the `guard` (and every other guard that may observe it) is released.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtMap;
+    /// let tree = ArtMap::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, vec![1, 2], &guard).unwrap();
+    /// let old = tree.insert(1, vec![3], &guard).unwrap();
+    /// assert_eq!(old, Some(&vec![1, 2]));
+    /// assert_eq!(tree.get(&1, &guard), Some(&vec![3]));
+    /// ```
+    #[inline]
+    pub fn insert<'g>(
+        &'g self,
+        k: K,
+        v: V,
+        guard: &'g epoch::Guard,
+    ) -> Result<Option<&'g V>, OOMError> {
+        let ptr = Box::into_raw(Box::new(v)) as usize;
+        match self.inner.insert(FixedKey::new(&k), ptr, guard) {
+            Ok(old) => Ok(old.map(|old| unsafe { Self::defer_drop_value(old, guard) })),
+            Err(e) => {
+                unsafe { drop(Box::from_raw(ptr as *mut V)) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the number of keys in the tree.
+    ///
+    /// It is exact if there are no concurrent updates, otherwise it may or may not count the concurrent ones.
+    /// The count is maintained on every insert and remove, calling it does not walk the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtMap;
+    /// let tree = ArtMap::<usize, String>::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert!(tree.is_empty());
+    /// tree.insert(1, "a".to_string(), &guard).unwrap();
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the tree contains no keys, see [ArtMap::len].
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A cheaper variant of [ArtMap::len], see [Art::approximate_len](crate::Art::approximate_len) for how far off it may be.
+    #[inline]
+    pub fn approximate_len(&self) -> usize {
+        self.inner.approximate_len()
+    }
+
+    /// Removes key-value pair from the tree, returns the value if the key was found.
+    ///
+    /// The value is dropped once the `guard` (and every other guard that may observe it) is released.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtMap;
+    /// let tree = ArtMap::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, "hello".to_string(), &guard).unwrap();
+    /// assert_eq!(tree.remove(&1, &guard).unwrap(), "hello");
+    /// assert!(tree.get(&1, &guard).is_none());
+    /// ```
+    #[inline]
+    pub fn remove<'g>(&'g self, k: &K, guard: &'g epoch::Guard) -> Option<&'g V> {
+        let (old, new) = self
+            .inner
+            .compute_if_present(&FixedKey::new(k), &mut |_v| None, guard)?;
+        debug_assert!(new.is_none());
+        Some(unsafe { Self::defer_drop_value(old, guard) })
+    }
+
+    /// Scan the tree with the key `range`, write the result to the `result` buffer in ascending key order.
+    /// It scans the length of `result` or the number of the keys within the range, whichever is smaller;
+    /// returns the number of the keys scanned. The range has the same meaning as in [Art::range](crate::Art::range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtMap;
+    /// let tree = ArtMap::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, "a", &guard).unwrap();
+    /// tree.insert(2, "b", &guard).unwrap();
+    ///
+    /// let mut result = vec![None; 4];
+    /// let scanned = tree.range(0..2, &mut result, &guard);
+    /// assert_eq!(scanned, 1);
+    /// assert_eq!(result[0], Some((1, &"a")));
+    /// ```
+    #[inline]
+    pub fn range<'g>(
+        &'g self,
+        range: impl RangeBounds<K>,
+        result: &mut [Option<(K, &'g V)>],
+        guard: &'g epoch::Guard,
+    ) -> usize {
+        self.inner.range(
+            &FixedKey::range(&range),
+            result,
+            &mut |slot, key, value| {
+                *slot = Some((FixedKey::<K_LEN>::decode_bytes(key), unsafe {
+                    Self::value_ref(value)
+                }))
+            },
+            guard,
+        )
+    }
+
+    /// # Safety
+    /// `v` must be a value pointer read from the tree while the guard of lifetime `'g` is pinned.
+    unsafe fn value_ref<'g>(v: usize) -> &'g V {
+        &*(v as *const V)
+    }
+
+    /// # Safety
+    /// `v` must be a value pointer that was just unlinked from the tree.
+    unsafe fn defer_drop_value(v: usize, guard: &epoch::Guard) -> &V {
+        guard.defer(move || drop(Box::from_raw(v as *mut V)));
+        Self::value_ref(v)
+    }
+}
@@ -0,0 +1,307 @@
+use std::{marker::PhantomData, ops::RangeBounds};
+
+use crate::{
+    epoch, error::OOMError, key::FixedKey, tree::RawTree, Allocator, ArtKey, DefaultAllocator,
+};
+
+/// The value of [ArtPair].
+type Pair = (u64, u64);
+
+/// The adaptive radix tree with `(u64, u64)` values, e.g., a row id and a version, or an offset and a length.
+///
+/// Unlike [Art](crate::Art), the values are 16 bytes: the last level nodes store them inline in wider slots,
+/// so they are neither packed into a `usize` nor allocated on the heap.
+/// All value comparisons, e.g., in [ArtPair::compare_exchange], are on the whole 128-bit value.
+///
+/// Keys are the same as [Art](crate::Art), see [ArtKey].
+pub struct ArtPair<
+    K: ArtKey,
+    A: Allocator + Clone + 'static = DefaultAllocator,
+    const K_LEN: usize = 8,
+> {
+    inner: RawTree<FixedKey<K_LEN>, Pair, A, K_LEN>,
+    pt_key: PhantomData<K>,
+}
+
+impl<K: ArtKey> Default for ArtPair<K> {
+    fn default() -> Self {
+        Self::new(DefaultAllocator {})
+    }
+}
+
+impl<K: ArtKey, A: Allocator + Clone + Send, const K_LEN: usize> ArtPair<K, A, K_LEN> {
+    /// Create an empty [ArtPair] tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::<usize>::default();
+    /// ```
+    #[inline]
+    pub fn new(allocator: A) -> Self {
+        const {
+            assert!(
+                K_LEN > 0 && K::LEN <= K_LEN,
+                "the key is longer than K_LEN bytes"
+            )
+        };
+        ArtPair {
+            inner: RawTree::new(allocator),
+            pt_key: PhantomData,
+        }
+    }
+
+    /// Enters an epoch.
+    /// Note: this can be expensive, try to reuse it.
+    #[inline]
+    pub fn pin(&self) -> epoch::Guard {
+        crossbeam_epoch::pin()
+    }
+
+    /// Returns a copy of the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, (42, 7), &guard).unwrap();
+    /// assert_eq!(tree.get(&1, &guard), Some((42, 7)));
+    /// ```
+    #[inline]
+    pub fn get(&self, key: &K, guard: &epoch::Guard) -> Option<Pair> {
+        self.inner.get(&FixedKey::new(key), guard)
+    }
+
+    /// Insert a key-value pair to the tree, returns the previous value if the key was already present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, (42, 7), &guard).unwrap();
+    /// let old = tree.insert(1, (42, 8), &guard).unwrap();
+    /// assert_eq!(old, Some((42, 7)));
+    /// ```
+    #[inline]
+    pub fn insert(&self, k: K, v: Pair, guard: &epoch::Guard) -> Result<Option<Pair>, OOMError> {
+        self.inner.insert(FixedKey::new(&k), v, guard)
+    }
+
+    /// Returns the number of keys in the tree.
+    ///
+    /// It is exact if there are no concurrent updates, otherwise it may or may not count the concurrent ones.
+    /// The count is maintained on every insert and remove, calling it does not walk the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::<usize>::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert!(tree.is_empty());
+    /// tree.insert(1, (42, 7), &guard).unwrap();
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the tree contains no keys, see [ArtPair::len].
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A cheaper variant of [ArtPair::len], see [Art::approximate_len](crate::Art::approximate_len) for how far off it may be.
+    #[inline]
+    pub fn approximate_len(&self) -> usize {
+        self.inner.approximate_len()
+    }
+
+    /// Removes key-value pair from the tree, returns the value if the key was found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, (42, 7), &guard).unwrap();
+    /// assert_eq!(tree.remove(&1, &guard), Some((42, 7)));
+    /// assert!(tree.get(&1, &guard).is_none());
+    /// ```
+    #[inline]
+    pub fn remove(&self, k: &K, guard: &epoch::Guard) -> Option<Pair> {
+        let (old, new) = self
+            .inner
+            .compute_if_present(&FixedKey::new(k), &mut |_v| None, guard)?;
+        debug_assert!(new.is_none());
+        Some(old)
+    }
+
+    /// Scan the tree with the key `range`, write the result to the `result` buffer in ascending key order.
+    /// It scans the length of `result` or the number of the keys within the range, whichever is smaller;
+    /// returns the number of the keys scanned. The range has the same meaning as in [Art::range](crate::Art::range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, (42, 7), &guard).unwrap();
+    ///
+    /// let mut result = [(0, (0, 0)); 2];
+    /// let scanned = tree.range(1..=1, &mut result, &guard);
+    /// assert_eq!(scanned, 1);
+    /// assert_eq!(result[0], (1, (42, 7)));
+    /// ```
+    #[inline]
+    pub fn range(
+        &self,
+        range: impl RangeBounds<K>,
+        result: &mut [(K, Pair)],
+        guard: &epoch::Guard,
+    ) -> usize {
+        self.inner.range(
+            &FixedKey::range(&range),
+            result,
+            &mut |slot, key, value| *slot = (FixedKey::<K_LEN>::decode_bytes(key), value),
+            guard,
+        )
+    }
+
+    /// Compute and update the value if the key presents in the tree.
+    /// Returns the (old, new) value
+    ///
+    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
+    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, (42, 7), &guard).unwrap();
+    /// let old = tree.compute_if_present(&1, |(id, ver)| Some((id, ver + 1)), &guard).unwrap();
+    /// assert_eq!(old, ((42, 7), Some((42, 8))));
+    /// ```
+    #[inline]
+    pub fn compute_if_present<F>(
+        &self,
+        key: &K,
+        mut f: F,
+        guard: &epoch::Guard,
+    ) -> Option<(Pair, Option<Pair>)>
+    where
+        F: FnMut(Pair) -> Option<Pair>,
+    {
+        self.inner
+            .compute_if_present(&FixedKey::new(key), &mut f, guard)
+    }
+
+    /// Compute or insert the value if the key is not in the tree.
+    /// Returns the Option(old) value
+    ///
+    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
+    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::default();
+    /// let guard = tree.pin();
+    ///
+    /// let bump = |v: Option<(u64, u64)>| v.map_or((42, 0), |(id, ver)| (id, ver + 1));
+    /// assert_eq!(tree.compute_or_insert(1, bump, &guard).unwrap(), None);
+    /// assert_eq!(tree.compute_or_insert(1, bump, &guard).unwrap(), Some((42, 0)));
+    /// assert_eq!(tree.get(&1, &guard), Some((42, 1)));
+    /// ```
+    pub fn compute_or_insert<F>(
+        &self,
+        key: K,
+        mut f: F,
+        guard: &epoch::Guard,
+    ) -> Result<Option<Pair>, OOMError>
+    where
+        F: FnMut(Option<Pair>) -> Pair,
+    {
+        self.inner
+            .compute_or_insert(FixedKey::new(&key), &mut f, guard)
+    }
+
+    /// Get a random value from the tree, perform the transformation `f`.
+    /// This is useful for randomized algorithms.
+    ///
+    /// Returns (key, old_value, new_value)
+    ///
+    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
+    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    #[cfg(feature = "db_extension")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "db_extension")))]
+    pub fn compute_on_random(
+        &self,
+        rng: &mut impl rand::Rng,
+        mut f: impl FnMut(K, Pair) -> Pair,
+        guard: &epoch::Guard,
+    ) -> Option<(K, Pair, Pair)> {
+        let mut remapped = |key: &[u8], value| f(FixedKey::<K_LEN>::decode_bytes(key), value);
+        let (key, old_v, new_v) = self.inner.compute_on_random(rng, &mut remapped, guard)?;
+        Some((
+            FixedKey::<K_LEN>::decode_bytes(key.as_bytes()),
+            old_v,
+            new_v,
+        ))
+    }
+
+    /// Update the value if the current value equals to `old`, all 128 bits are compared.
+    /// `None` as the new value removes the key.
+    /// Returns the new value on success, otherwise the current value.
+    ///
+    /// # Examples:
+    /// ```
+    /// use congee::ArtPair;
+    /// let tree = ArtPair::default();
+    /// let guard = tree.pin();
+    /// tree.insert(1, (42, 7), &guard).unwrap();
+    ///
+    /// assert_eq!(tree.compare_exchange(&1, &(42, 6), Some((42, 8)), &guard), Err(Some((42, 7))));
+    /// assert_eq!(tree.compare_exchange(&1, &(42, 7), Some((42, 8)), &guard), Ok(Some((42, 8))));
+    /// ```
+    pub fn compare_exchange(
+        &self,
+        key: &K,
+        old: &Pair,
+        new: Option<Pair>,
+        guard: &epoch::Guard,
+    ) -> Result<Option<Pair>, Option<Pair>> {
+        let mut fc = |v: Pair| if v == *old { new } else { Some(v) };
+        match self
+            .inner
+            .compute_if_present(&FixedKey::new(key), &mut fc, guard)
+        {
+            Some((actual_old, actual_new)) => {
+                if actual_old == *old && actual_new == new {
+                    Ok(new)
+                } else {
+                    Err(actual_new)
+                }
+            }
+            None => Err(None),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::{marker::PhantomData, ops::RangeBounds};
+
+use crate::{
+    epoch, error::OOMError, key::FixedKey, range_iter::RangeIter, tree::RawTree, Allocator, ArtKey,
+    DefaultAllocator,
+};
+
+/// The adaptive radix tree as an ordered set.
+///
+/// There are no values, so the last key byte of every key is only a bit in a mask, like the mask of `Node256`:
+/// a last level node takes 56 bytes for up to 256 keys.
+///
+/// Keys are the same as [Art](crate::Art), see [ArtKey].
+pub struct ArtSet<
+    K: ArtKey,
+    A: Allocator + Clone + 'static = DefaultAllocator,
+    const K_LEN: usize = 8,
+> {
+    inner: RawTree<FixedKey<K_LEN>, (), A, K_LEN>,
+    pt_key: PhantomData<K>,
+}
+
+impl<K: ArtKey> Default for ArtSet<K> {
+    fn default() -> Self {
+        Self::new(DefaultAllocator {})
+    }
+}
+
+impl<K: ArtKey, A: Allocator + Clone + Send, const K_LEN: usize> ArtSet<K, A, K_LEN> {
+    /// Create an empty [ArtSet].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::<usize>::default();
+    /// ```
+    #[inline]
+    pub fn new(allocator: A) -> Self {
+        const {
+            assert!(
+                K_LEN > 0 && K::LEN <= K_LEN,
+                "the key is longer than K_LEN bytes"
+            )
+        };
+        ArtSet {
+            inner: RawTree::new(allocator),
+            pt_key: PhantomData,
+        }
+    }
+
+    /// Enters an epoch.
+    /// Note: this can be expensive, try to reuse it.
+    #[inline]
+    pub fn pin(&self) -> epoch::Guard {
+        crossbeam_epoch::pin()
+    }
+
+    /// Adds a key to the set, returns whether the key was newly inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::default();
+    /// let guard = set.pin();
+    ///
+    /// assert!(set.insert(1, &guard).unwrap());
+    /// assert!(!set.insert(1, &guard).unwrap());
+    /// ```
+    #[inline]
+    pub fn insert(&self, k: K, guard: &epoch::Guard) -> Result<bool, OOMError> {
+        let old = self.inner.insert(FixedKey::new(&k), (), guard)?;
+        Ok(old.is_none())
+    }
+
+    /// Returns whether the set contains the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::default();
+    /// let guard = set.pin();
+    ///
+    /// set.insert(1, &guard).unwrap();
+    /// assert!(set.contains(&1, &guard));
+    /// assert!(!set.contains(&2, &guard));
+    /// ```
+    #[inline]
+    pub fn contains(&self, k: &K, guard: &epoch::Guard) -> bool {
+        self.inner.get(&FixedKey::new(k), guard).is_some()
+    }
+
+    /// Returns the number of keys in the set.
+    ///
+    /// It is exact if there are no concurrent updates, otherwise it may or may not count the concurrent ones.
+    /// The count is maintained on every insert and remove, calling it does not walk the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::<usize>::default();
+    /// let guard = set.pin();
+    ///
+    /// assert!(set.is_empty());
+    /// set.insert(1, &guard).unwrap();
+    /// assert_eq!(set.len(), 1);
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the set contains no keys, see [ArtSet::len].
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A cheaper variant of [ArtSet::len], see [Art::approximate_len](crate::Art::approximate_len) for how far off it may be.
+    #[inline]
+    pub fn approximate_len(&self) -> usize {
+        self.inner.approximate_len()
+    }
+
+    /// Removes a key from the set, returns whether the key was present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::default();
+    /// let guard = set.pin();
+    ///
+    /// set.insert(1, &guard).unwrap();
+    /// assert!(set.remove(&1, &guard));
+    /// assert!(!set.remove(&1, &guard));
+    /// ```
+    #[inline]
+    pub fn remove(&self, k: &K, guard: &epoch::Guard) -> bool {
+        self.inner
+            .compute_if_present(&FixedKey::new(k), &mut |_v| None, guard)
+            .is_some()
+    }
+
+    /// Scan the set with the key `range`, write the keys to the `result` buffer in ascending order.
+    /// It scans the length of `result` or the number of the keys within the range, whichever is smaller;
+    /// returns the number of the keys scanned. The range has the same meaning as in [Art::range](crate::Art::range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::default();
+    /// let guard = set.pin();
+    ///
+    /// set.insert(1, &guard).unwrap();
+    /// set.insert(3, &guard).unwrap();
+    ///
+    /// let mut result = [0; 4];
+    /// let scanned = set.range(0..3, &mut result, &guard);
+    /// assert_eq!(&result[..scanned], &[1]);
+    /// ```
+    #[inline]
+    pub fn range(
+        &self,
+        range: impl RangeBounds<K>,
+        result: &mut [K],
+        guard: &epoch::Guard,
+    ) -> usize {
+        self.inner.range(
+            &FixedKey::range(&range),
+            result,
+            &mut |slot, key, _| *slot = FixedKey::<K_LEN>::decode_bytes(key),
+            guard,
+        )
+    }
+
+    /// Iterates over all keys of the set in ascending order, see [ArtSet::range_iter].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::default();
+    /// let guard = set.pin();
+    ///
+    /// for k in [3, 1, 2] {
+    ///     set.insert(k, &guard).unwrap();
+    /// }
+    /// assert_eq!(set.iter(&guard).collect::<Vec<_>>(), vec![1, 2, 3]);
+    /// ```
+    pub fn iter<'a>(&'a self, guard: &'a epoch::Guard) -> impl Iterator<Item = K> + 'a {
+        RangeIter::new(&self.inner, FixedKey::range::<K>(&(..)), false, guard)
+            .map(|(k, _)| k.decode())
+    }
+
+    /// Iterates over all keys of the set in descending order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::default();
+    /// let guard = set.pin();
+    ///
+    /// for k in [3, 1, 2] {
+    ///     set.insert(k, &guard).unwrap();
+    /// }
+    /// assert_eq!(set.iter_rev(&guard).collect::<Vec<_>>(), vec![3, 2, 1]);
+    /// ```
+    pub fn iter_rev<'a>(&'a self, guard: &'a epoch::Guard) -> impl Iterator<Item = K> + 'a {
+        RangeIter::new(&self.inner, FixedKey::range::<K>(&(..)), true, guard)
+            .map(|(k, _)| k.decode())
+    }
+
+    /// Iterates over the keys within `range` in ascending order, see [ArtSet::range] for the meaning of the range.
+    ///
+    /// Keys are scanned lazily in batches, a key inserted or removed during the iteration may or may not be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::ArtSet;
+    /// let set = ArtSet::default();
+    /// let guard = set.pin();
+    ///
+    /// for k in 0..100 {
+    ///     set.insert(k, &guard).unwrap();
+    /// }
+    /// assert!(set.range_iter(10..20, &guard).eq(10..20));
+    /// ```
+    pub fn range_iter<'a>(
+        &'a self,
+        range: impl RangeBounds<K>,
+        guard: &'a epoch::Guard,
+    ) -> impl Iterator<Item = K> + 'a {
+        RangeIter::new(&self.inner, FixedKey::range(&range), false, guard).map(|(k, _)| k.decode())
+    }
+}
@@ -16,8 +16,12 @@ use crate::{
     Allocator,
 };
 
-pub(crate) const MAX_KEY_LEN: usize = 8;
-pub(crate) type Prefix = [u8; MAX_KEY_LEN];
+/// Number of prefix bytes stored inline in a node.
+/// The prefix is relative to the parent node, so it does not depend on the key length.
+/// In the byte tree, compressed paths longer than this are only partially stored, the rest is checked against the leaf;
+/// the fixed length tree splits them into a chain of nodes instead.
+pub(crate) const MAX_PREFIX_LEN: usize = 8;
+pub(crate) type Prefix = [u8; MAX_PREFIX_LEN];
 
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -29,56 +33,81 @@ pub(crate) enum NodeType {
 }
 
 impl NodeType {
-    pub(crate) fn node_layout(&self) -> std::alloc::Layout {
+    /// Layout of the node with `S` as its slot type.
+    pub(crate) fn node_layout<S: Copy>(&self) -> std::alloc::Layout {
         match *self {
-            NodeType::N4 => std::alloc::Layout::from_size_align(
-                std::mem::size_of::<Node4>(),
-                std::mem::align_of::<Node4>(),
-            )
-            .unwrap(),
-            NodeType::N16 => std::alloc::Layout::from_size_align(
-                std::mem::size_of::<Node16>(),
-                std::mem::align_of::<Node16>(),
-            )
-            .unwrap(),
-            NodeType::N48 => std::alloc::Layout::from_size_align(
-                std::mem::size_of::<Node48>(),
-                std::mem::align_of::<Node48>(),
-            )
-            .unwrap(),
-            NodeType::N256 => std::alloc::Layout::from_size_align(
-                std::mem::size_of::<Node256>(),
-                std::mem::align_of::<Node256>(),
-            )
-            .unwrap(),
+            NodeType::N4 => std::alloc::Layout::new::<Node4<S>>(),
+            NodeType::N16 => std::alloc::Layout::new::<Node16<S>>(),
+            NodeType::N48 => std::alloc::Layout::new::<Node48<S>>(),
+            NodeType::N256 => std::alloc::Layout::new::<Node256<S>>(),
+        }
+    }
+
+    /// A node of this type holding at most this many slots shrinks to a smaller type.
+    /// It is below the capacity of the smaller type, so that alternating inserts and removes
+    /// do not grow and shrink the node back and forth.
+    pub(crate) fn shrink_threshold(&self) -> usize {
+        match *self {
+            NodeType::N4 => 0,
+            NodeType::N16 => 3,
+            NodeType::N48 => 12,
+            NodeType::N256 => 37,
+        }
+    }
+
+    /// The number of slots a node of this type holds.
+    pub(crate) fn capacity(&self) -> usize {
+        match *self {
+            NodeType::N4 => 4,
+            NodeType::N16 => 16,
+            NodeType::N48 => 48,
+            NodeType::N256 => 256,
+        }
+    }
+
+    /// The smallest node type that holds `count` slots.
+    pub(crate) fn fitting(count: usize) -> NodeType {
+        match count {
+            0..=4 => NodeType::N4,
+            5..=16 => NodeType::N16,
+            17..=48 => NodeType::N48,
+            _ => NodeType::N256,
         }
     }
 }
 
+/// A node maps key bytes to slots: child pointers for inner nodes, values for the last level nodes.
 pub(crate) trait Node {
+    type Slot: Copy;
+
     fn base(&self) -> &BaseNode;
     fn is_full(&self) -> bool;
-    fn insert(&mut self, key: u8, node: NodePtr);
-    fn change(&mut self, key: u8, val: NodePtr) -> NodePtr;
-    fn get_child(&self, key: u8) -> Option<NodePtr>;
-    fn get_children(&self, start: u8, end: u8) -> NodeIter<'_>;
+    /// Whether the node shrinks to the next smaller type once a slot is removed,
+    /// see [NodeType::shrink_threshold].
+    fn is_underfull(&self) -> bool {
+        self.base().get_count() <= Self::get_type().shrink_threshold() + 1
+    }
+    fn insert(&mut self, key: u8, node: Self::Slot);
+    fn change(&mut self, key: u8, val: Self::Slot) -> Self::Slot;
+    fn get_child(&self, key: u8) -> Option<Self::Slot>;
+    fn get_children(&self, start: u8, end: u8) -> NodeIter<'_, Self::Slot>;
     fn remove(&mut self, k: u8);
-    fn copy_to<N: Node>(&self, dst: &mut N);
+    fn copy_to<N: Node<Slot = Self::Slot>>(&self, dst: &mut N);
     fn get_type() -> NodeType;
 
     #[cfg(feature = "db_extension")]
-    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, NodePtr)>;
+    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, Self::Slot)>;
 }
 
-pub(crate) enum NodeIter<'a> {
-    N4(Node4Iter<'a>),
-    N16(Node16Iter<'a>),
-    N48(Node48Iter<'a>),
-    N256(Node256Iter<'a>),
+pub(crate) enum NodeIter<'a, S = NodePtr> {
+    N4(Node4Iter<'a, S>),
+    N16(Node16Iter<'a, S>),
+    N48(Node48Iter<'a, S>),
+    N256(Node256Iter<'a, S>),
 }
 
-impl Iterator for NodeIter<'_> {
-    type Item = (u8, NodePtr);
+impl<S: Copy> Iterator for NodeIter<'_, S> {
+    type Item = (u8, S);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
@@ -90,49 +119,103 @@ impl Iterator for NodeIter<'_> {
     }
 }
 
+impl<S: Copy> DoubleEndedIterator for NodeIter<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            NodeIter::N4(iter) => iter.next_back(),
+            NodeIter::N16(iter) => iter.next_back(),
+            NodeIter::N48(iter) => iter.next_back(),
+            NodeIter::N256(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<'a, S: Copy> NodeIter<'a, S> {
+    /// Iterates in descending key order if `reverse`.
+    pub(crate) fn directed(self, reverse: bool) -> DirectedIter<NodeIter<'a, S>> {
+        DirectedIter {
+            iter: self,
+            reverse,
+        }
+    }
+}
+
+pub(crate) struct DirectedIter<I> {
+    iter: I,
+    reverse: bool,
+}
+
+impl<I: DoubleEndedIterator> Iterator for DirectedIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reverse {
+            self.iter.next_back()
+        } else {
+            self.iter.next()
+        }
+    }
+}
+
 #[repr(C)]
 pub(crate) struct BaseNode {
     // 2b type | 60b version | 1b lock | 1b obsolete
     pub(crate) type_version_lock_obsolete: AtomicUsize,
+    /// The number of values in the subtree, [BaseNode::MOVED] once the node is replaced by a copy.
+    #[cfg(feature = "order_statistics")]
+    keys: AtomicUsize,
     pub(crate) meta: NodeMeta,
 }
 
 pub(crate) struct NodeMeta {
+    // length of the compressed path, can be larger than the stored prefix
     prefix_cnt: u32,
     pub(crate) count: u16,
     node_type: NodeType,
     prefix: Prefix,
 }
 
+impl NodeMeta {
+    fn set_prefix(&mut self, prefix: &[u8], prefix_len: usize) {
+        let stored = std::cmp::min(prefix_len, MAX_PREFIX_LEN);
+        self.prefix[..stored].copy_from_slice(&prefix[..stored]);
+        self.prefix_cnt = prefix_len as u32;
+    }
+}
+
 #[cfg(not(feature = "shuttle"))]
 mod layout_assertion {
     use super::*;
     const _: () = assert!(std::mem::size_of::<NodeMeta>() == 16);
     const _: () = assert!(std::mem::align_of::<NodeMeta>() == 4);
+    #[cfg(not(feature = "order_statistics"))]
     const _: () = assert!(std::mem::size_of::<BaseNode>() == 24);
+    #[cfg(feature = "order_statistics")]
+    const _: () = assert!(std::mem::size_of::<BaseNode>() == 32);
     const _: () = assert!(std::mem::align_of::<BaseNode>() == 8);
 }
 
+// Generates a method that dispatches to the concrete node type, `S` is the slot type of the node.
 macro_rules! gen_method {
-    ($method_name:ident, ($($arg_n:ident : $args:ty),*), $return:ty) => {
+    ($method_name:ident, $node_method:ident, ($($arg_n:ident : $args:ty),*), $return:ty) => {
         impl BaseNode {
-            pub(crate) fn $method_name(&self, $($arg_n : $args),*) -> $return {
+            pub(crate) fn $method_name<S: Copy>(&self, $($arg_n : $args),*) -> $return {
                 match self.get_type() {
                     NodeType::N4 => {
-                        let node = unsafe{&* (self as *const BaseNode as *const Node4)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&* (self as *const BaseNode as *const Node4<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                     NodeType::N16 => {
-                        let node = unsafe{&* (self as *const BaseNode as *const Node16)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&* (self as *const BaseNode as *const Node16<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                     NodeType::N48 => {
-                        let node = unsafe{&* (self as *const BaseNode as *const Node48)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&* (self as *const BaseNode as *const Node48<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                     NodeType::N256 => {
-                        let node = unsafe{&* (self as *const BaseNode as *const Node256)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&* (self as *const BaseNode as *const Node256<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                 }
             }
@@ -141,25 +224,25 @@ macro_rules! gen_method {
 }
 
 macro_rules! gen_method_mut {
-    ($method_name:ident, ($($arg_n:ident : $args:ty),*), $return:ty) => {
+    ($method_name:ident, $node_method:ident, ($($arg_n:ident : $args:ty),*), $return:ty) => {
         impl BaseNode {
-            pub(crate) fn $method_name(&mut self, $($arg_n : $args),*) -> $return {
+            pub(crate) fn $method_name<S: Copy>(&mut self, $($arg_n : $args),*) -> $return {
                 match self.get_type() {
                     NodeType::N4 => {
-                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node4)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node4<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                     NodeType::N16 => {
-                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node16)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node16<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                     NodeType::N48 => {
-                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node48)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node48<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                     NodeType::N256 => {
-                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node256)};
-                        node.$method_name($($arg_n),*)
+                        let node = unsafe{&mut * (self as *mut BaseNode as *mut Node256<S>)};
+                        node.$node_method($($arg_n),*)
                     },
                 }
             }
@@ -167,36 +250,61 @@ macro_rules! gen_method_mut {
     };
 }
 
-gen_method!(get_child, (k: u8), Option<NodePtr>);
-gen_method!(get_children, (start: u8, end: u8), NodeIter<'_>);
+// The value methods access the slots of the last level nodes, the others access child pointers.
+gen_method!(get_value, get_child, (k: u8), Option<S>);
+gen_method!(get_values, get_children, (start: u8, end: u8), NodeIter<'_, S>);
 
 #[cfg(feature = "db_extension")]
 gen_method!(
+    get_random_value,
     get_random_child,
     (rng: &mut impl rand::Rng),
-    Option<(u8, NodePtr)>
+    Option<(u8, S)>
 );
-gen_method_mut!(change, (key: u8, val: NodePtr), NodePtr);
-gen_method_mut!(remove, (key: u8), ());
+gen_method_mut!(change_value, change, (key: u8, val: S), S);
+gen_method_mut!(insert_value, insert, (key: u8, val: S), ());
+gen_method_mut!(remove_value, remove, (key: u8), ());
 
 impl BaseNode {
-    pub(crate) fn new(n_type: NodeType, prefix: &[u8]) -> Self {
-        let mut prefix_v: [u8; MAX_KEY_LEN] = [0; MAX_KEY_LEN];
+    pub(crate) fn get_child(&self, k: u8) -> Option<NodePtr> {
+        self.get_value(k)
+    }
 
-        assert!(prefix.len() <= MAX_KEY_LEN);
-        for (i, v) in prefix.iter().enumerate() {
-            prefix_v[i] = *v;
-        }
+    pub(crate) fn get_children(&self, start: u8, end: u8) -> NodeIter<'_> {
+        self.get_values(start, end)
+    }
+
+    #[cfg(feature = "db_extension")]
+    pub(crate) fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, NodePtr)> {
+        self.get_random_value(rng)
+    }
+
+    pub(crate) fn change(&mut self, key: u8, val: NodePtr) -> NodePtr {
+        self.change_value(key, val)
+    }
+
+    pub(crate) fn remove(&mut self, key: u8) {
+        self.remove_value::<NodePtr>(key)
+    }
+}
+
+impl BaseNode {
+    #[cfg(feature = "order_statistics")]
+    const MOVED: usize = usize::MAX;
 
-        let meta = NodeMeta {
-            prefix_cnt: prefix.len() as u32,
+    pub(crate) fn new(n_type: NodeType, prefix: &[u8]) -> Self {
+        let mut meta = NodeMeta {
+            prefix_cnt: 0,
             count: 0,
-            prefix: prefix_v,
+            prefix: [0; MAX_PREFIX_LEN],
             node_type: n_type,
         };
+        meta.set_prefix(prefix, prefix.len());
 
         BaseNode {
             type_version_lock_obsolete: AtomicUsize::new(0),
+            #[cfg(feature = "order_statistics")]
+            keys: AtomicUsize::new(0),
             meta,
         }
     }
@@ -205,7 +313,7 @@ impl BaseNode {
         prefix: &[u8],
         allocator: &impl Allocator,
     ) -> Result<*mut N, ArtError> {
-        let layout = N::get_type().node_layout();
+        let layout = std::alloc::Layout::new::<N>();
         let ptr = allocator
             .allocate_zeroed(layout)
             .map_err(|_e| ArtError::Oom)?;
@@ -215,7 +323,7 @@ impl BaseNode {
             std::ptr::write(ptr, node);
 
             if matches!(N::get_type(), NodeType::N48) {
-                let mem = ptr as *mut Node48;
+                let mem = ptr as *mut Node48<N::Slot>;
                 (*mem).init_empty();
             }
 
@@ -225,7 +333,12 @@ impl BaseNode {
 
     /// Here we must get a clone of allocator because the drop_node might be called in epoch guard
     pub(crate) unsafe fn drop_node<A: Allocator>(node: *mut BaseNode, allocator: A) {
-        let layout = (*node).get_type().node_layout();
+        Self::drop_node_of::<NodePtr, A>(node, allocator);
+    }
+
+    /// Same as [BaseNode::drop_node], for a node with `S` as its slot type.
+    pub(crate) unsafe fn drop_node_of<S: Copy, A: Allocator>(node: *mut BaseNode, allocator: A) {
+        let layout = (*node).get_type().node_layout::<S>();
         let ptr = std::ptr::NonNull::new(node as *mut u8).unwrap();
         allocator.deallocate(ptr, layout);
     }
@@ -235,7 +348,7 @@ impl BaseNode {
     }
 
     #[inline]
-    pub(crate) fn read_lock(&self) -> Result<ReadGuard, ArtError> {
+    pub(crate) fn read_lock(&self) -> Result<ReadGuard<'_>, ArtError> {
         let version = self.type_version_lock_obsolete.load(Ordering::Acquire);
 
         // #[cfg(test)]
@@ -256,24 +369,96 @@ impl BaseNode {
         self.meta.count as usize
     }
 
+    /// The number of values in the subtree, `None` if the node was replaced by a copy that holds the count now.
+    #[cfg(feature = "order_statistics")]
+    pub(crate) fn keys(&self) -> Option<usize> {
+        let keys = self.keys.load(Ordering::Acquire);
+        (keys != Self::MOVED).then_some(keys)
+    }
+
+    /// Sets the number of values in the subtree of a node that no other thread can see yet.
+    #[inline]
+    pub(crate) fn set_keys(&self, _keys: usize) {
+        #[cfg(feature = "order_statistics")]
+        self.keys.store(_keys, Ordering::Release);
+    }
+
+    /// Adds `delta` to the number of values in the subtree of a node that this thread has write-locked.
+    #[inline]
+    pub(crate) fn add_keys(&self, _delta: isize) {
+        #[cfg(feature = "order_statistics")]
+        self.keys.fetch_add(_delta as usize, Ordering::AcqRel);
+    }
+
+    /// Adds `delta` to the number of values in the subtree of a node that other threads may update,
+    /// fails if the node was replaced by a copy.
+    ///
+    /// The count of a replaced node is moved to its copy in a single step, so the update either
+    /// reaches the copy that way or fails and has to be applied to the copy.
+    #[cfg(feature = "order_statistics")]
+    pub(crate) fn try_add_keys(&self, delta: isize) -> Result<(), ArtError> {
+        self.keys
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |keys| {
+                (keys != Self::MOVED).then_some(keys.wrapping_add(delta as usize))
+            })
+            .map(|_| ())
+            .map_err(|_| ArtError::VersionNotMatch)
+    }
+
+    /// Moves the number of values in the subtree to `copy`, which replaces this node, plus `delta`.
+    /// This node must be write-locked and `copy` not yet visible to other threads.
+    #[inline]
+    pub(crate) fn move_keys(&self, _copy: &BaseNode, _delta: isize) {
+        #[cfg(feature = "order_statistics")]
+        {
+            let keys = self.keys.swap(Self::MOVED, Ordering::AcqRel);
+            _copy.set_keys(keys.wrapping_add(_delta as usize));
+        }
+    }
+
+    /// The sum of the subtree counts of the children of a node that no other thread can change.
+    pub(crate) fn child_keys(&self) -> usize {
+        #[cfg(feature = "order_statistics")]
+        return self
+            .get_children(0, 255)
+            .map(|(_, c)| unsafe { &*c.as_ptr() }.keys().unwrap_or(0))
+            .sum();
+        #[cfg(not(feature = "order_statistics"))]
+        0
+    }
+
     fn is_obsolete(version: usize) -> bool {
         (version & 1) == 1
     }
 
+    /// The stored part of the compressed path, i.e., the key bytes between the parent and this node.
+    /// It is the full compressed path unless `prefix_len() > MAX_PREFIX_LEN`.
     pub(crate) fn prefix(&self) -> &[u8] {
-        unsafe {
-            self.meta
-                .prefix
-                .get_unchecked(..self.meta.prefix_cnt as usize)
-        }
+        let len = std::cmp::min(self.meta.prefix_cnt as usize, MAX_PREFIX_LEN);
+        unsafe { self.meta.prefix.get_unchecked(..len) }
+    }
+
+    /// The length of the compressed path.
+    pub(crate) fn prefix_len(&self) -> usize {
+        self.meta.prefix_cnt as usize
     }
 
-    pub(crate) fn insert_grow<CurT: Node, BiggerT: Node, A: Allocator + Send + Clone + 'static>(
+    /// Replaces the compressed path, `prefix` must hold at least `min(prefix_len, MAX_PREFIX_LEN)` bytes.
+    pub(crate) fn set_prefix(&mut self, prefix: &[u8], prefix_len: usize) {
+        self.meta.set_prefix(prefix, prefix_len);
+    }
+
+    pub(crate) fn insert_grow<
+        CurT: Node,
+        BiggerT: Node<Slot = CurT::Slot>,
+        A: Allocator + Send + Clone + 'static,
+    >(
         n: ConcreteReadGuard<CurT>,
         parent: (u8, Option<ReadGuard>),
-        val: (u8, NodePtr),
+        val: (u8, CurT::Slot),
         allocator: &A,
         guard: &Guard,
+        counted: impl FnOnce(&BaseNode),
     ) -> Result<(), ArtError> {
         if !n.as_ref().is_full() {
             if let Some(p) = parent.1 {
@@ -283,6 +468,8 @@ impl BaseNode {
             let mut write_n = n.upgrade().map_err(|v| v.1)?;
 
             write_n.as_mut().insert(val.0, val.1);
+            write_n.as_ref().base().add_keys(1);
+            counted(write_n.as_ref().base());
             return Ok(());
         }
 
@@ -294,59 +481,229 @@ impl BaseNode {
 
         let mut write_n = n.upgrade().map_err(|v| v.1)?;
 
-        let n_big = BaseNode::make_node::<BiggerT>(write_n.as_ref().base().prefix(), allocator)?;
+        let n_big = BaseNode::make_node::<BiggerT>(&[], allocator)?;
+        let old_base = write_n.as_ref().base();
+        unsafe { &mut *(n_big as *mut BaseNode) }
+            .set_prefix(old_base.prefix(), old_base.prefix_len());
         write_n.as_ref().copy_to(unsafe { &mut *n_big });
         unsafe { &mut *n_big }.insert(val.0, val.1);
+        old_base.move_keys(unsafe { &*(n_big as *const BaseNode) }, 1);
 
         write_p
             .as_mut()
             .change(parent.0, NodePtr::from_node(n_big as *mut BaseNode));
+        write_p.as_ref().add_keys(1);
+        counted(write_p.as_ref());
+
+        write_n.mark_obsolete();
+        let delete_n = write_n.as_mut() as *mut CurT as usize;
+        std::mem::forget(write_n);
+        let allocator: A = allocator.clone();
+        guard.defer(move || unsafe {
+            BaseNode::drop_node_of::<CurT::Slot, A>(delete_n as *mut BaseNode, allocator);
+        });
+        Ok(())
+    }
+
+    /// Creates a node of `n_type` with the prefix of `self`, holding the slots for which `keep` holds.
+    /// `S` is the slot type of `self`.
+    pub(crate) fn copy_children<S: Copy>(
+        &self,
+        n_type: NodeType,
+        mut keep: impl FnMut(u8) -> bool,
+        allocator: &impl Allocator,
+    ) -> Result<*mut BaseNode, ArtError> {
+        let new = Self::make_node_of(
+            n_type,
+            &[],
+            self.get_values::<S>(0, 255).filter(|(k, _)| keep(*k)),
+            allocator,
+        )?;
+        unsafe { &mut *new }.set_prefix(self.prefix(), self.prefix_len());
+        Ok(new)
+    }
+
+    /// Creates a node of `n_type` with `S` as its slot type, holding `slots`.
+    pub(crate) fn make_node_of<S: Copy>(
+        n_type: NodeType,
+        prefix: &[u8],
+        slots: impl IntoIterator<Item = (u8, S)>,
+        allocator: &impl Allocator,
+    ) -> Result<*mut BaseNode, ArtError> {
+        let new = match n_type {
+            NodeType::N4 => BaseNode::make_node::<Node4<S>>(prefix, allocator)? as *mut BaseNode,
+            NodeType::N16 => BaseNode::make_node::<Node16<S>>(prefix, allocator)? as *mut BaseNode,
+            NodeType::N48 => BaseNode::make_node::<Node48<S>>(prefix, allocator)? as *mut BaseNode,
+            NodeType::N256 => {
+                BaseNode::make_node::<Node256<S>>(prefix, allocator)? as *mut BaseNode
+            }
+        };
+        let new_node = unsafe { &mut *new };
+        for (k, slot) in slots {
+            new_node.insert_value(k, slot);
+        }
+        Ok(new)
+    }
+
+    pub(crate) fn remove_shrink<
+        CurT: Node,
+        SmallerT: Node<Slot = CurT::Slot>,
+        A: Allocator + Send + Clone + 'static,
+    >(
+        n: ConcreteReadGuard<CurT>,
+        parent: (u8, Option<ReadGuard>),
+        key: u8,
+        allocator: &A,
+        guard: &Guard,
+        counted: impl FnOnce(&BaseNode),
+    ) -> Result<(), ArtError> {
+        // the root has no parent to point to a smaller node, it is never replaced
+        let p = match parent.1 {
+            Some(p) if n.as_ref().is_underfull() => p,
+            p => {
+                if let Some(p) = p {
+                    p.unlock()?;
+                }
+
+                let mut write_n = n.upgrade().map_err(|v| v.1)?;
+
+                write_n.as_mut().remove(key);
+                write_n.as_ref().base().add_keys(-1);
+                counted(write_n.as_ref().base());
+                return Ok(());
+            }
+        };
+
+        let mut write_p = p.upgrade().map_err(|v| v.1)?;
+
+        let mut write_n = n.upgrade().map_err(|v| v.1)?;
+
+        let n_small = match BaseNode::make_node::<SmallerT>(&[], allocator) {
+            Ok(n_small) => n_small,
+            // shrinking is optional, without memory for the smaller node the key is removed in place;
+            // the parent is released first, the ancestors are counted without holding it
+            Err(_) => {
+                drop(write_p);
+                write_n.as_mut().remove(key);
+                write_n.as_ref().base().add_keys(-1);
+                counted(write_n.as_ref().base());
+                return Ok(());
+            }
+        };
+        let old_base = write_n.as_ref().base();
+        unsafe { &mut *(n_small as *mut BaseNode) }
+            .set_prefix(old_base.prefix(), old_base.prefix_len());
+        write_n.as_ref().copy_to(unsafe { &mut *n_small });
+        unsafe { &mut *n_small }.remove(key);
+        old_base.move_keys(unsafe { &*(n_small as *const BaseNode) }, -1);
+
+        write_p
+            .as_mut()
+            .change(parent.0, NodePtr::from_node(n_small as *mut BaseNode));
+        write_p.as_ref().add_keys(-1);
+        counted(write_p.as_ref());
 
         write_n.mark_obsolete();
         let delete_n = write_n.as_mut() as *mut CurT as usize;
         std::mem::forget(write_n);
         let allocator: A = allocator.clone();
         guard.defer(move || unsafe {
-            BaseNode::drop_node(delete_n as *mut BaseNode, allocator);
+            BaseNode::drop_node_of::<CurT::Slot, A>(delete_n as *mut BaseNode, allocator);
         });
         Ok(())
     }
 
-    pub(crate) fn insert_and_unlock<'a, A: Allocator + Send + Clone + 'static>(
+    /// Removes `key` from `node`, replacing the node with a smaller one if it becomes underfull.
+    ///
+    /// The subtree counts of the changed nodes drop by one, `counted` is called with the topmost of them
+    /// while they are still locked, to update the counts of its ancestors, see [RawTree::count_keys](crate::tree::RawTree::count_keys).
+    pub(crate) fn remove_and_unlock<S: Copy, A: Allocator + Send + Clone + 'static>(
+        node: ReadGuard,
+        parent: (u8, Option<ReadGuard>),
+        key: u8,
+        allocator: &A,
+        guard: &Guard,
+        counted: impl FnOnce(&BaseNode),
+    ) -> Result<(), ArtError> {
+        match node.as_ref().get_type() {
+            NodeType::N4 => Self::remove_shrink::<Node4<S>, Node4<S>, A>(
+                node.into_concrete(),
+                parent,
+                key,
+                allocator,
+                guard,
+                counted,
+            ),
+            NodeType::N16 => Self::remove_shrink::<Node16<S>, Node4<S>, A>(
+                node.into_concrete(),
+                parent,
+                key,
+                allocator,
+                guard,
+                counted,
+            ),
+            NodeType::N48 => Self::remove_shrink::<Node48<S>, Node16<S>, A>(
+                node.into_concrete(),
+                parent,
+                key,
+                allocator,
+                guard,
+                counted,
+            ),
+            NodeType::N256 => Self::remove_shrink::<Node256<S>, Node48<S>, A>(
+                node.into_concrete(),
+                parent,
+                key,
+                allocator,
+                guard,
+                counted,
+            ),
+        }
+    }
+
+    /// Inserts `val` into `node`, replacing the node with a bigger one if it is full.
+    ///
+    /// The slot holds a single value, `counted` is called as for [BaseNode::remove_and_unlock].
+    pub(crate) fn insert_and_unlock<'a, S: Copy, A: Allocator + Send + Clone + 'static>(
         node: ReadGuard<'a>,
         parent: (u8, Option<ReadGuard>),
-        val: (u8, NodePtr),
+        val: (u8, S),
         allocator: &'a A,
         guard: &Guard,
+        counted: impl FnOnce(&BaseNode),
     ) -> Result<(), ArtError> {
         match node.as_ref().get_type() {
-            NodeType::N4 => Self::insert_grow::<Node4, Node16, A>(
+            NodeType::N4 => Self::insert_grow::<Node4<S>, Node16<S>, A>(
                 node.into_concrete(),
                 parent,
                 val,
                 allocator,
                 guard,
+                counted,
             ),
-            NodeType::N16 => Self::insert_grow::<Node16, Node48, A>(
+            NodeType::N16 => Self::insert_grow::<Node16<S>, Node48<S>, A>(
                 node.into_concrete(),
                 parent,
                 val,
                 allocator,
                 guard,
+                counted,
             ),
-            NodeType::N48 => Self::insert_grow::<Node48, Node256, A>(
+            NodeType::N48 => Self::insert_grow::<Node48<S>, Node256<S>, A>(
                 node.into_concrete(),
                 parent,
                 val,
                 allocator,
                 guard,
+                counted,
             ),
-            NodeType::N256 => Self::insert_grow::<Node256, Node256, A>(
+            NodeType::N256 => Self::insert_grow::<Node256<S>, Node256<S>, A>(
                 node.into_concrete(),
                 parent,
                 val,
                 allocator,
                 guard,
+                counted,
             ),
         }
     }
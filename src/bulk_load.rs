@@ -0,0 +1,200 @@
+use crate::{
+    base_node::{BaseNode, NodeType, MAX_PREFIX_LEN},
+    error::ArtError,
+    key::RawKey,
+    node_ptr::NodePtr,
+    tree::RawTree,
+    Allocator,
+};
+
+/// Builds the subtrees below a node from keys in ascending order, without any locking.
+///
+/// Only the nodes on the path of the last key are open, all other nodes are complete
+/// and are created at their final size once the next key leaves them.
+/// The nodes are not reachable by other threads until [BulkLoader::finish] hands them over.
+pub(crate) struct BulkLoader<
+    't,
+    T: RawKey,
+    V: Copy + PartialEq,
+    A: Allocator + Clone + 'static,
+    const K_LEN: usize,
+> {
+    tree: &'t RawTree<T, V, A, K_LEN>,
+    /// The open inner nodes, their levels and children, the first one is the node the subtrees are loaded below.
+    inner: Vec<(usize, Vec<(u8, NodePtr)>)>,
+    /// The values of the open last level node.
+    leaf: Vec<(u8, V)>,
+    last: [u8; K_LEN],
+    count: usize,
+}
+
+impl<T: RawKey, V: Copy + PartialEq, A: Allocator + Clone, const K_LEN: usize>
+    BulkLoader<'_, T, V, A, K_LEN>
+{
+    /// A loader for the subtrees below the node at `level`, which must be an inner node.
+    /// The nodes are allocated with the allocator of `tree`.
+    pub(crate) fn new(
+        tree: &RawTree<T, V, A, K_LEN>,
+        level: usize,
+    ) -> BulkLoader<'_, T, V, A, K_LEN> {
+        debug_assert!(level < K_LEN - 1);
+        BulkLoader {
+            tree,
+            inner: vec![(level, Vec::new())],
+            leaf: Vec::new(),
+            last: [0; K_LEN],
+            count: 0,
+        }
+    }
+
+    /// Adds a key, which must not be smaller than the last one. The value of a repeated key replaces the previous one.
+    ///
+    /// # Panics
+    /// Panics if `key` is smaller than the last key.
+    pub(crate) fn push(&mut self, key: &[u8], val: V) -> Result<(), ArtError> {
+        if self.count > 0 {
+            let common = self
+                .last
+                .iter()
+                .zip(key)
+                .take_while(|(l, k)| l == k)
+                .count();
+            if common == K_LEN {
+                self.leaf.last_mut().unwrap().1 = val;
+                return Ok(());
+            }
+            assert!(key[common] > self.last[common], "the keys are not sorted");
+            debug_assert!(
+                common >= self.inner[0].0,
+                "the key is not below the loaded node"
+            );
+            if common < K_LEN - 1 {
+                self.close_above(common)?;
+            }
+        }
+        self.leaf.push((key[K_LEN - 1], val));
+        self.last.copy_from_slice(key);
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Completes the open nodes, returns the children of the node the subtrees are loaded below
+    /// and the number of keys in them.
+    pub(crate) fn finish(mut self) -> Result<(Vec<(u8, NodePtr)>, usize), ArtError> {
+        if self.count > 0 {
+            self.close_above(self.inner[0].0)?;
+        }
+        let (_, children) = self.inner.pop().unwrap();
+        Ok((children, self.count))
+    }
+
+    /// Completes the open nodes below `level`, so that the path of the next key branches off at `level`.
+    fn close_above(&mut self, level: usize) -> Result<(), ArtError> {
+        let mut node = None;
+        let mut node_level = K_LEN - 1;
+        loop {
+            let top_level = self.inner.last().unwrap().0;
+            let parent_level = std::cmp::max(top_level, level);
+            let child = match node.take() {
+                None => self.make_leaf(parent_level)?,
+                Some(children) => {
+                    make_inner(self.tree, children, &self.last, parent_level, node_level)?
+                }
+            };
+            if top_level < level {
+                self.inner.push((level, vec![(self.last[level], child)]));
+                return Ok(());
+            }
+            let (_, children) = self.inner.last_mut().unwrap();
+            children.push((self.last[top_level], child));
+            if top_level == level {
+                return Ok(());
+            }
+            let (top_level, children) = self.inner.pop().unwrap();
+            node = Some(children);
+            node_level = top_level;
+        }
+    }
+
+    /// Creates the open last level node below the node at `parent_level`.
+    fn make_leaf(&mut self, parent_level: usize) -> Result<NodePtr, ArtError> {
+        let values = std::mem::take(&mut self.leaf);
+        // same as `RawTree::make_leaf_node`, the keys of a set are only a bit in the mask of a `Node256`
+        let n_type = if std::mem::size_of::<V>() == 0 {
+            NodeType::N256
+        } else {
+            NodeType::fitting(values.len())
+        };
+        let prefix_start =
+            std::cmp::max(parent_level + 1, (K_LEN - 1).saturating_sub(MAX_PREFIX_LEN));
+        self.tree.note_fan_out(K_LEN - 1, values.len());
+        let count = values.len();
+        let leaf = BaseNode::make_node_of(
+            n_type,
+            &self.last[prefix_start..K_LEN - 1],
+            values,
+            self.tree.allocator(),
+        )?;
+        unsafe { &*leaf }.set_keys(count);
+        self.tree.chain_above(
+            &self.last,
+            parent_level,
+            NodePtr::from_node(leaf),
+            prefix_start - 1,
+        )
+    }
+}
+
+impl<T: RawKey, V: Copy + PartialEq, A: Allocator + Clone, const K_LEN: usize> Drop
+    for BulkLoader<'_, T, V, A, K_LEN>
+{
+    fn drop(&mut self) {
+        for (level, children) in self.inner.drain(..) {
+            for (_, child) in children {
+                unsafe { self.tree.drop_child(child, level) };
+            }
+        }
+    }
+}
+
+/// Creates the inner node at `level` below the node at `parent_level`, `key` provides the prefix bytes between them.
+/// On failure, the `children` are freed.
+pub(crate) fn make_inner<
+    T: RawKey,
+    V: Copy + PartialEq,
+    A: Allocator + Clone,
+    const K_LEN: usize,
+>(
+    tree: &RawTree<T, V, A, K_LEN>,
+    children: Vec<(u8, NodePtr)>,
+    key: &[u8],
+    parent_level: usize,
+    level: usize,
+) -> Result<NodePtr, ArtError> {
+    let prefix_start = std::cmp::max(parent_level + 1, level.saturating_sub(MAX_PREFIX_LEN));
+    tree.note_fan_out(level, children.len());
+    let node = BaseNode::make_node_of(
+        NodeType::fitting(children.len()),
+        &key[prefix_start..level],
+        children.iter().copied(),
+        tree.allocator(),
+    );
+    match node {
+        Ok(node) => {
+            let n = unsafe { &*node };
+            n.set_keys(n.child_keys());
+            tree.chain_above(
+                key,
+                parent_level,
+                NodePtr::from_node(node),
+                prefix_start - 1,
+            )
+        }
+        Err(e) => {
+            for (_, child) in children {
+                unsafe { tree.drop_child(child, level) };
+            }
+            Err(e)
+        }
+    }
+}
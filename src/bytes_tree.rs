@@ -0,0 +1,699 @@
+use std::{
+    alloc::Layout,
+    cmp,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+    ptr::NonNull,
+};
+
+use crossbeam_epoch::Guard;
+
+use crate::{
+    base_node::{BaseNode, Node, MAX_PREFIX_LEN},
+    error::{ArtError, OOMError},
+    key::RawKey,
+    lock::ReadGuard,
+    node_256::Node256,
+    node_4::Node4,
+    node_ptr::NodePtr,
+    utils::{Backoff, BackoffPolicy},
+    Allocator, DefaultAllocator,
+};
+
+/// A leaf of the [BytesTree], followed by `key_len` bytes of the full key.
+/// Leaves are immutable, updating a value installs a new leaf.
+#[repr(C)]
+pub(crate) struct Leaf {
+    value: usize,
+    key_len: usize,
+}
+
+impl Leaf {
+    fn layout(key_len: usize) -> Layout {
+        Layout::from_size_align(
+            std::mem::size_of::<Leaf>() + key_len,
+            std::mem::align_of::<Leaf>(),
+        )
+        .unwrap()
+    }
+
+    fn make_leaf(
+        key: &[u8],
+        value: usize,
+        allocator: &impl Allocator,
+    ) -> Result<*mut Leaf, ArtError> {
+        let ptr = allocator
+            .allocate(Self::layout(key.len()))
+            .map_err(|_e| ArtError::Oom)?;
+        let leaf = ptr.as_ptr() as *mut Leaf;
+        unsafe {
+            std::ptr::write(
+                leaf,
+                Leaf {
+                    value,
+                    key_len: key.len(),
+                },
+            );
+            std::ptr::copy_nonoverlapping(key.as_ptr(), leaf.add(1) as *mut u8, key.len());
+        }
+        Ok(leaf)
+    }
+
+    /// Here we must get a clone of allocator because the drop_leaf might be called in epoch guard
+    unsafe fn drop_leaf<A: Allocator>(leaf: *mut Leaf, allocator: A) {
+        let layout = Self::layout((*leaf).key_len);
+        allocator.deallocate(NonNull::new(leaf as *mut u8).unwrap(), layout);
+    }
+
+    pub(crate) fn key(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts((self as *const Leaf).add(1) as *const u8, self.key_len)
+        }
+    }
+
+    pub(crate) fn value(&self) -> usize {
+        self.value
+    }
+}
+
+/// Compares the key bytes leading to a subtree with a range bound.
+/// `Equal` means the bound continues into the subtree.
+fn cmp_path(path: &[u8], bound: &[u8]) -> cmp::Ordering {
+    let len = cmp::min(path.len(), bound.len());
+    match path[..len].cmp(&bound[..len]) {
+        cmp::Ordering::Equal if path.len() > bound.len() => cmp::Ordering::Greater,
+        ord => ord,
+    }
+}
+
+/// Compares the stored part of the node prefix with the key, returns the level after the prefix.
+/// The bytes that are not stored are skipped, the caller must verify the key at the leaf.
+fn check_prefix_optimistic(node: &BaseNode, key: &[u8], level: usize) -> Option<usize> {
+    let next_level = level + node.prefix_len();
+    if key.len() < next_level || !key[level..].starts_with(node.prefix()) {
+        return None;
+    }
+    Some(next_level)
+}
+
+/// Loads the full compressed path of `node` into `prefix`.
+/// Bytes beyond the stored prefix are read from the left most leaf of the subtree, as all its keys share the path.
+fn load_prefix(node: &ReadGuard, level: usize, prefix: &mut Vec<u8>) -> Result<(), ArtError> {
+    let prefix_len = node.as_ref().prefix_len();
+    prefix.clear();
+    if prefix_len <= MAX_PREFIX_LEN {
+        prefix.extend_from_slice(node.as_ref().prefix());
+        return Ok(());
+    }
+
+    let mut child = node.as_ref().get_children(0, 255).next().map(|(_k, c)| c);
+    node.check_version()?;
+    loop {
+        let cur = child.ok_or(ArtError::VersionNotMatch)?;
+        if cur.is_leaf() {
+            let key = unsafe { &*cur.as_leaf::<Leaf>() }.key();
+            if key.len() < level + prefix_len {
+                return Err(ArtError::VersionNotMatch);
+            }
+            prefix.extend_from_slice(&key[level..level + prefix_len]);
+            return Ok(());
+        }
+        let cur = unsafe { &*cur.as_ptr() }.read_lock()?;
+        child = cur.as_ref().get_children(0, 255).next().map(|(_k, c)| c);
+        cur.check_version()?;
+    }
+}
+
+/// The ART tree with variable length keys.
+///
+/// Unlike the [RawTree](crate::tree::RawTree), leaves can live at any level (lazy expansion) and hold the full key,
+/// so compressed paths longer than `MAX_PREFIX_LEN` are skipped optimistically and verified at the leaf.
+/// The keys must be prefix free, i.e., no key is a prefix of another key, see also `BytesKey`.
+pub(crate) struct BytesTree<K: RawKey, A: Allocator + Clone + 'static = DefaultAllocator> {
+    root: *const Node256,
+    allocator: A,
+    backoff: BackoffPolicy,
+    _pt_key: PhantomData<K>,
+}
+
+unsafe impl<K: RawKey, A: Allocator + Clone> Send for BytesTree<K, A> {}
+unsafe impl<K: RawKey, A: Allocator + Clone> Sync for BytesTree<K, A> {}
+
+impl<K: RawKey> Default for BytesTree<K> {
+    fn default() -> Self {
+        Self::new(DefaultAllocator {})
+    }
+}
+
+impl<K: RawKey, A: Allocator + Clone> Drop for BytesTree<K, A> {
+    fn drop(&mut self) {
+        let mut sub_nodes = vec![self.root as *const BaseNode];
+
+        while let Some(node) = sub_nodes.pop() {
+            let children = unsafe { &*node }.get_children(0, 255);
+            for (_k, n) in children {
+                if n.is_leaf() {
+                    unsafe {
+                        Leaf::drop_leaf(n.as_leaf::<Leaf>() as *mut Leaf, self.allocator.clone());
+                    }
+                } else {
+                    sub_nodes.push(n.as_ptr());
+                }
+            }
+            unsafe {
+                BaseNode::drop_node(node as *mut BaseNode, self.allocator.clone());
+            }
+        }
+    }
+}
+
+impl<K: RawKey, A: Allocator + Clone> BytesTree<K, A> {
+    pub fn new(allocator: A) -> Self {
+        Self::with_backoff(allocator, BackoffPolicy::default())
+    }
+
+    pub(crate) fn with_backoff(allocator: A, backoff: BackoffPolicy) -> Self {
+        BytesTree {
+            root: BaseNode::make_node::<Node256>(&[], &allocator)
+                .expect("Can't allocate memory for root node!") as *const Node256,
+            allocator,
+            backoff,
+            _pt_key: PhantomData,
+        }
+    }
+
+    /// A fresh backoff of the tree's policy, for the retry loop of an operation.
+    #[inline]
+    fn backoff(&self) -> Backoff {
+        Backoff::with_policy(self.backoff)
+    }
+}
+
+impl<K: RawKey, A: Allocator + Clone + Send> BytesTree<K, A> {
+    #[inline]
+    pub(crate) fn get(&self, key: &K, _guard: &Guard) -> Option<usize> {
+        let backoff = self.backoff();
+        loop {
+            match self.get_inner(key.as_bytes()) {
+                Ok(v) => return v,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    fn get_inner(&self, key: &[u8]) -> Result<Option<usize>, ArtError> {
+        let mut level = 0;
+        let mut node = unsafe { &*self.root }.base().read_lock()?;
+
+        loop {
+            level = match check_prefix_optimistic(node.as_ref(), key, level) {
+                Some(l) if l < key.len() => l,
+                _ => {
+                    node.check_version()?;
+                    return Ok(None);
+                }
+            };
+
+            let child_node = node.as_ref().get_child(key[level]);
+            node.check_version()?;
+
+            let Some(child_node) = child_node else {
+                return Ok(None);
+            };
+
+            if child_node.is_leaf() {
+                let leaf = unsafe { &*child_node.as_leaf::<Leaf>() };
+                return Ok((leaf.key() == key).then(|| leaf.value()));
+            }
+
+            level += 1;
+
+            let child = unsafe { &*child_node.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            node = child;
+        }
+    }
+
+    #[inline]
+    fn insert_inner<F>(
+        &self,
+        k: &K,
+        val_func: &mut F,
+        guard: &Guard,
+    ) -> Result<Option<usize>, ArtError>
+    where
+        F: FnMut(Option<usize>) -> usize,
+    {
+        let key = k.as_bytes();
+        let mut parent_node: Option<ReadGuard> = None;
+        let mut next_node = self.root as *const BaseNode;
+        let mut parent_key: u8;
+        let mut node_key: u8 = 0;
+        let mut level = 0;
+        let mut prefix = Vec::new();
+
+        loop {
+            parent_key = node_key;
+            let node = unsafe { &*next_node }.read_lock()?;
+
+            let prefix_len = node.as_ref().prefix_len();
+            if prefix_len > 0 {
+                load_prefix(&node, level, &mut prefix)?;
+                node.check_version()?;
+
+                let matched = prefix
+                    .iter()
+                    .zip(&key[level..])
+                    .take_while(|(p, k)| p == k)
+                    .count();
+
+                if matched < prefix_len {
+                    assert!(level + matched < key.len(), "keys must be prefix free");
+
+                    // the key diverges inside the compressed path, split it with a new node
+                    let mut write_p = parent_node.unwrap().upgrade().map_err(|(_n, v)| v)?;
+                    let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+
+                    let new_middle_node =
+                        BaseNode::make_node::<Node4>(&prefix[..matched], &self.allocator)?;
+                    let new_leaf = match Leaf::make_leaf(key, val_func(None), &self.allocator) {
+                        Ok(l) => l,
+                        Err(e) => {
+                            unsafe {
+                                BaseNode::drop_node(
+                                    new_middle_node as *mut BaseNode,
+                                    self.allocator.clone(),
+                                );
+                            }
+                            return Err(e);
+                        }
+                    };
+
+                    let middle = unsafe { &mut *new_middle_node };
+                    middle.insert(key[level + matched], NodePtr::from_leaf(new_leaf));
+                    middle.insert(prefix[matched], NodePtr::from_node(write_n.as_mut()));
+
+                    write_n
+                        .as_mut()
+                        .set_prefix(&prefix[matched + 1..], prefix_len - matched - 1);
+                    write_p.as_mut().change(
+                        parent_key,
+                        NodePtr::from_node(new_middle_node as *mut BaseNode),
+                    );
+                    return Ok(None);
+                }
+                level += prefix_len;
+            }
+
+            assert!(level < key.len(), "keys must be prefix free");
+            node_key = key[level];
+
+            let child_node = node.as_ref().get_child(node_key);
+            node.check_version()?;
+
+            let child_node = match child_node {
+                Some(n) => n,
+                None => {
+                    let new_leaf = Leaf::make_leaf(key, val_func(None), &self.allocator)?;
+                    if let Err(e) = BaseNode::insert_and_unlock(
+                        node,
+                        (parent_key, parent_node),
+                        (node_key, NodePtr::from_leaf(new_leaf)),
+                        &self.allocator,
+                        guard,
+                        // the byte tree does not count the keys of subtrees
+                        |_| {},
+                    ) {
+                        unsafe { Leaf::drop_leaf(new_leaf, self.allocator.clone()) };
+                        return Err(e);
+                    }
+                    return Ok(None);
+                }
+            };
+
+            if let Some(p) = parent_node {
+                p.unlock()?;
+            }
+
+            if !child_node.is_leaf() {
+                parent_node = Some(node);
+                next_node = child_node.as_ptr();
+                level += 1;
+                continue;
+            }
+
+            let leaf = unsafe { &*child_node.as_leaf::<Leaf>() };
+            if leaf.key() == key {
+                let old = leaf.value();
+                let new = val_func(Some(old));
+                if old == new {
+                    node.check_version()?;
+                    return Ok(Some(old));
+                }
+
+                let new_leaf = Leaf::make_leaf(key, new, &self.allocator)?;
+                let mut write_n = match node.upgrade() {
+                    Ok(w) => w,
+                    Err((_n, e)) => {
+                        unsafe { Leaf::drop_leaf(new_leaf, self.allocator.clone()) };
+                        return Err(e);
+                    }
+                };
+                write_n
+                    .as_mut()
+                    .change(node_key, NodePtr::from_leaf(new_leaf));
+
+                self.defer_drop_leaf(child_node, guard);
+                return Ok(Some(old));
+            }
+
+            // lazy expansion: the slot holds another key, push both leaves down into a new node
+            let leaf_key = leaf.key();
+            let matched = leaf_key[level + 1..]
+                .iter()
+                .zip(&key[level + 1..])
+                .take_while(|(l, k)| l == k)
+                .count();
+            let split_level = level + 1 + matched;
+            assert!(
+                split_level < key.len() && split_level < leaf_key.len(),
+                "keys must be prefix free"
+            );
+
+            let n4 = BaseNode::make_node::<Node4>(&key[level + 1..split_level], &self.allocator)?;
+            let new_leaf = match Leaf::make_leaf(key, val_func(None), &self.allocator) {
+                Ok(l) => l,
+                Err(e) => {
+                    unsafe { BaseNode::drop_node(n4 as *mut BaseNode, self.allocator.clone()) };
+                    return Err(e);
+                }
+            };
+            unsafe { &mut *n4 }.insert(key[split_level], NodePtr::from_leaf(new_leaf));
+            unsafe { &mut *n4 }.insert(leaf_key[split_level], child_node);
+
+            let mut write_n = match node.upgrade() {
+                Ok(w) => w,
+                Err((_n, e)) => {
+                    unsafe {
+                        Leaf::drop_leaf(new_leaf, self.allocator.clone());
+                        BaseNode::drop_node(n4 as *mut BaseNode, self.allocator.clone());
+                    }
+                    return Err(e);
+                }
+            };
+            write_n
+                .as_mut()
+                .change(node_key, NodePtr::from_node(n4 as *mut BaseNode));
+            return Ok(None);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn insert(
+        &self,
+        k: &K,
+        val: usize,
+        guard: &Guard,
+    ) -> Result<Option<usize>, OOMError> {
+        let backoff = self.backoff();
+        loop {
+            match self.insert_inner(k, &mut |_| val, guard) {
+                Ok(v) => return Ok(v),
+                Err(e) => match e {
+                    ArtError::Locked | ArtError::VersionNotMatch => {
+                        backoff.wait();
+                        continue;
+                    }
+                    ArtError::Oom => return Err(OOMError::new()),
+                },
+            }
+        }
+    }
+
+    fn defer_drop_leaf(&self, leaf: NodePtr, guard: &Guard) {
+        let leaf = leaf.as_leaf::<Leaf>() as usize;
+        let allocator = self.allocator.clone();
+        guard.defer(move || unsafe {
+            Leaf::drop_leaf(leaf as *mut Leaf, allocator);
+        });
+    }
+
+    #[inline]
+    fn compute_if_present_inner<F>(
+        &self,
+        k: &K,
+        remapping_function: &mut F,
+        guard: &Guard,
+    ) -> Result<Option<(usize, Option<usize>)>, ArtError>
+    where
+        F: FnMut(usize) -> Option<usize>,
+    {
+        let key = k.as_bytes();
+        // the ancestors of the current node, needed to unlink nodes that become empty
+        let mut path: Vec<(ReadGuard, u8)> = Vec::new();
+        let mut level = 0;
+        let mut node = unsafe { &*self.root }.base().read_lock()?;
+
+        loop {
+            level = match check_prefix_optimistic(node.as_ref(), key, level) {
+                Some(l) if l < key.len() => l,
+                _ => {
+                    node.check_version()?;
+                    return Ok(None);
+                }
+            };
+
+            let node_key = key[level];
+            let child_node = node.as_ref().get_child(node_key);
+            node.check_version()?;
+
+            let child_node = match child_node {
+                Some(n) => n,
+                None => return Ok(None),
+            };
+
+            if !child_node.is_leaf() {
+                level += 1;
+                let child = unsafe { &*child_node.as_ptr() }.read_lock()?;
+                node.check_version()?;
+                path.push((node, node_key));
+                node = child;
+                continue;
+            }
+
+            let leaf = unsafe { &*child_node.as_leaf::<Leaf>() };
+            if leaf.key() != key {
+                return Ok(None);
+            }
+
+            let old = leaf.value();
+            match remapping_function(old) {
+                Some(new) => {
+                    if new == old {
+                        node.check_version()?;
+                        return Ok(Some((old, Some(old))));
+                    }
+                    let new_leaf = Leaf::make_leaf(key, new, &self.allocator)?;
+                    let mut write_n = match node.upgrade() {
+                        Ok(w) => w,
+                        Err((_n, e)) => {
+                            unsafe { Leaf::drop_leaf(new_leaf, self.allocator.clone()) };
+                            return Err(e);
+                        }
+                    };
+                    write_n
+                        .as_mut()
+                        .change(node_key, NodePtr::from_leaf(new_leaf));
+                    self.defer_drop_leaf(child_node, guard);
+                    return Ok(Some((old, Some(new))));
+                }
+                None => {
+                    if node.as_ref().get_count() > 1 || path.is_empty() {
+                        BaseNode::remove_and_unlock::<NodePtr, A>(
+                            node,
+                            path.pop().map_or((0, None), |(n, k)| (k, Some(n))),
+                            node_key,
+                            &self.allocator,
+                            guard,
+                            |_| {},
+                        )?;
+                    } else {
+                        // the node becomes empty, unlink the highest ancestor that only leads to this leaf,
+                        // so that every subtree keeps at least one leaf to load the prefix from.
+                        let mut chain = vec![node];
+                        while path.len() > 1 && path.last().unwrap().0.as_ref().get_count() == 1 {
+                            chain.push(path.pop().unwrap().0);
+                        }
+                        let (parent, parent_key) = path.pop().unwrap();
+
+                        let mut write_p = parent.upgrade().map_err(|(_n, v)| v)?;
+                        let mut write_chain = Vec::with_capacity(chain.len());
+                        for n in chain.into_iter().rev() {
+                            write_chain.push(n.upgrade().map_err(|(_n, v)| v)?);
+                        }
+
+                        write_p.as_mut().remove(parent_key);
+                        for mut write_n in write_chain {
+                            write_n.mark_obsolete();
+                            let allocator = self.allocator.clone();
+                            let delete_n = write_n.as_mut() as *mut BaseNode as usize;
+                            std::mem::forget(write_n);
+                            guard.defer(move || unsafe {
+                                BaseNode::drop_node(delete_n as *mut BaseNode, allocator);
+                            });
+                        }
+                    }
+                    self.defer_drop_leaf(child_node, guard);
+                    return Ok(Some((old, None)));
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn compute_if_present<F>(
+        &self,
+        k: &K,
+        remapping_function: &mut F,
+        guard: &Guard,
+    ) -> Option<(usize, Option<usize>)>
+    where
+        F: FnMut(usize) -> Option<usize>,
+    {
+        let backoff = self.backoff();
+        loop {
+            match self.compute_if_present_inner(k, &mut *remapping_function, guard) {
+                Ok(n) => return n,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    /// Scans the keys within `range`, `fill` writes the (key, value) of a leaf to a slot of `result`.
+    /// Returns the number of slots filled.
+    #[inline]
+    pub(crate) fn range<R>(
+        &self,
+        range: &impl RangeBounds<K>,
+        result: &mut [R],
+        fill: &mut impl FnMut(&mut R, &[u8], usize),
+        _guard: &Guard,
+    ) -> usize {
+        let start = range.start_bound().map(|k| k.as_bytes());
+        let end = range.end_bound().map(|k| k.as_bytes());
+        if is_empty_range(start, end) || result.is_empty() {
+            return 0;
+        }
+
+        let mut scan = BytesScan {
+            start,
+            end,
+            result,
+            fill,
+            found: 0,
+            path: Vec::new(),
+            prefix: Vec::new(),
+        };
+
+        let backoff = self.backoff();
+        loop {
+            scan.found = 0;
+            scan.path.clear();
+            match scan.scan_node(self.root as *const BaseNode) {
+                Ok(_) => return scan.found,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+}
+
+/// Whether no key can be within the bounds, e.g., the start is above the end.
+fn is_empty_range(start: Bound<&[u8]>, end: Bound<&[u8]>) -> bool {
+    match (start, end) {
+        (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+        (Bound::Included(s), Bound::Included(e)) => s > e,
+        (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) => {
+            s >= e
+        }
+    }
+}
+
+struct BytesScan<'a, R, F: FnMut(&mut R, &[u8], usize)> {
+    start: Bound<&'a [u8]>,
+    end: Bound<&'a [u8]>,
+    result: &'a mut [R],
+    fill: &'a mut F,
+    found: usize,
+    path: Vec<u8>,
+    prefix: Vec<u8>,
+}
+
+impl<R, F: FnMut(&mut R, &[u8], usize)> BytesScan<'_, R, F> {
+    fn is_full(&self) -> bool {
+        self.found == self.result.len()
+    }
+
+    fn scan_node(&mut self, node: *const BaseNode) -> Result<(), ArtError> {
+        let node = unsafe { &*node }.read_lock()?;
+        let base = self.path.len();
+
+        load_prefix(&node, base, &mut self.prefix)?;
+        self.path.extend_from_slice(&self.prefix);
+        node.check_version()?;
+
+        // An unbounded side never prunes, it is treated as a bound already passed.
+        let level = self.path.len();
+        let start = match self.start {
+            Bound::Included(s) | Bound::Excluded(s) => Some(s),
+            Bound::Unbounded => None,
+        };
+        let end = match self.end {
+            Bound::Included(e) | Bound::Excluded(e) => Some(e),
+            Bound::Unbounded => None,
+        };
+        let start_cmp = start.map_or(cmp::Ordering::Greater, |s| cmp_path(&self.path, s));
+        let end_cmp = end.map_or(cmp::Ordering::Less, |e| cmp_path(&self.path, e));
+        if start_cmp == cmp::Ordering::Less
+            || end_cmp == cmp::Ordering::Greater
+            || (end_cmp == cmp::Ordering::Equal
+                && level >= end.map_or(0, |e| e.len())
+                && matches!(self.end, Bound::Excluded(_)))
+        {
+            self.path.truncate(base);
+            return Ok(());
+        }
+
+        let start_level = match start {
+            Some(s) if start_cmp == cmp::Ordering::Equal && level < s.len() => s[level],
+            _ => 0,
+        };
+        let end_level = match end {
+            Some(e) if end_cmp == cmp::Ordering::Equal && level < e.len() => e[level],
+            _ => 255,
+        };
+
+        for (k, child) in node.as_ref().get_children(start_level, end_level) {
+            node.check_version()?;
+
+            if child.is_leaf() {
+                let leaf = unsafe { &*child.as_leaf::<Leaf>() };
+                let key = leaf.key();
+                if (self.start, self.end).contains(key) {
+                    (self.fill)(&mut self.result[self.found], key, leaf.value());
+                    self.found += 1;
+                }
+            } else {
+                self.path.push(k);
+                self.scan_node(child.as_ptr())?;
+                self.path.pop();
+            }
+
+            if self.is_full() {
+                break;
+            }
+        }
+        node.check_version()?;
+        self.path.truncate(base);
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{epoch, error::OOMError, key::FixedKey, Allocator, Art, ArtKey};
+
+/// A view into a single key of an [Art], returned by [Art::entry].
+///
+/// The entry is a snapshot: whether it is [Entry::Occupied] or [Entry::Vacant] is decided by a lookup
+/// when it is created, and other threads may insert or remove the key afterwards.
+/// The variant picks the operation, the operation itself is applied atomically to the tree as it is then:
+///
+/// - [Entry::or_insert_with] returns the value in the tree, inserting it first if the key is absent,
+///   regardless of the variant.
+/// - [Entry::and_modify] updates the current value if occupied and the key is still present.
+/// - [Entry::insert] inserts or replaces the value in either case.
+/// - [Entry::remove] removes the key if occupied.
+///
+/// The closures of [Entry::and_modify] may be called more than once if the update is retried,
+/// only the result of the last call is stored.
+pub enum Entry<
+    'a,
+    K: ArtKey,
+    V: Clone + From<usize>,
+    A: Allocator + Clone + Send + 'static,
+    const K_LEN: usize,
+> where
+    usize: From<V>,
+{
+    Occupied(OccupiedEntry<'a, K, V, A, K_LEN>),
+    Vacant(VacantEntry<'a, K, V, A, K_LEN>),
+}
+
+/// An [Entry] of a key that was present.
+pub struct OccupiedEntry<
+    'a,
+    K: ArtKey,
+    V: Clone + From<usize>,
+    A: Allocator + Clone + Send + 'static,
+    const K_LEN: usize,
+> where
+    usize: From<V>,
+{
+    tree: &'a Art<K, V, A, K_LEN>,
+    guard: &'a epoch::Guard,
+    key: K,
+    value: V,
+}
+
+/// An [Entry] of a key that was absent.
+pub struct VacantEntry<
+    'a,
+    K: ArtKey,
+    V: Clone + From<usize>,
+    A: Allocator + Clone + Send + 'static,
+    const K_LEN: usize,
+> where
+    usize: From<V>,
+{
+    tree: &'a Art<K, V, A, K_LEN>,
+    guard: &'a epoch::Guard,
+    key: K,
+}
+
+impl<'a, K: ArtKey, V: Clone + From<usize>, A: Allocator + Clone + Send, const K_LEN: usize>
+    Entry<'a, K, V, A, K_LEN>
+where
+    usize: From<V>,
+{
+    pub(crate) fn new(tree: &'a Art<K, V, A, K_LEN>, key: K, guard: &'a epoch::Guard) -> Self {
+        match tree.get(&key, guard) {
+            Some(value) => Entry::Occupied(OccupiedEntry {
+                tree,
+                guard,
+                key,
+                value,
+            }),
+            None => Entry::Vacant(VacantEntry { tree, guard, key }),
+        }
+    }
+
+    /// Returns the key of the entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => e.key(),
+        }
+    }
+
+    /// Returns the value of the key in the tree, inserts `default` first if the key is absent.
+    /// The key is looked up again, so the result is the value at the time of the call even if
+    /// the key was inserted or removed since the entry was created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert_eq!(tree.entry(1, &guard).or_insert(42).unwrap(), 42);
+    /// assert_eq!(tree.entry(1, &guard).or_insert(43).unwrap(), 42);
+    /// ```
+    #[inline]
+    pub fn or_insert(self, default: V) -> Result<V, OOMError> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Same as [Entry::or_insert], but the value is computed by `default`.
+    ///
+    /// `default` is called at most once, and only if the key is absent:
+    /// if the insert is retried, the value it returned is reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// let v = tree.entry(1, &guard).or_insert_with(|| 42).unwrap();
+    /// assert_eq!(v, 42);
+    /// let v = tree.entry(1, &guard).or_insert_with(|| unreachable!()).unwrap();
+    /// assert_eq!(v, 42);
+    /// ```
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> Result<V, OOMError> {
+        let (tree, guard, key) = match self {
+            Entry::Occupied(e) => (e.tree, e.guard, e.key),
+            Entry::Vacant(e) => (e.tree, e.guard, e.key),
+        };
+
+        let mut default = Some(default);
+        let mut new = None;
+        let old = tree.inner.compute_or_insert(
+            FixedKey::new(&key),
+            &mut |old| match old {
+                Some(old) => old,
+                None => *new.get_or_insert_with(|| usize::from(default.take().unwrap()())),
+            },
+            guard,
+        )?;
+        Ok(V::from(old.or(new).unwrap()))
+    }
+
+    /// Updates the value with `f` if occupied and the key is still present.
+    /// The entry becomes vacant if the key was removed concurrently.
+    ///
+    /// `f` may be called more than once if the update is retried, only the last result is stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// for _ in 0..3 {
+    ///     tree.entry(1, &guard).and_modify(|v| v + 1).or_insert(0).unwrap();
+    /// }
+    /// assert_eq!(tree.get(&1, &guard), Some(2));
+    /// ```
+    pub fn and_modify<F: FnMut(V) -> V>(self, mut f: F) -> Self {
+        let e = match self {
+            Entry::Occupied(e) => e,
+            vacant => return vacant,
+        };
+
+        let key = FixedKey::new(&e.key);
+        let mut remapping = |v: usize| Some(usize::from(f(V::from(v))));
+        match e
+            .tree
+            .inner
+            .compute_if_present(&key, &mut remapping, e.guard)
+        {
+            Some((_, Some(new))) => Entry::Occupied(OccupiedEntry {
+                value: V::from(new),
+                ..e
+            }),
+            _ => Entry::Vacant(VacantEntry {
+                tree: e.tree,
+                guard: e.guard,
+                key: e.key,
+            }),
+        }
+    }
+
+    /// Inserts or replaces the value, returns the previous value in the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert_eq!(tree.entry(1, &guard).insert(42).unwrap(), None);
+    /// assert_eq!(tree.entry(1, &guard).insert(43).unwrap(), Some(42));
+    /// ```
+    #[inline]
+    pub fn insert(self, value: V) -> Result<Option<V>, OOMError> {
+        match self {
+            Entry::Occupied(e) => e.insert(value),
+            Entry::Vacant(e) => e.insert(value),
+        }
+    }
+
+    /// Removes the key if occupied, returns the removed value.
+    /// Returns `None` if vacant, or if the key was removed concurrently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// assert_eq!(tree.entry(1, &guard).remove(), Some(42));
+    /// assert_eq!(tree.entry(1, &guard).remove(), None);
+    /// ```
+    #[inline]
+    pub fn remove(self) -> Option<V> {
+        match self {
+            Entry::Occupied(e) => e.remove(),
+            Entry::Vacant(_) => None,
+        }
+    }
+}
+
+impl<K: ArtKey, V: Clone + From<usize>, A: Allocator + Clone + Send, const K_LEN: usize>
+    OccupiedEntry<'_, K, V, A, K_LEN>
+where
+    usize: From<V>,
+{
+    /// Returns the key of the entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the value when the entry was created, it may have been changed since then.
+    #[inline]
+    pub fn get(&self) -> V {
+        self.value.clone()
+    }
+
+    /// Inserts or replaces the value, returns the previous value in the tree,
+    /// `None` if the key was removed concurrently.
+    #[inline]
+    pub fn insert(self, value: V) -> Result<Option<V>, OOMError> {
+        self.tree.insert(self.key, value, self.guard)
+    }
+
+    /// Removes the key, returns the removed value, `None` if the key was removed concurrently.
+    #[inline]
+    pub fn remove(self) -> Option<V> {
+        self.tree.remove(&self.key, self.guard)
+    }
+}
+
+impl<K: ArtKey, V: Clone + From<usize>, A: Allocator + Clone + Send, const K_LEN: usize>
+    VacantEntry<'_, K, V, A, K_LEN>
+where
+    usize: From<V>,
+{
+    /// Returns the key of the entry.
+    #[inline]
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Inserts or replaces the value, returns the previous value in the tree,
+    /// `None` unless the key was inserted concurrently.
+    #[inline]
+    pub fn insert(self, value: V) -> Result<Option<V>, OOMError> {
+        self.tree.insert(self.key, value, self.guard)
+    }
+}
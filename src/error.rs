@@ -30,3 +30,56 @@ impl Display for OOMError {
 }
 
 impl Error for OOMError {}
+
+/// The `try_` operations gave up because the [RetryBudget](crate::RetryBudget) ran out,
+/// i.e., they kept conflicting with concurrent writers.
+pub struct Contended {}
+
+impl Contended {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Debug for Contended {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Contended").finish()
+    }
+}
+
+impl Display for Contended {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "The operation is contended, the retry budget ran out!")
+    }
+}
+
+impl Error for Contended {}
+
+/// The error of the `try_` operations that may allocate.
+#[derive(Debug)]
+pub enum TryInsertError {
+    Contended(Contended),
+    Oom(OOMError),
+}
+
+impl Display for TryInsertError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TryInsertError::Contended(e) => Display::fmt(e, f),
+            TryInsertError::Oom(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for TryInsertError {}
+
+impl From<ArtError> for TryInsertError {
+    fn from(e: ArtError) -> Self {
+        match e {
+            ArtError::Locked | ArtError::VersionNotMatch => {
+                TryInsertError::Contended(Contended::new())
+            }
+            ArtError::Oom => TryInsertError::Oom(OOMError::new()),
+        }
+    }
+}
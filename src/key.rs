@@ -1,3 +1,6 @@
+use std::ops::{Bound, RangeBounds};
+
+#[cfg(test)]
 const STACK_KEY_LEN: usize = 56;
 
 /// A trait for Art-specific keys, don't use it unless you know what you are doing.
@@ -5,16 +8,16 @@ pub trait RawKey: Eq + PartialEq + Default + PartialOrd + Ord {
     fn len(&self) -> usize;
 
     fn as_bytes(&self) -> &[u8];
-
-    fn key_from(tid: usize) -> Self;
 }
 
+#[cfg(test)]
 #[derive(Clone)]
 pub(crate) struct TestingKey {
     len: usize,
     stack_keys: [u8; STACK_KEY_LEN],
 }
 
+#[cfg(test)]
 impl RawKey for TestingKey {
     fn len(&self) -> usize {
         self.len
@@ -23,22 +26,9 @@ impl RawKey for TestingKey {
     fn as_bytes(&self) -> &[u8] {
         self.stack_keys[..self.len].as_ref()
     }
-
-    fn key_from(tid: usize) -> TestingKey {
-        let mut stack_keys = [0; STACK_KEY_LEN];
-
-        let swapped = tid.swap_bytes();
-
-        for (i, v) in swapped.to_le_bytes().iter().enumerate() {
-            stack_keys[i] = *v;
-        }
-
-        TestingKey {
-            len: std::mem::size_of::<usize>(),
-            stack_keys,
-        }
-    }
 }
+
+#[cfg(test)]
 impl Ord for TestingKey {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         for i in 0..std::cmp::min(self.len(), other.len()) {
@@ -56,12 +46,14 @@ impl Ord for TestingKey {
     }
 }
 
+#[cfg(test)]
 impl PartialOrd for TestingKey {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
+#[cfg(test)]
 impl PartialEq for TestingKey {
     fn eq(&self, other: &Self) -> bool {
         if self.len != other.len {
@@ -76,14 +68,17 @@ impl PartialEq for TestingKey {
     }
 }
 
+#[cfg(test)]
 impl Eq for TestingKey {}
 
+#[cfg(test)]
 impl Default for TestingKey {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(test)]
 impl TestingKey {
     fn new() -> Self {
         TestingKey {
@@ -91,44 +86,297 @@ impl TestingKey {
             stack_keys: [0; STACK_KEY_LEN],
         }
     }
+
+    pub(crate) fn key_from(tid: usize) -> TestingKey {
+        let mut stack_keys = [0; STACK_KEY_LEN];
+
+        let swapped = tid.swap_bytes();
+
+        for (i, v) in swapped.to_le_bytes().iter().enumerate() {
+            stack_keys[i] = *v;
+        }
+
+        TestingKey {
+            len: std::mem::size_of::<usize>(),
+            stack_keys,
+        }
+    }
 }
 
-#[derive(Default, PartialEq, Eq)]
-#[repr(C)]
-pub struct UsizeKey {
-    val: usize,
+/// The fixed length, byte-comparable encoding of an [ArtKey], zero-padded at the front to `K_LEN` bytes.
+///
+/// The padding is the same for every key, it becomes a compressed prefix shared by all of them,
+/// so a key shorter than `K_LEN` still fills the last level nodes like a key of `K_LEN` bytes.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FixedKey<const K_LEN: usize> {
+    data: [u8; K_LEN],
 }
 
-impl Ord for UsizeKey {
-    #[inline]
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let a = self.val.swap_bytes();
-        let b = other.val.swap_bytes();
-        a.cmp(&b)
+impl<const K_LEN: usize> Default for FixedKey<K_LEN> {
+    fn default() -> Self {
+        Self { data: [0; K_LEN] }
     }
 }
 
-impl PartialOrd for UsizeKey {
+impl<const K_LEN: usize> FixedKey<K_LEN> {
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    pub(crate) fn new<K: ArtKey>(key: &K) -> Self {
+        let mut data = [0; K_LEN];
+        key.encode(&mut data[K_LEN - K::LEN..]);
+        Self { data }
+    }
+
+    /// Decodes the key of type `K` back.
+    #[inline]
+    pub(crate) fn decode<K: ArtKey>(&self) -> K {
+        Self::decode_bytes(&self.data)
+    }
+
+    /// Decodes the key of type `K` from the `K_LEN` bytes of an encoded key.
+    #[inline]
+    pub(crate) fn decode_bytes<K: ArtKey>(bytes: &[u8]) -> K {
+        K::decode(&bytes[K_LEN - K::LEN..K_LEN])
+    }
+
+    /// The encoded bytes of the first `prefix_len` bytes of the key of type `K`, including the padding.
+    #[inline]
+    pub(crate) fn prefix_bytes<K: ArtKey>(&self, prefix_len: usize) -> &[u8] {
+        &self.data[..K_LEN - K::LEN + prefix_len]
+    }
+
+    #[inline]
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        let mut data = [0; K_LEN];
+        data.copy_from_slice(bytes);
+        Self { data }
+    }
+
+    /// The inclusive bounds of the keys whose first `prefix_len` bytes are the same as `key`'s.
+    pub(crate) fn prefix_range<K: ArtKey>(
+        key: &K,
+        prefix_len: usize,
+    ) -> (Bound<Self>, Bound<Self>) {
+        let mut start = Self::new(key);
+        let mut end = start.clone();
+        let prefix_len = start.prefix_bytes::<K>(prefix_len).len();
+        start.data[prefix_len..].fill(0);
+        end.data[prefix_len..].fill(u8::MAX);
+        (Bound::Included(start), Bound::Included(end))
+    }
+
+    /// Encodes the bounds of a range of `K`.
+    #[inline]
+    pub(crate) fn range<K: ArtKey>(range: &impl RangeBounds<K>) -> (Bound<Self>, Bound<Self>) {
+        (
+            range.start_bound().map(Self::new),
+            range.end_bound().map(Self::new),
+        )
     }
 }
 
-impl RawKey for UsizeKey {
+impl<const K_LEN: usize> RawKey for FixedKey<K_LEN> {
     #[inline]
     fn len(&self) -> usize {
-        8
+        K_LEN
     }
 
     #[inline]
     fn as_bytes(&self) -> &[u8] {
-        unsafe { std::slice::from_raw_parts(&self.val as *const usize as *const u8, 8) }
+        &self.data
+    }
+}
+
+/// A variable length key, encoded to be prefix free while keeping the byte-wise order:
+/// `0x00` is escaped as `0x00 0x01` and every key is terminated with `0x00 0x00`.
+#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct BytesKey {
+    encoded: Vec<u8>,
+}
+
+impl BytesKey {
+    pub(crate) fn new(key: &[u8]) -> Self {
+        let mut encoded = Vec::with_capacity(key.len() + 2);
+        for b in key {
+            encoded.push(*b);
+            if *b == 0 {
+                encoded.push(1);
+            }
+        }
+        encoded.extend_from_slice(&[0, 0]);
+        Self { encoded }
     }
 
-    fn key_from(tid: usize) -> Self {
-        Self {
-            val: tid.swap_bytes(),
+    /// Decodes the `encoded` key into `out`, the previous content of `out` is cleared.
+    pub(crate) fn decode(encoded: &[u8], out: &mut Vec<u8>) {
+        debug_assert!(encoded.ends_with(&[0, 0]));
+        out.clear();
+        let mut i = 0;
+        while i < encoded.len() - 2 {
+            out.push(encoded[i]);
+            i += if encoded[i] == 0 { 2 } else { 1 };
         }
     }
+
+    /// Encodes both ends of a byte string range, the encoding keeps the order so the bounds keep their meaning.
+    pub(crate) fn range<'a>(range: &impl RangeBounds<&'a [u8]>) -> (Bound<Self>, Bound<Self>) {
+        (
+            range.start_bound().map(|k| Self::new(k)),
+            range.end_bound().map(|k| Self::new(k)),
+        )
+    }
+}
+
+impl RawKey for BytesKey {
+    #[inline]
+    fn len(&self) -> usize {
+        self.encoded.len()
+    }
+
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        &self.encoded
+    }
+}
+
+/// A key type that can be stored in an [Art](crate::Art).
+///
+/// Keys are encoded into `LEN` bytes, the encoding must preserve the order of the keys,
+/// i.e., `a < b` if and only if `a.encode()` is byte-wise smaller than `b.encode()`.
+///
+/// Integers are encoded in big-endian with the sign bit flipped, tuples are the concatenation of
+/// their elements, so every implementation in this crate sorts in the natural order of the type.
+///
+/// # Examples
+///
+/// ```
+/// use congee::ArtKey;
+///
+/// let mut a = [0; 4];
+/// let mut b = [0; 4];
+/// (-1i32).encode(&mut a);
+/// 1i32.encode(&mut b);
+/// assert!(a < b);
+/// assert_eq!(i32::decode(&a), -1);
+/// ```
+pub trait ArtKey: Sized {
+    /// The length of the encoded key in bytes.
+    const LEN: usize;
+
+    /// Writes the encoded key to `out`, the length of `out` is exactly `LEN`.
+    fn encode(&self, out: &mut [u8]);
+
+    /// Decodes the key from `bytes`, which was written by `encode`.
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_unsigned_key {
+    ($($t:ty),*) => {
+        $(
+            impl ArtKey for $t {
+                const LEN: usize = std::mem::size_of::<$t>();
+
+                #[inline]
+                fn encode(&self, out: &mut [u8]) {
+                    out.copy_from_slice(&self.to_be_bytes());
+                }
+
+                #[inline]
+                fn decode(bytes: &[u8]) -> Self {
+                    <$t>::from_be_bytes(bytes.try_into().unwrap())
+                }
+            }
+        )*
+    };
 }
+
+macro_rules! impl_signed_key {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl ArtKey for $t {
+                const LEN: usize = std::mem::size_of::<$t>();
+
+                #[inline]
+                fn encode(&self, out: &mut [u8]) {
+                    // flip the sign bit so that negative values sort before positive ones
+                    let v = (*self as $u) ^ (1 << (<$u>::BITS - 1));
+                    out.copy_from_slice(&v.to_be_bytes());
+                }
+
+                #[inline]
+                fn decode(bytes: &[u8]) -> Self {
+                    let v = <$u>::from_be_bytes(bytes.try_into().unwrap());
+                    (v ^ (1 << (<$u>::BITS - 1))) as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned_key!(u8, u16, u32, u64, u128, usize);
+impl_signed_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+impl ArtKey for bool {
+    const LEN: usize = 1;
+
+    #[inline]
+    fn encode(&self, out: &mut [u8]) {
+        out[0] = *self as u8;
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+impl ArtKey for char {
+    const LEN: usize = 4;
+
+    #[inline]
+    fn encode(&self, out: &mut [u8]) {
+        (*self as u32).encode(out);
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Self {
+        char::from_u32(u32::decode(bytes)).expect("invalid char encoding")
+    }
+}
+
+macro_rules! impl_tuple_key {
+    ($($name:ident)+) => {
+        impl<$($name: ArtKey),+> ArtKey for ($($name,)+) {
+            const LEN: usize = 0 $(+ $name::LEN)+;
+
+            #[inline]
+            #[allow(non_snake_case)]
+            fn encode(&self, out: &mut [u8]) {
+                let ($($name,)+) = self;
+                let mut offset = 0;
+                $(
+                    $name.encode(&mut out[offset..offset + $name::LEN]);
+                    offset += $name::LEN;
+                )+
+                debug_assert_eq!(offset, Self::LEN);
+            }
+
+            #[inline]
+            fn decode(bytes: &[u8]) -> Self {
+                let mut offset = 0;
+                ($(
+                    {
+                        offset += $name::LEN;
+                        $name::decode(&bytes[offset - $name::LEN..offset])
+                    },
+                )+)
+            }
+        }
+    };
+}
+
+impl_tuple_key!(A);
+impl_tuple_key!(A B);
+impl_tuple_key!(A B C);
+impl_tuple_key!(A B C D);
+impl_tuple_key!(A B C D E);
+impl_tuple_key!(A B C D E F);
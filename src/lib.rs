@@ -3,7 +3,14 @@
 #![allow(clippy::len_without_is_empty)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+mod art_bytes;
+mod art_map;
+mod art_pair;
+mod art_set;
 mod base_node;
+mod bulk_load;
+mod bytes_tree;
+mod entry;
 mod error;
 mod key;
 mod lock;
@@ -12,9 +19,11 @@ mod node_256;
 mod node_4;
 mod node_48;
 mod node_ptr;
+mod transaction;
 mod tree;
 mod utils;
 
+mod range_iter;
 mod range_scan;
 
 #[cfg(feature = "stats")]
@@ -23,12 +32,25 @@ mod stats;
 #[cfg(test)]
 mod tests;
 
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
-use error::OOMError;
-use key::RawKey;
-use key::UsizeKey;
-use tree::RawTree;
+pub use art_bytes::ArtBytes;
+pub use art_map::ArtMap;
+pub use art_pair::ArtPair;
+pub use art_set::ArtSet;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+use error::ArtError;
+pub use error::{Contended, OOMError, TryInsertError};
+use key::FixedKey;
+use range_iter::RangeIter;
+pub use transaction::Transaction;
+use tree::{RawTree, GET_MANY_GROUP};
+
+pub use key::ArtKey;
+pub use utils::{BackoffPolicy, RetryBudget};
 
 /// Types needed to safely access shared data concurrently.
 pub mod epoch {
@@ -75,22 +97,36 @@ impl Allocator for DefaultAllocator {
 
 /// The adaptive radix tree.
 /// Currently we only support only one type of memory, the allocator must return the type of memory requested.
+///
+/// Keys can be any [ArtKey], they are ordered by their natural order.
+/// `K_LEN` is the depth of the tree, i.e., the encoded key length in bytes, it must be at least [ArtKey::LEN].
+/// Shorter keys are zero-padded at the front: the padding is a compressed path above the keys,
+/// their last level nodes are as full as with a matching `K_LEN`, e.g., 4 for `u32` and 16 for `u128`,
+/// which saves a few nodes on the path:
+///
+/// ```
+/// use congee::{Art, DefaultAllocator};
+/// let tree = Art::<u128, usize, _, 16>::new(DefaultAllocator {});
+/// let guard = tree.pin();
+///
+/// tree.insert(u128::MAX, 42, &guard).unwrap();
+/// assert_eq!(tree.get(&u128::MAX, &guard), Some(42));
+/// ```
 pub struct Art<
-    K: Clone + From<usize>,
+    K: ArtKey,
     V: Clone + From<usize>,
     A: Allocator + Clone + 'static = DefaultAllocator,
+    const K_LEN: usize = 8,
 > where
-    usize: From<K>,
     usize: From<V>,
 {
-    inner: RawTree<UsizeKey, A>,
+    inner: RawTree<FixedKey<K_LEN>, usize, A, K_LEN>,
     pt_key: PhantomData<K>,
     pt_val: PhantomData<V>,
 }
 
-impl<K: Clone + From<usize>, V: Clone + From<usize>> Default for Art<K, V>
+impl<K: ArtKey, V: Clone + From<usize>> Default for Art<K, V>
 where
-    usize: From<K>,
     usize: From<V>,
 {
     fn default() -> Self {
@@ -98,9 +134,9 @@ where
     }
 }
 
-impl<K: Clone + From<usize>, V: Clone + From<usize>, A: Allocator + Clone + Send> Art<K, V, A>
+impl<K: ArtKey, V: Clone + From<usize>, A: Allocator + Clone + Send, const K_LEN: usize>
+    Art<K, V, A, K_LEN>
 where
-    usize: From<K>,
     usize: From<V>,
 {
     /// Returns a copy of the value corresponding to the key.
@@ -117,11 +153,49 @@ where
     /// ```
     #[inline]
     pub fn get(&self, key: &K, guard: &epoch::Guard) -> Option<V> {
-        let key = UsizeKey::key_from(usize::from(key.clone()));
+        let key = FixedKey::new(key);
         let v = self.inner.get(&key, guard)?;
         Some(V::from(v))
     }
 
+    /// Looks up a batch of keys, sets `values[i]` to a copy of the value of `keys[i]`.
+    ///
+    /// The lookups are interleaved, the nodes a lookup reads next are prefetched while the others proceed.
+    /// Sorted keys walk their common path down the tree once.
+    ///
+    /// # Panics
+    /// Panics if `keys` and `values` differ in length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard);
+    /// tree.insert(3, 44, &guard);
+    /// let mut values = [None; 3];
+    /// tree.get_many(&[1, 2, 3], &mut values, &guard);
+    /// assert_eq!(values, [Some(42), None, Some(44)]);
+    /// ```
+    pub fn get_many(&self, keys: &[K], values: &mut [Option<V>], guard: &epoch::Guard) {
+        assert_eq!(keys.len(), values.len(), "keys and values differ in length");
+        for (keys, values) in keys
+            .chunks(GET_MANY_GROUP)
+            .zip(values.chunks_mut(GET_MANY_GROUP))
+        {
+            let encoded: [FixedKey<K_LEN>; GET_MANY_GROUP] =
+                std::array::from_fn(|i| keys.get(i).map(FixedKey::new).unwrap_or_default());
+            let mut found = [None; GET_MANY_GROUP];
+            self.inner
+                .get_many(&encoded[..keys.len()], &mut found[..keys.len()], guard);
+            for (v, found) in values.iter_mut().zip(found) {
+                *v = found.map(V::from);
+            }
+        }
+    }
+
     /// Enters an epoch.
     /// Note: this can be expensive, try to reuse it.
     ///
@@ -147,13 +221,137 @@ where
     /// ```
     #[inline]
     pub fn new(allocator: A) -> Self {
+        Self::with_backoff(allocator, BackoffPolicy::default())
+    }
+
+    /// Create an empty [Art] tree, whose operations wait with `backoff` before they restart after a conflict
+    /// with a concurrent writer. [Art::new] uses [BackoffPolicy::Spin].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, BackoffPolicy, DefaultAllocator};
+    /// use std::time::Duration;
+    /// let tree = Art::<usize, usize>::with_backoff(
+    ///     DefaultAllocator {},
+    ///     BackoffPolicy::Park { max_park: Duration::from_millis(1) },
+    /// );
+    /// let guard = tree.pin();
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// assert_eq!(tree.get(&1, &guard), Some(42));
+    /// ```
+    #[inline]
+    pub fn with_backoff(allocator: A, backoff: BackoffPolicy) -> Self {
+        Self::from_raw(RawTree::with_backoff(allocator, backoff))
+    }
+
+    /// Builds an [Art] tree from key-value pairs in ascending key order.
+    ///
+    /// Unlike inserting the keys one by one, every node is created at its final size and nothing is locked.
+    /// The value of a repeated key replaces the previous one.
+    ///
+    /// # Panics
+    /// Panics if the keys are not sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, DefaultAllocator};
+    /// let tree = Art::<usize, usize>::from_sorted_iter((0..1000).map(|k| (k, k * 2)), DefaultAllocator {})
+    ///     .unwrap();
+    /// let guard = tree.pin();
+    /// assert_eq!(tree.len(), 1000);
+    /// assert_eq!(tree.get(&500, &guard), Some(1000));
+    /// ```
+    pub fn from_sorted_iter(
+        iter: impl IntoIterator<Item = (K, V)>,
+        allocator: A,
+    ) -> Result<Self, OOMError> {
+        let iter = iter
+            .into_iter()
+            .map(|(k, v)| (FixedKey::new(&k), usize::from(v)));
+        RawTree::from_sorted(iter, allocator, BackoffPolicy::default())
+            .map(Self::from_raw)
+            .map_err(|_| OOMError::new())
+    }
+
+    /// Same as [Art::from_sorted_iter], with the tree built by all available threads.
+    ///
+    /// The pairs are split at the first key byte in which the keys differ,
+    /// every thread builds the subtrees of its part of the key space.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, DefaultAllocator};
+    /// let items: Vec<(usize, usize)> = (0..1000).map(|k| (k << 48, k)).collect();
+    /// let tree = Art::<usize, usize>::par_from_sorted_slice(&items, DefaultAllocator {}).unwrap();
+    /// let guard = tree.pin();
+    /// assert_eq!(tree.len(), 1000);
+    /// assert_eq!(tree.get(&(500 << 48), &guard), Some(500));
+    /// ```
+    pub fn par_from_sorted_slice(items: &[(K, V)], allocator: A) -> Result<Self, OOMError>
+    where
+        K: Sync,
+        V: Sync,
+        A: Sync,
+    {
+        let entry = |(k, v): &(K, V)| (FixedKey::new(k), usize::from(v.clone()));
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        RawTree::from_sorted_par(items, entry, threads, allocator, BackoffPolicy::default())
+            .map(Self::from_raw)
+            .map_err(|_| OOMError::new())
+    }
+
+    fn from_raw(inner: RawTree<FixedKey<K_LEN>, usize, A, K_LEN>) -> Self {
+        const {
+            assert!(
+                K_LEN > 0 && K::LEN <= K_LEN,
+                "the key is longer than K_LEN bytes"
+            )
+        };
         Art {
-            inner: RawTree::new(allocator),
+            inner,
             pt_key: PhantomData,
             pt_val: PhantomData,
         }
     }
 
+    /// Returns the number of keys in the tree.
+    ///
+    /// It is exact if there are no concurrent updates, otherwise it may or may not count the concurrent ones.
+    /// The count is maintained on every insert and remove, calling it does not walk the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::<usize, usize>::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert!(tree.is_empty());
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// assert_eq!(tree.len(), 1);
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the tree contains no keys, see [Art::len].
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A cheaper variant of [Art::len] that only reads the merged total of the key counter.
+    /// The counter is split into at most 64 stripes shared by the threads, each buffering up to 64 updates
+    /// before merging them, so the result may lag [Art::len] by up to 64 updates per stripe.
+    #[inline]
+    pub fn approximate_len(&self) -> usize {
+        self.inner.approximate_len()
+    }
+
     /// Removes key-value pair from the tree, returns the value if the key was found.
     ///
     /// # Examples
@@ -170,12 +368,80 @@ where
     /// ```
     #[inline]
     pub fn remove(&self, k: &K, guard: &epoch::Guard) -> Option<V> {
-        let key = UsizeKey::key_from(usize::from(k.clone()));
+        let key = FixedKey::new(k);
         let (old, new) = self.inner.compute_if_present(&key, &mut |_v| None, guard)?;
         debug_assert!(new.is_none());
         Some(V::from(old))
     }
 
+    /// Removes every key within the range, returns the number of removed keys.
+    ///
+    /// Subtrees whose keys are all within the range are unlinked at once and freed as a unit,
+    /// only the keys in the nodes that straddle a bound are removed one by one.
+    /// It is not atomic: keys inserted into the range concurrently may or may not be removed.
+    /// Like [Art::range], an empty or inverted range removes nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i, &guard).unwrap();
+    /// }
+    /// assert_eq!(tree.remove_range(..900, &guard), 900);
+    /// assert_eq!(tree.len(), 100);
+    /// assert_eq!(tree.first_key_value(&guard), Some((900, 900)));
+    /// ```
+    pub fn remove_range(&self, range: impl RangeBounds<K>, guard: &epoch::Guard) -> usize {
+        let (start, end) = FixedKey::range(&range);
+        let mut removed = self
+            .inner
+            .remove_covered(start.as_ref(), end.as_ref(), guard);
+        for (k, _) in RangeIter::new(&self.inner, (start, end), false, guard) {
+            if self
+                .inner
+                .compute_if_present(&k, &mut |_| None, guard)
+                .is_some()
+            {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Removes every key for which `f` returns false, returns the number of removed keys.
+    ///
+    /// `f` sees the current value of the key, it may be called more than once for a key
+    /// if the removal is retried. Keys inserted concurrently may or may not be visited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// for i in 0..100 {
+    ///     tree.insert(i, i * 2, &guard).unwrap();
+    /// }
+    /// assert_eq!(tree.retain(|k, v| k % 2 == 0 && v < 100, &guard), 75);
+    /// assert_eq!(tree.len(), 25);
+    /// assert_eq!(tree.get(&48, &guard), Some(96));
+    /// ```
+    pub fn retain(&self, mut f: impl FnMut(K, V) -> bool, guard: &epoch::Guard) -> usize {
+        let mut removed = 0;
+        for (k, _) in RangeIter::new(&self.inner, FixedKey::range::<K>(&(..)), false, guard) {
+            let mut keep = |v: usize| f(k.decode(), V::from(v)).then_some(v);
+            if let Some((_, None)) = self.inner.compute_if_present(&k, &mut keep, guard) {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
     /// Insert a key-value pair to the tree, returns the previous value if the key was already present.
     ///
     /// # Examples
@@ -192,16 +458,20 @@ where
     /// ```
     #[inline]
     pub fn insert(&self, k: K, v: V, guard: &epoch::Guard) -> Result<Option<V>, OOMError> {
-        let key = UsizeKey::key_from(usize::from(k));
+        let key = FixedKey::new(&k);
         let val = self.inner.insert(key, usize::from(v), guard);
         val.map(|inner| inner.map(|v| V::from(v)))
     }
 
-    /// Scan the tree with the range of [start, end], write the result to the
-    /// `result` buffer.
+    /// Scan the tree with the key `range`, write the result to the `result` buffer in ascending key order.
     /// It scans the length of `result` or the number of the keys within the range, whichever is smaller;
     /// returns the number of the keys scanned.
     ///
+    /// The range has the same meaning as in [BTreeMap::range](std::collections::BTreeMap::range):
+    /// `a..b` is [a, b), `a..=b` is [a, b], `a..` and `..b` are unbounded on one side and `..` covers every key.
+    /// Unlike [BTreeMap::range](std::collections::BTreeMap::range), an empty or inverted range, e.g., `5..5`
+    /// or `6..=5`, does not panic but scans nothing.
+    ///
     /// # Examples
     ///
     /// ```
@@ -210,32 +480,37 @@ where
     /// let guard = tree.pin();
     ///
     /// tree.insert(1, 42, &guard);
+    /// tree.insert(usize::MAX, 7, &guard);
     ///
-    /// let low_key = 1;
-    /// let high_key = 2;
     /// let mut result = [(0, 0); 2];
-    /// let scanned = tree.range(&low_key, &high_key, &mut result, &guard);
+    /// let scanned = tree.range(1..2, &mut result, &guard);
     /// assert_eq!(scanned, 1);
     /// assert_eq!(result, [(1, 42), (0, 0)]);
+    ///
+    /// let scanned = tree.range(2.., &mut result, &guard);
+    /// assert_eq!(&result[..scanned], &[(usize::MAX, 7)]);
     /// ```
     #[inline]
     pub fn range(
         &self,
-        start: &K,
-        end: &K,
-        result: &mut [(usize, usize)],
+        range: impl RangeBounds<K>,
+        result: &mut [(K, V)],
         guard: &epoch::Guard,
     ) -> usize {
-        let start = UsizeKey::key_from(usize::from(start.clone()));
-        let end = UsizeKey::key_from(usize::from(end.clone()));
-        self.inner.range(&start, &end, result, guard)
+        self.inner.range(
+            &FixedKey::range(&range),
+            result,
+            &mut |slot, key, value| *slot = (FixedKey::<K_LEN>::decode_bytes(key), V::from(value)),
+            guard,
+        )
     }
 
-    /// Compute and update the value if the key presents in the tree.
-    /// Returns the (old, new) value
+    /// Returns an iterator over the key-value pairs within `range`, in ascending key order.
+    /// The range has the same meaning as in [Art::range].
     ///
-    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
-    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    /// Unlike [Art::range], there is no result buffer: the keys are scanned lazily in batches,
+    /// each batch continues from the key after the last returned one.
+    /// Keys inserted or removed during the iteration may or may not be returned.
     ///
     /// # Examples
     ///
@@ -244,157 +519,1095 @@ where
     /// let tree = Art::default();
     /// let guard = tree.pin();
     ///
-    /// tree.insert(1, 42, &guard);
-    /// let old = tree.compute_if_present(&1, |v| Some(v+1), &guard).unwrap();
-    /// assert_eq!(old, (42, Some(43)));
-    /// let val = tree.get(&1, &guard).unwrap();
-    /// assert_eq!(val, 43);
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i * 2, &guard).unwrap();
+    /// }
+    /// let mut iter = tree.range_iter(10..500, &guard);
+    /// assert_eq!(iter.next(), Some((10, 20)));
+    /// assert_eq!(iter.count(), 489);
+    /// assert_eq!(tree.range_iter(.., &guard).count(), 1000);
+    /// ```
+    pub fn range_iter<'a>(
+        &'a self,
+        range: impl RangeBounds<K>,
+        guard: &'a epoch::Guard,
+    ) -> impl Iterator<Item = (K, V)> + 'a {
+        RangeIter::new(&self.inner, FixedKey::range(&range), false, guard)
+            .map(|(k, v)| (k.decode(), V::from(v)))
+    }
+
+    /// Same as [Art::range], but scans in descending key order:
+    /// `result` is filled with the largest keys within `range`, the largest first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// for i in 0..10 {
+    ///     tree.insert(i, i * 2, &guard).unwrap();
+    /// }
+    /// let mut result = [(0, 0); 3];
+    /// let scanned = tree.range_rev(2..8, &mut result, &guard);
+    /// assert_eq!(scanned, 3);
+    /// assert_eq!(result, [(7, 14), (6, 12), (5, 10)]);
     /// ```
     #[inline]
-    pub fn compute_if_present<F>(
+    pub fn range_rev(
         &self,
-        key: &K,
-        mut f: F,
+        range: impl RangeBounds<K>,
+        result: &mut [(K, V)],
         guard: &epoch::Guard,
-    ) -> Option<(usize, Option<usize>)>
-    where
-        F: FnMut(usize) -> Option<usize>,
-    {
-        let u_key = UsizeKey::key_from(usize::from(key.clone()));
+    ) -> usize {
+        self.inner.range_rev(
+            &FixedKey::range(&range),
+            result,
+            &mut |slot, key, value| *slot = (FixedKey::<K_LEN>::decode_bytes(key), V::from(value)),
+            guard,
+        )
+    }
 
-        self.inner.compute_if_present(&u_key, &mut f, guard)
+    /// Same as [Art::range_iter], but iterates in descending key order,
+    /// each batch continues from the key before the last returned one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i * 2, &guard).unwrap();
+    /// }
+    /// let mut iter = tree.range_iter_rev(10..500, &guard);
+    /// assert_eq!(iter.next(), Some((499, 998)));
+    /// assert_eq!(iter.last(), Some((10, 20)));
+    /// ```
+    pub fn range_iter_rev<'a>(
+        &'a self,
+        range: impl RangeBounds<K>,
+        guard: &'a epoch::Guard,
+    ) -> impl Iterator<Item = (K, V)> + 'a {
+        RangeIter::new(&self.inner, FixedKey::range(&range), true, guard)
+            .map(|(k, v)| (k.decode(), V::from(v)))
     }
 
-    /// Compute or insert the value if the key is not in the tree.
-    /// Returns the Option(old) value
+    /// Returns an iterator over the key-value pairs whose first `prefix_len` encoded key bytes
+    /// are the same as `prefix`'s, in ascending key order. The rest of `prefix` is ignored.
     ///
-    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
-    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    /// This is useful for composite keys, e.g., all rows of a tenant with `(tenant_id, row_id)` keys.
+    /// The keys are scanned lazily in batches like [Art::range_iter].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is larger than the key length, i.e., [ArtKey::LEN].
     ///
     /// # Examples
     ///
     /// ```
     /// use congee::Art;
-    /// let tree = Art::default();
+    /// let tree = Art::<(u32, u32), usize>::default();
     /// let guard = tree.pin();
     ///
-    /// tree.insert(1, 42, &guard);
-    /// let old = tree.compute_or_insert(1, |v| v.unwrap() + 1, &guard).unwrap().unwrap();
-    /// assert_eq!(old, 42);
-    /// let val = tree.get(&1, &guard).unwrap();
-    /// assert_eq!(val, 43);
+    /// tree.insert((1, 5), 15, &guard).unwrap();
+    /// tree.insert((2, 1), 21, &guard).unwrap();
+    /// tree.insert((2, 9), 29, &guard).unwrap();
+    /// tree.insert((3, 0), 30, &guard).unwrap();
     ///
-    /// let old = tree.compute_or_insert(2, |v| {
-    ///     assert!(v.is_none());
-    ///     2
-    /// }, &guard).unwrap();
-    /// assert!(old.is_none());
-    /// let val = tree.get(&2, &guard).unwrap();
-    /// assert_eq!(val, 2);
+    /// let rows: Vec<_> = tree.prefix_iter(&(2, 0), 4, &guard).collect();
+    /// assert_eq!(rows, vec![((2, 1), 21), ((2, 9), 29)]);
     /// ```
-    pub fn compute_or_insert<F>(
-        &self,
-        key: K,
-        mut f: F,
-        guard: &epoch::Guard,
-    ) -> Result<Option<V>, OOMError>
-    where
-        F: FnMut(Option<usize>) -> usize,
-    {
-        let u_key = UsizeKey::key_from(usize::from(key));
-        let u_val = self.inner.compute_or_insert(u_key, &mut f, guard)?;
-        Ok(u_val.map(|v| V::from(v)))
+    pub fn prefix_iter<'a>(
+        &'a self,
+        prefix: &K,
+        prefix_len: usize,
+        guard: &'a epoch::Guard,
+    ) -> impl Iterator<Item = (K, V)> + 'a {
+        assert!(prefix_len <= K::LEN, "the prefix is longer than the key");
+        let range = FixedKey::prefix_range(prefix, prefix_len);
+        RangeIter::new(&self.inner, range, false, guard).map(|(k, v)| (k.decode(), V::from(v)))
     }
 
-    /// Display the internal node statistics
-    #[cfg(feature = "stats")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "stats")))]
-    pub fn stats(&self) -> stats::NodeStats {
-        self.inner.stats()
+    /// Returns the number of keys whose first `prefix_len` encoded key bytes are the same as `prefix`'s,
+    /// see [Art::prefix_iter].
+    ///
+    /// It descends to the subtree of the prefix and only counts that subtree, the keys are not decoded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is larger than the key length, i.e., [ArtKey::LEN].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::<(u32, u32), usize>::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert((1, 5), 15, &guard).unwrap();
+    /// tree.insert((2, 1), 21, &guard).unwrap();
+    /// tree.insert((2, 9), 29, &guard).unwrap();
+    ///
+    /// assert_eq!(tree.count_prefix(&(2, 0), 4, &guard), 2);
+    /// assert_eq!(tree.count_prefix(&(4, 0), 4, &guard), 0);
+    /// ```
+    pub fn count_prefix(&self, prefix: &K, prefix_len: usize, guard: &epoch::Guard) -> usize {
+        assert!(prefix_len <= K::LEN, "the prefix is longer than the key");
+        let prefix = FixedKey::<K_LEN>::new(prefix);
+        self.inner
+            .count_prefix(prefix.prefix_bytes::<K>(prefix_len), guard)
     }
 
-    /// Get a random value from the tree, perform the transformation `f`.
-    /// This is useful for randomized algorithms.
+    /// Returns the key-value pair with the smallest key, `None` if the tree is empty.
     ///
-    /// `f` takes key and value as input and return the new value, |key: usize, value: usize| -> usize.
+    /// # Examples
     ///
-    /// Returns (key, old_value, new_value)
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
     ///
-    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
-    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    /// assert_eq!(tree.first_key_value(&guard), None);
+    /// tree.insert(3, 30, &guard).unwrap();
+    /// tree.insert(1, 10, &guard).unwrap();
+    /// assert_eq!(tree.first_key_value(&guard), Some((1, 10)));
+    /// ```
+    #[inline]
+    pub fn first_key_value(&self, guard: &epoch::Guard) -> Option<(K, V)> {
+        self.seek(Bound::Unbounded, false, guard)
+    }
+
+    /// Returns the key-value pair with the largest key, `None` if the tree is empty.
+    ///
+    /// # Examples
     ///
-    /// # Examples:
     /// ```
     /// use congee::Art;
     /// let tree = Art::default();
     /// let guard = tree.pin();
-    /// tree.insert(1, 42, &guard);
-    /// let mut rng = rand::thread_rng();
-    /// let (key, old_v, new_v) = tree.compute_on_random(&mut rng, |k, v| {
-    ///     assert_eq!(k, 1);
-    ///     assert_eq!(v, 42);
-    ///     v + 1
-    /// }, &guard).unwrap();
-    /// assert_eq!(key, 1);
-    /// assert_eq!(old_v, 42);
-    /// assert_eq!(new_v, 43);
+    ///
+    /// tree.insert(3, 30, &guard).unwrap();
+    /// tree.insert(1, 10, &guard).unwrap();
+    /// assert_eq!(tree.last_key_value(&guard), Some((3, 30)));
     /// ```
-    #[cfg(feature = "db_extension")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "db_extension")))]
-    pub fn compute_on_random(
-        &self,
-        rng: &mut impl rand::Rng,
-        mut f: impl FnMut(K, V) -> V,
-        guard: &epoch::Guard,
-    ) -> Option<(K, V, V)> {
-        let mut remapped = |key: usize, value: usize| -> usize {
-            let v = f(K::from(key), V::from(value));
-            usize::from(v)
-        };
-        let (key, old_v, new_v) = self.inner.compute_on_random(rng, &mut remapped, guard)?;
-        Some((K::from(key), V::from(old_v), V::from(new_v)))
+    #[inline]
+    pub fn last_key_value(&self, guard: &epoch::Guard) -> Option<(K, V)> {
+        self.seek(Bound::Unbounded, true, guard)
     }
 
-    /// Update the value if the old value matches with the new one.
-    /// Returns the current value.
+    /// Returns the key-value pair with the smallest key that is greater than or equal to `k`.
+    ///
+    /// # Examples
     ///
-    /// # Examples:
     /// ```
     /// use congee::Art;
     /// let tree = Art::default();
     /// let guard = tree.pin();
-    /// tree.insert(1, 42, &guard);
     ///
+    /// tree.insert(2, 20, &guard).unwrap();
+    /// tree.insert(4, 40, &guard).unwrap();
+    /// assert_eq!(tree.lower_bound(&2, &guard), Some((2, 20)));
+    /// assert_eq!(tree.lower_bound(&3, &guard), Some((4, 40)));
+    /// assert_eq!(tree.lower_bound(&5, &guard), None);
+    /// ```
+    #[inline]
+    pub fn lower_bound(&self, k: &K, guard: &epoch::Guard) -> Option<(K, V)> {
+        self.seek(Bound::Included(k), false, guard)
+    }
+
+    /// Returns the key-value pair with the largest key that is less than or equal to `k`.
+    ///
+    /// Note that this is the mirror of [Art::lower_bound], not C++'s `upper_bound` (which is [Art::successor]).
+    ///
+    /// # Examples
     ///
-    /// let v = tree.compare_exchange(&1, &42, Some(43), &guard).unwrap();
-    /// assert_eq!(v, Some(43));
     /// ```
-    pub fn compare_exchange(
-        &self,
-        key: &K,
-        old: &V,
-        new: Option<V>,
-        guard: &epoch::Guard,
-    ) -> Result<Option<V>, Option<V>> {
-        let u_key = UsizeKey::key_from(usize::from(key.clone()));
-        let new_v = new.clone().map(|v| usize::from(v));
-        let mut fc = |v: usize| -> Option<usize> {
-            if v == usize::from(old.clone()) {
-                new_v
-            } else {
-                Some(v)
-            }
-        };
-        let v = self.inner.compute_if_present(&u_key, &mut fc, guard);
-        match v {
-            Some((actual_old, actual_new)) => {
-                if actual_old == usize::from(old.clone()) && actual_new == new_v {
-                    Ok(new)
-                } else {
-                    Err(actual_new.map(|v| V::from(v)))
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(2, 20, &guard).unwrap();
+    /// tree.insert(4, 40, &guard).unwrap();
+    /// assert_eq!(tree.upper_bound(&4, &guard), Some((4, 40)));
+    /// assert_eq!(tree.upper_bound(&3, &guard), Some((2, 20)));
+    /// assert_eq!(tree.upper_bound(&1, &guard), None);
+    /// ```
+    #[inline]
+    pub fn upper_bound(&self, k: &K, guard: &epoch::Guard) -> Option<(K, V)> {
+        self.seek(Bound::Included(k), true, guard)
+    }
+
+    /// Returns the key-value pair with the smallest key that is strictly greater than `k`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(2, 20, &guard).unwrap();
+    /// tree.insert(4, 40, &guard).unwrap();
+    /// assert_eq!(tree.successor(&2, &guard), Some((4, 40)));
+    /// assert_eq!(tree.successor(&4, &guard), None);
+    /// ```
+    #[inline]
+    pub fn successor(&self, k: &K, guard: &epoch::Guard) -> Option<(K, V)> {
+        self.seek(Bound::Excluded(k), false, guard)
+    }
+
+    /// Returns the key-value pair with the largest key that is strictly less than `k`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(2, 20, &guard).unwrap();
+    /// tree.insert(4, 40, &guard).unwrap();
+    /// assert_eq!(tree.predecessor(&4, &guard), Some((2, 20)));
+    /// assert_eq!(tree.predecessor(&2, &guard), None);
+    /// ```
+    #[inline]
+    pub fn predecessor(&self, k: &K, guard: &epoch::Guard) -> Option<(K, V)> {
+        self.seek(Bound::Excluded(k), true, guard)
+    }
+
+    fn seek(&self, bound: Bound<&K>, reverse: bool, guard: &epoch::Guard) -> Option<(K, V)> {
+        let bound = bound.map(FixedKey::new);
+        let (key, v) = self.inner.seek(bound.as_ref(), reverse, guard)?;
+        Some((FixedKey::<K_LEN>::decode_bytes(key.as_bytes()), V::from(v)))
+    }
+
+    /// Returns the [Entry] of the key, for in-place get-or-insert and update workflows.
+    ///
+    /// See [Entry] for how its operations behave under concurrent updates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, Entry};
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// match tree.entry(1, &guard) {
+    ///     Entry::Occupied(_) => unreachable!(),
+    ///     Entry::Vacant(e) => assert_eq!(e.insert(42).unwrap(), None),
+    /// }
+    /// let counter = tree.entry(1, &guard).and_modify(|v| v + 1).or_insert(0).unwrap();
+    /// assert_eq!(counter, 43);
+    /// ```
+    #[inline]
+    pub fn entry<'a>(&'a self, k: K, guard: &'a epoch::Guard) -> Entry<'a, K, V, A, K_LEN> {
+        Entry::new(self, k, guard)
+    }
+
+    /// Runs `f` as a transaction over several keys: its reads and writes take effect as one atomic step.
+    ///
+    /// The writes of [Transaction::put] and [Transaction::remove] are buffered and visible to later
+    /// [Transaction::get]s of the same transaction. On commit, the nodes holding the written keys are locked
+    /// and the nodes of every read are validated; if any of them has changed, the writes are discarded
+    /// and `f` runs again. Returns the result of the attempt that committed.
+    ///
+    /// Since an attempt may see the updates of concurrent commits between its reads,
+    /// `f` should not rely on invariants across keys before it commits, e.g., by panicking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// tree.insert(1, 10, &guard).unwrap();
+    ///
+    /// // move 3 from key 1 to key 2
+    /// let moved = tree
+    ///     .transaction(
+    ///         |tx| {
+    ///             let from = tx.get(&1).unwrap_or(0);
+    ///             let to = tx.get(&2).unwrap_or(0);
+    ///             let amount = std::cmp::min(from, 3);
+    ///             tx.put(1, from - amount);
+    ///             tx.put(2, to + amount);
+    ///             amount
+    ///         },
+    ///         &guard,
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(moved, 3);
+    /// assert_eq!(tree.get(&1, &guard), Some(7));
+    /// assert_eq!(tree.get(&2, &guard), Some(3));
+    /// ```
+    pub fn transaction<R>(
+        &self,
+        mut f: impl FnMut(&mut Transaction<'_, K, V, A, K_LEN>) -> R,
+        guard: &epoch::Guard,
+    ) -> Result<R, OOMError> {
+        let backoff = self.inner.backoff();
+        loop {
+            let mut tx = Transaction::new(&self.inner);
+            let r = f(&mut tx);
+            match tx.commit(guard) {
+                Ok(()) => return Ok(r),
+                Err(ArtError::Oom) => return Err(OOMError::new()),
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    /// Moves the value of `old` to `new` as one linearizable step: no reader sees the value under both keys
+    /// or under neither. The value of `new` is replaced if present.
+    /// Returns the moved value, `None` if `old` is absent, then nothing changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// tree.insert(1, 42, &guard).unwrap();
+    ///
+    /// assert_eq!(tree.rename(&1, 2, &guard).unwrap(), Some(42));
+    /// assert_eq!(tree.get(&1, &guard), None);
+    /// assert_eq!(tree.get(&2, &guard), Some(42));
+    /// assert_eq!(tree.rename(&1, 3, &guard).unwrap(), None);
+    /// ```
+    pub fn rename(&self, old: &K, new: K, guard: &epoch::Guard) -> Result<Option<V>, OOMError> {
+        let moved = self
+            .inner
+            .rename(&FixedKey::new(old), &FixedKey::new(&new), guard)?;
+        Ok(moved.map(V::from))
+    }
+
+    /// Compute and update the value if the key presents in the tree.
+    /// Returns the (old, new) value
+    ///
+    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
+    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard);
+    /// let old = tree.compute_if_present(&1, |v| Some(v+1), &guard).unwrap();
+    /// assert_eq!(old, (42, Some(43)));
+    /// let val = tree.get(&1, &guard).unwrap();
+    /// assert_eq!(val, 43);
+    /// ```
+    #[inline]
+    pub fn compute_if_present<F>(
+        &self,
+        key: &K,
+        mut f: F,
+        guard: &epoch::Guard,
+    ) -> Option<(usize, Option<usize>)>
+    where
+        F: FnMut(usize) -> Option<usize>,
+    {
+        let u_key = FixedKey::new(key);
+
+        self.inner.compute_if_present(&u_key, &mut f, guard)
+    }
+
+    /// Compute or insert the value if the key is not in the tree.
+    /// Returns the Option(old) value
+    ///
+    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
+    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard);
+    /// let old = tree.compute_or_insert(1, |v| v.unwrap() + 1, &guard).unwrap().unwrap();
+    /// assert_eq!(old, 42);
+    /// let val = tree.get(&1, &guard).unwrap();
+    /// assert_eq!(val, 43);
+    ///
+    /// let old = tree.compute_or_insert(2, |v| {
+    ///     assert!(v.is_none());
+    ///     2
+    /// }, &guard).unwrap();
+    /// assert!(old.is_none());
+    /// let val = tree.get(&2, &guard).unwrap();
+    /// assert_eq!(val, 2);
+    /// ```
+    pub fn compute_or_insert<F>(
+        &self,
+        key: K,
+        mut f: F,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, OOMError>
+    where
+        F: FnMut(Option<usize>) -> usize,
+    {
+        let u_key = FixedKey::new(&key);
+        let u_val = self.inner.compute_or_insert(u_key, &mut f, guard)?;
+        Ok(u_val.map(|v| V::from(v)))
+    }
+
+    /// Same as [Art::get], but gives up with [Contended] once the `budget` runs out
+    /// instead of retrying until it succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, RetryBudget};
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// let budget = RetryBudget::restarts(8);
+    /// assert_eq!(tree.try_get(&1, budget, &guard).unwrap(), Some(42));
+    /// ```
+    #[inline]
+    pub fn try_get(
+        &self,
+        key: &K,
+        budget: RetryBudget,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, Contended> {
+        let v = self
+            .inner
+            .try_get(&FixedKey::new(key), &budget, guard)
+            .map_err(|_| Contended::new())?;
+        Ok(v.map(|v| V::from(v)))
+    }
+
+    /// Same as [Art::insert], but gives up with [TryInsertError::Contended] once the `budget` runs out
+    /// instead of retrying until it succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, RetryBudget};
+    /// use std::time::Duration;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// let budget = RetryBudget::timeout(Duration::from_millis(1));
+    /// assert_eq!(tree.try_insert(1, 42, budget, &guard).unwrap(), None);
+    /// ```
+    #[inline]
+    pub fn try_insert(
+        &self,
+        k: K,
+        v: V,
+        budget: RetryBudget,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, TryInsertError> {
+        let v = usize::from(v);
+        let old =
+            self.inner
+                .try_compute_or_insert(FixedKey::new(&k), &mut |_| v, &budget, guard)?;
+        Ok(old.map(|v| V::from(v)))
+    }
+
+    /// Same as [Art::remove], but gives up with [Contended] once the `budget` runs out
+    /// instead of retrying until it succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, RetryBudget};
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// let budget = RetryBudget::restarts(8);
+    /// assert_eq!(tree.try_remove(&1, budget, &guard).unwrap(), Some(42));
+    /// ```
+    #[inline]
+    pub fn try_remove(
+        &self,
+        k: &K,
+        budget: RetryBudget,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, Contended> {
+        let removed = self
+            .try_compute_if_present(k, |_v| None, budget, guard)?
+            .map(|(old, new)| {
+                debug_assert!(new.is_none());
+                V::from(old)
+            });
+        Ok(removed)
+    }
+
+    /// Same as [Art::compute_if_present], but gives up with [Contended] once the `budget` runs out
+    /// instead of retrying until it succeeds.
+    /// `f` may be called on every attempt, none of its results is stored if it gives up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, RetryBudget};
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// let budget = RetryBudget::restarts(8);
+    /// let res = tree.try_compute_if_present(&1, |v| Some(v + 1), budget, &guard);
+    /// assert_eq!(res.unwrap(), Some((42, Some(43))));
+    /// ```
+    #[inline]
+    pub fn try_compute_if_present<F>(
+        &self,
+        key: &K,
+        mut f: F,
+        budget: RetryBudget,
+        guard: &epoch::Guard,
+    ) -> Result<Option<(usize, Option<usize>)>, Contended>
+    where
+        F: FnMut(usize) -> Option<usize>,
+    {
+        self.inner
+            .try_compute_if_present(&FixedKey::new(key), &mut f, &budget, guard)
+            .map_err(|_| Contended::new())
+    }
+
+    /// Same as [Art::compute_or_insert], but gives up with [TryInsertError::Contended] once the `budget`
+    /// runs out instead of retrying until it succeeds.
+    /// `f` may be called on every attempt, none of its results is stored if it gives up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::{Art, RetryBudget};
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// let budget = RetryBudget::restarts(8);
+    /// let old = tree.try_compute_or_insert(1, |v| v.map_or(0, |v| v + 1), budget, &guard);
+    /// assert_eq!(old.unwrap(), None);
+    /// assert_eq!(tree.get(&1, &guard), Some(0));
+    /// ```
+    #[inline]
+    pub fn try_compute_or_insert<F>(
+        &self,
+        key: K,
+        mut f: F,
+        budget: RetryBudget,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, TryInsertError>
+    where
+        F: FnMut(Option<usize>) -> usize,
+    {
+        let old = self
+            .inner
+            .try_compute_or_insert(FixedKey::new(&key), &mut f, &budget, guard)?;
+        Ok(old.map(|v| V::from(v)))
+    }
+
+    /// Display the internal node statistics
+    #[cfg(feature = "stats")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stats")))]
+    pub fn stats(&self) -> stats::NodeStats {
+        self.inner.stats()
+    }
+
+    /// Get a random value from the tree, perform the transformation `f`.
+    /// This is useful for randomized algorithms.
+    ///
+    /// The value is not picked uniformly: every node on the way picks one of its children with equal probability,
+    /// so the values below nodes with few children are more likely. See [Art::sample] for uniform samples.
+    ///
+    /// `f` takes key and value as input and return the new value, |key: usize, value: usize| -> usize.
+    ///
+    /// Returns (key, old_value, new_value)
+    ///
+    /// Note that the function `f` is a FnMut and it must be safe to execute multiple times.
+    /// The `f` is expected to be short and fast as it will hold a exclusive lock on the leaf node.
+    ///
+    /// # Examples:
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// tree.insert(1, 42, &guard);
+    /// let mut rng = rand::thread_rng();
+    /// let (key, old_v, new_v) = tree.compute_on_random(&mut rng, |k, v| {
+    ///     assert_eq!(k, 1);
+    ///     assert_eq!(v, 42);
+    ///     v + 1
+    /// }, &guard).unwrap();
+    /// assert_eq!(key, 1);
+    /// assert_eq!(old_v, 42);
+    /// assert_eq!(new_v, 43);
+    /// ```
+    #[cfg(feature = "db_extension")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "db_extension")))]
+    pub fn compute_on_random(
+        &self,
+        rng: &mut impl rand::Rng,
+        mut f: impl FnMut(K, V) -> V,
+        guard: &epoch::Guard,
+    ) -> Option<(K, V, V)> {
+        let mut remapped = |key: &[u8], value: usize| -> usize {
+            let v = f(FixedKey::<K_LEN>::decode_bytes(key), V::from(value));
+            usize::from(v)
+        };
+        let (key, old_v, new_v) = self.inner.compute_on_random(rng, &mut remapped, guard)?;
+        Some((
+            FixedKey::<K_LEN>::decode_bytes(key.as_bytes()),
+            V::from(old_v),
+            V::from(new_v),
+        ))
+    }
+
+    /// Picks `n` keys uniformly at random, with replacement, and returns them with their values.
+    /// Returns no keys if the tree is empty.
+    ///
+    /// Each key is found by random descents from the root, which are accepted or rejected based on the number of
+    /// children of the nodes on their path, so that every key is equally likely, also under concurrent updates.
+    /// If the descents are expected to be rejected so often that scanning the keys is cheaper,
+    /// as in a tree whose keys are spread very unevenly, or if they are rejected far more often than expected,
+    /// the remaining keys are picked at random positions in a single scan.
+    /// With the `order_statistics` feature, a descent along the subtree counts picks the key, nothing is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// for k in 0..100 {
+    ///     tree.insert(k, k * 2, &guard).unwrap();
+    /// }
+    ///
+    /// let mut rng = rand::thread_rng();
+    /// let samples = tree.sample(&mut rng, 10, &guard);
+    /// assert_eq!(samples.len(), 10);
+    /// assert!(samples.iter().all(|(k, v)| *k < 100 && *v == k * 2));
+    /// ```
+    #[cfg(feature = "db_extension")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "db_extension")))]
+    pub fn sample(&self, rng: &mut impl rand::Rng, n: usize, guard: &epoch::Guard) -> Vec<(K, V)> {
+        self.sample_weighted(rng, n, 1.0, |_, _| 1.0, guard)
+    }
+
+    /// Same as [Art::sample], but a key is picked with a probability proportional to its `weight`,
+    /// which must be between `0.0` and `max_weight`.
+    ///
+    /// Uniform samples are kept with the probability of their weight relative to `max_weight`,
+    /// so a `max_weight` close to the largest weight needs the fewest draws.
+    /// Keys of weight `0.0` are never picked.
+    /// It gives up after 1024 draws per requested sample and returns the samples found so far,
+    /// e.g., none if no key has a positive weight.
+    ///
+    /// # Panics
+    /// Panics if a weight is not within `0.0..=max_weight`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// for k in 0..100 {
+    ///     tree.insert(k, k % 2, &guard).unwrap();
+    /// }
+    ///
+    /// // only the keys with odd values
+    /// let mut rng = rand::thread_rng();
+    /// let samples = tree.sample_weighted(&mut rng, 10, 1.0, |_, v| *v as f64, &guard);
+    /// assert!(samples.iter().all(|(k, v)| k % 2 == 1 && *v == 1));
+    /// ```
+    #[cfg(feature = "db_extension")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "db_extension")))]
+    pub fn sample_weighted(
+        &self,
+        rng: &mut impl rand::Rng,
+        n: usize,
+        max_weight: f64,
+        mut weight: impl FnMut(&K, &V) -> f64,
+        guard: &epoch::Guard,
+    ) -> Vec<(K, V)> {
+        const DRAWS_PER_SAMPLE: usize = 1024;
+        let mut samples = Vec::with_capacity(n);
+        let mut draws = n.saturating_mul(DRAWS_PER_SAMPLE);
+        // the draws so far and how many of them were kept, to size the scans for the weights
+        let (mut drawn, mut kept) = (0usize, 0);
+        let mut attempts = self.inner.sample_attempts(n, self.len());
+        let mut picked = Vec::new();
+        while samples.len() < n && draws > 0 {
+            match attempts {
+                Some(per_sample) => match self.inner.sample(rng, per_sample, guard) {
+                    Ok(Some((key, value))) => picked.push((
+                        FixedKey::<K_LEN>::decode_bytes(key.as_bytes()),
+                        V::from(value),
+                    )),
+                    Ok(None) => break,
+                    // the descents were rejected more often than expected, the rest is picked by position
+                    Err(_) => {
+                        attempts = None;
+                        continue;
+                    }
+                },
+                None => {
+                    let missing = n - samples.len();
+                    let count = missing
+                        .saturating_mul((drawn + 1).div_ceil(kept + 1))
+                        .min(draws);
+                    if self.sample_positions(rng, count, &mut picked, guard) == 0 {
+                        break;
+                    }
+                    // the tree may have shrunk, the positions past its end are drawn but found nothing
+                    draws -= count - picked.len();
+                }
+            }
+            for sample in picked.drain(..) {
+                if samples.len() == n {
+                    break;
+                }
+                draws -= 1;
+                drawn += 1;
+                let w = weight(&sample.0, &sample.1);
+                assert!(
+                    (0.0..=max_weight).contains(&w),
+                    "the weight is not within 0.0..=max_weight"
+                );
+                if rng.gen::<f64>() * max_weight < w {
+                    kept += 1;
+                    samples.push(sample);
+                }
+            }
+        }
+        samples
+    }
+
+    /// Picks `count` keys at uniformly random positions, with replacement, in a single scan,
+    /// and appends them to `picked` in random order. Returns the length of the tree the positions are drawn from.
+    #[cfg(feature = "db_extension")]
+    fn sample_positions(
+        &self,
+        rng: &mut impl rand::Rng,
+        count: usize,
+        picked: &mut Vec<(K, V)>,
+        guard: &epoch::Guard,
+    ) -> usize {
+        use rand::seq::SliceRandom;
+
+        let len = self.len();
+        if len == 0 {
+            return 0;
+        }
+        let mut positions: Vec<usize> = (0..count).map(|_| rng.gen_range(0..len)).collect();
+        positions.sort_unstable();
+
+        let start = picked.len();
+        let mut iter = RangeIter::new(&self.inner, FixedKey::range::<K>(&(..)), false, guard);
+        // the last key found and its position
+        let mut found: Option<(usize, FixedKey<K_LEN>, usize)> = None;
+        for pos in positions {
+            if found.as_ref().is_none_or(|(at, _, _)| *at != pos) {
+                let skip = pos - found.as_ref().map_or(0, |(at, _, _)| at + 1);
+                let Some((key, value)) = iter.nth(skip) else {
+                    break;
+                };
+                found = Some((pos, key, value));
+            }
+            let (_, key, value) = found.as_ref().unwrap();
+            picked.push((key.decode(), V::from(*value)));
+        }
+        // the draws are independent, after a shuffle their order is as random as the one they were drawn in
+        picked[start..].shuffle(rng);
+        len
+    }
+
+    /// Returns the number of keys smaller than `key`, whether or not `key` is present.
+    ///
+    /// With the `order_statistics` feature, every node keeps the number of keys in its subtree,
+    /// so the rank is found in one descent, without enumerating the smaller keys.
+    /// The counts cost 8 bytes per node, e.g., a `Node4` takes 128 instead of 64 bytes with the alignment,
+    /// and every insert and remove updates the counts of the ancestors.
+    /// The result is exact if there are no concurrent updates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// for k in (0..100).step_by(10) {
+    ///     tree.insert(k, k, &guard).unwrap();
+    /// }
+    ///
+    /// assert_eq!(tree.rank(&0, &guard), 0);
+    /// assert_eq!(tree.rank(&30, &guard), 3);
+    /// assert_eq!(tree.rank(&35, &guard), 4);
+    /// assert_eq!(tree.rank(&1000, &guard), 10);
+    /// ```
+    #[cfg(feature = "order_statistics")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "order_statistics")))]
+    pub fn rank(&self, key: &K, guard: &epoch::Guard) -> usize {
+        self.inner.rank(&FixedKey::new(key), guard).0
+    }
+
+    /// Returns the `i`-th smallest key and its value, counting from zero, or `None` if there are at most `i` keys.
+    ///
+    /// It descends along the subtree counts, see [Art::rank].
+    /// The result is exact if there are no concurrent updates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// for k in (0..100).step_by(10) {
+    ///     tree.insert(k, k * 2, &guard).unwrap();
+    /// }
+    ///
+    /// assert_eq!(tree.select(0, &guard), Some((0, 0)));
+    /// assert_eq!(tree.select(3, &guard), Some((30, 60)));
+    /// assert_eq!(tree.select(10, &guard), None);
+    /// ```
+    #[cfg(feature = "order_statistics")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "order_statistics")))]
+    pub fn select(&self, i: usize, guard: &epoch::Guard) -> Option<(K, V)> {
+        let (key, value) = self.inner.select(i, guard)?;
+        Some((
+            FixedKey::<K_LEN>::decode_bytes(key.as_bytes()),
+            V::from(value),
+        ))
+    }
+
+    /// Returns the number of keys within `range`, from the ranks of its bounds, see [Art::rank].
+    /// An empty or inverted range counts nothing.
+    /// The result is exact if there are no concurrent updates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// for k in (0..100).step_by(10) {
+    ///     tree.insert(k, k, &guard).unwrap();
+    /// }
+    ///
+    /// assert_eq!(tree.count_range(10..50, &guard), 4);
+    /// assert_eq!(tree.count_range(10..=50, &guard), 5);
+    /// assert_eq!(tree.count_range(95.., &guard), 0);
+    /// assert_eq!(tree.count_range(.., &guard), 10);
+    /// ```
+    #[cfg(feature = "order_statistics")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "order_statistics")))]
+    pub fn count_range(&self, range: impl RangeBounds<K>, guard: &epoch::Guard) -> usize {
+        self.inner.count_range(&FixedKey::range(&range), guard)
+    }
+
+    /// Update the value if the current value matches `old`, `None` as `old` matches an absent key.
+    /// Returns the new value if it matched, the current value otherwise.
+    ///
+    /// An absent key only takes `new` if the allocator has memory for it, otherwise the result is `Err(None)`.
+    ///
+    /// # Examples:
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// tree.insert(1, 42, &guard);
+    ///
+    ///
+    /// let v = tree.compare_exchange(&1, &42, Some(43), &guard).unwrap();
+    /// assert_eq!(v, Some(43));
+    ///
+    /// assert_eq!(tree.compare_exchange(&2, None, Some(44), &guard), Ok(Some(44)));
+    /// assert_eq!(tree.compare_exchange(&2, None, Some(45), &guard), Err(Some(44)));
+    /// ```
+    pub fn compare_exchange<'a>(
+        &self,
+        key: &K,
+        old: impl Into<Option<&'a V>>,
+        new: Option<V>,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, Option<V>>
+    where
+        V: 'a,
+    {
+        let u_key = FixedKey::new(key);
+        let new_v = new.clone().map(|v| usize::from(v));
+        let Some(old) = old.into() else {
+            // the key must be absent, it is only read unless there is a new value to insert
+            let Some(new_v) = new_v else {
+                return match self.inner.get(&u_key, guard) {
+                    None => Ok(None),
+                    Some(v) => Err(Some(V::from(v))),
+                };
+            };
+            return match self
+                .inner
+                .compute_or_insert(u_key, &mut |v| v.unwrap_or(new_v), guard)
+            {
+                Ok(None) => Ok(new),
+                Ok(Some(v)) => Err(Some(V::from(v))),
+                Err(_) => Err(None),
+            };
+        };
+        let old = usize::from(old.clone());
+        let mut fc = |v: usize| -> Option<usize> {
+            if v == old {
+                new_v
+            } else {
+                Some(v)
+            }
+        };
+        let v = self.inner.compute_if_present(&u_key, &mut fc, guard);
+        match v {
+            Some((actual_old, actual_new)) => {
+                if actual_old == old && actual_new == new_v {
+                    Ok(new)
+                } else {
+                    Err(actual_new.map(|v| V::from(v)))
                 }
             }
             None => Err(None),
         }
     }
+
+    /// Adds `delta` to the value of the key, an absent key counts as 0 and is inserted.
+    /// Returns the previous value, `None` if the key was absent.
+    ///
+    /// The value wraps around on overflow, like [AtomicUsize::fetch_add](std::sync::atomic::AtomicUsize::fetch_add).
+    /// The update is a single step on the key's leaf node, concurrent `fetch_add`s never lose an update.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert_eq!(tree.fetch_add(1, 2, &guard).unwrap(), None);
+    /// assert_eq!(tree.fetch_add(1, 3, &guard).unwrap(), Some(2));
+    /// assert_eq!(tree.get(&1, &guard), Some(5));
+    /// ```
+    pub fn fetch_add(
+        &self,
+        k: K,
+        delta: usize,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, OOMError> {
+        self.compute_or_insert(k, |v| v.unwrap_or(0).wrapping_add(delta), guard)
+    }
+
+    /// Subtracts `delta` from the value of the key, an absent key counts as 0 and is inserted.
+    /// Returns the previous value, `None` if the key was absent.
+    ///
+    /// The value wraps around on overflow, like [AtomicUsize::fetch_sub](std::sync::atomic::AtomicUsize::fetch_sub).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 5, &guard).unwrap();
+    /// assert_eq!(tree.fetch_sub(1, 2, &guard).unwrap(), Some(5));
+    /// assert_eq!(tree.get(&1, &guard), Some(3));
+    /// ```
+    pub fn fetch_sub(
+        &self,
+        k: K,
+        delta: usize,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, OOMError> {
+        self.compute_or_insert(k, |v| v.unwrap_or(0).wrapping_sub(delta), guard)
+    }
+
+    /// Replaces the value of a present key, returns the previous value.
+    /// An absent key is left absent and `None` is returned, unlike [Art::insert].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert_eq!(tree.swap(&1, 42, &guard), None);
+    /// assert_eq!(tree.get(&1, &guard), None);
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// assert_eq!(tree.swap(&1, 43, &guard), Some(42));
+    /// assert_eq!(tree.get(&1, &guard), Some(43));
+    /// ```
+    pub fn swap(&self, k: &K, v: V, guard: &epoch::Guard) -> Option<V> {
+        let v = usize::from(v);
+        let (old, _) = self
+            .inner
+            .compute_if_present(&FixedKey::new(k), &mut |_| Some(v), guard)?;
+        Some(V::from(old))
+    }
+
+    /// Inserts the key-value pair unless the key is present, then its value is returned and left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// assert_eq!(tree.insert_if_absent(1, 42, &guard).unwrap(), None);
+    /// assert_eq!(tree.insert_if_absent(1, 43, &guard).unwrap(), Some(42));
+    /// assert_eq!(tree.get(&1, &guard), Some(42));
+    /// ```
+    pub fn insert_if_absent(
+        &self,
+        k: K,
+        v: V,
+        guard: &epoch::Guard,
+    ) -> Result<Option<V>, OOMError> {
+        let v = usize::from(v);
+        self.compute_or_insert(k, |old| old.unwrap_or(v), guard)
+    }
+
+    /// Removes the key if `pred` holds for its value.
+    /// Returns the removed value, or the value that was kept if `pred` does not hold, `None` if the key is absent.
+    ///
+    /// `pred` sees the current value and may be called again if the removal is retried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    ///
+    /// tree.insert(1, 42, &guard).unwrap();
+    /// assert_eq!(tree.remove_if(&1, |v| v > 42, &guard), Err(Some(42)));
+    /// assert_eq!(tree.remove_if(&1, |v| v == 42, &guard), Ok(42));
+    /// assert_eq!(tree.remove_if(&1, |_| true, &guard), Err(None));
+    /// ```
+    pub fn remove_if(
+        &self,
+        k: &K,
+        mut pred: impl FnMut(V) -> bool,
+        guard: &epoch::Guard,
+    ) -> Result<V, Option<V>> {
+        let mut keep = |v: usize| (!pred(V::from(v))).then_some(v);
+        match self
+            .inner
+            .compute_if_present(&FixedKey::new(k), &mut keep, guard)
+        {
+            Some((old, None)) => Ok(V::from(old)),
+            Some((old, Some(_))) => Err(Some(V::from(old))),
+            None => Err(None),
+        }
+    }
 }
@@ -72,6 +72,8 @@ impl<'a, T: Node> Drop for ConcreteWriteGuard<'a, T> {
     }
 }
 
+/// A copy of a read guard validates against the same version, it is not a lock on its own.
+#[derive(Clone)]
 pub(crate) struct ReadGuard<'a> {
     version: usize,
     node: &'a UnsafeCell<BaseNode>,
@@ -85,6 +87,11 @@ impl<'a> ReadGuard<'a> {
         }
     }
 
+    /// The version of the node when it was read.
+    pub(crate) fn version(&self) -> usize {
+        self.version
+    }
+
     pub(crate) fn check_version(&self) -> Result<usize, ArtError> {
         let v = self
             .as_ref()
@@ -5,19 +5,22 @@ use crate::{
 
 #[repr(C)]
 #[repr(align(8))] // Node 16 doesn't need to align to 64 bc it occupies 3 cache lines anyway
-pub(crate) struct Node16 {
+pub(crate) struct Node16<S = NodePtr> {
     base: BaseNode,
-    children: [NodePtr; 16],
+    children: [S; 16],
     keys: [u8; 16],
 }
 
-#[cfg(not(feature = "shuttle"))]
+#[cfg(not(any(feature = "shuttle", feature = "order_statistics")))]
 const _: () = assert!(std::mem::size_of::<Node16>() == 168);
 
+#[cfg(all(not(feature = "shuttle"), feature = "order_statistics"))]
+const _: () = assert!(std::mem::size_of::<Node16>() == 176);
+
 #[cfg(not(feature = "shuttle"))]
 const _: () = assert!(std::mem::align_of::<Node16>() == 8);
 
-impl Node16 {
+impl<S> Node16<S> {
     fn flip_sign(val: u8) -> u8 {
         val ^ 128
     }
@@ -74,46 +77,60 @@ impl Node16 {
     }
 }
 
-pub(crate) struct Node16Iter<'a> {
-    node: &'a Node16,
+pub(crate) struct Node16Iter<'a, S> {
+    node: &'a Node16<S>,
     start_pos: usize,
+    // exclusive
     end_pos: usize,
 }
 
-impl<'a> Iterator for Node16Iter<'a> {
-    type Item = (u8, NodePtr);
+impl<S: Copy> Iterator for Node16Iter<'_, S> {
+    type Item = (u8, S);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.start_pos > self.end_pos {
+        if self.start_pos >= self.end_pos {
             return None;
         }
-        let key = Node16::flip_sign(self.node.keys[self.start_pos]);
+        let key = Node16::<S>::flip_sign(self.node.keys[self.start_pos]);
         let child = self.node.children[self.start_pos];
         self.start_pos += 1;
         Some((key, child))
     }
 }
 
-impl Node for Node16 {
+impl<S: Copy> DoubleEndedIterator for Node16Iter<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start_pos >= self.end_pos {
+            return None;
+        }
+        self.end_pos -= 1;
+        let key = Node16::<S>::flip_sign(self.node.keys[self.end_pos]);
+        Some((key, self.node.children[self.end_pos]))
+    }
+}
+
+impl<S: Copy> Node for Node16<S> {
+    type Slot = S;
+
     fn get_type() -> NodeType {
         NodeType::N16
     }
 
-    fn get_children(&self, start: u8, end: u8) -> NodeIter {
+    fn get_children(&self, start: u8, end: u8) -> NodeIter<'_, S> {
         if self.base.meta.count == 0 {
             // FIXME: the node may be empty due to deletion, this is not intended, we should fix the delete logic
             return NodeIter::N16(Node16Iter {
                 node: self,
-                start_pos: 1,
+                start_pos: 0,
                 end_pos: 0,
             });
         }
         let start_pos = self.get_child_pos(start).unwrap_or(0);
         let end_pos = self
             .get_child_pos(end)
-            .unwrap_or(self.base.meta.count as usize - 1);
+            .map_or(self.base.meta.count as usize, |pos| pos + 1);
 
-        debug_assert!(end_pos < 16);
+        debug_assert!(end_pos <= 16);
 
         NodeIter::N16(Node16Iter {
             node: self,
@@ -143,7 +160,7 @@ impl Node for Node16 {
         debug_assert!(self.get_child(k).is_none());
     }
 
-    fn copy_to<N: Node>(&self, dst: &mut N) {
+    fn copy_to<N: Node<Slot = S>>(&self, dst: &mut N) {
         for i in 0..self.base.meta.count {
             dst.insert(
                 Self::flip_sign(self.keys[i as usize]),
@@ -161,7 +178,7 @@ impl Node for Node16 {
     }
 
     // Insert must keep keys sorted, is this necessary?
-    fn insert(&mut self, key: u8, node: NodePtr) {
+    fn insert(&mut self, key: u8, node: S) {
         let key_flipped = Self::flip_sign(key);
 
         let pos = self.get_insert_pos(key);
@@ -187,26 +204,29 @@ impl Node for Node16 {
         assert!(self.base.meta.count <= 16);
     }
 
-    fn change(&mut self, key: u8, val: NodePtr) -> NodePtr {
+    fn change(&mut self, key: u8, val: S) -> S {
         let pos = self.get_child_pos(key).unwrap();
         let old = self.children[pos];
         self.children[pos] = val;
         old
     }
 
-    fn get_child(&self, key: u8) -> Option<NodePtr> {
+    fn get_child(&self, key: u8) -> Option<S> {
         let pos = self.get_child_pos(key)?;
         let child = unsafe { self.children.get_unchecked(pos) };
         Some(*child)
     }
 
     #[cfg(feature = "db_extension")]
-    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, NodePtr)> {
+    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, S)> {
         if self.base.meta.count == 0 {
             return None;
         }
 
         let idx = rng.gen_range(0..self.base.meta.count);
-        Some((self.keys[idx as usize], self.children[idx as usize]))
+        Some((
+            Self::flip_sign(self.keys[idx as usize]),
+            self.children[idx as usize],
+        ))
     }
 }
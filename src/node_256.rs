@@ -5,18 +5,21 @@ use crate::{
 
 #[repr(C)]
 #[repr(align(8))]
-pub(crate) struct Node256 {
+pub(crate) struct Node256<S = NodePtr> {
     base: BaseNode,
     key_mask: [u8; 32],
-    children: [NodePtr; 256],
+    children: [S; 256],
 }
 
-#[cfg(not(feature = "shuttle"))]
+#[cfg(not(any(feature = "shuttle", feature = "order_statistics")))]
 const _: () = assert!(std::mem::size_of::<Node256>() == 2104);
+
+#[cfg(all(not(feature = "shuttle"), feature = "order_statistics"))]
+const _: () = assert!(std::mem::size_of::<Node256>() == 2112);
 #[cfg(not(feature = "shuttle"))]
 const _: () = assert!(std::mem::align_of::<Node256>() == 8);
 
-impl Node256 {
+impl<S> Node256<S> {
     #[inline]
     fn set_mask(&mut self, key: usize) {
         let idx = key / 8;
@@ -40,50 +43,65 @@ impl Node256 {
     }
 }
 
-pub(crate) struct Node256Iter<'a> {
-    start: u8,
-    end: u8,
-    idx: u16,
-    node: &'a Node256,
+pub(crate) struct Node256Iter<'a, S> {
+    start: u16,
+    // exclusive
+    end: u16,
+    node: &'a Node256<S>,
 }
 
-impl<'a> Iterator for Node256Iter<'a> {
-    type Item = (u8, NodePtr);
+impl<S: Copy> Iterator for Node256Iter<'_, S> {
+    type Item = (u8, S);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let cur = self.idx + self.start as u16;
+            if self.start >= self.end {
+                return None;
+            }
+
+            let cur = self.start;
+            self.start += 1;
+
+            if self.node.get_mask(cur as usize) {
+                return Some((cur as u8, self.node.children[cur as usize]));
+            }
+        }
+    }
+}
 
-            if cur > self.end as u16 {
+impl<S: Copy> DoubleEndedIterator for Node256Iter<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.start >= self.end {
                 return None;
             }
 
-            self.idx += 1;
+            self.end -= 1;
+            let cur = self.end;
 
             if self.node.get_mask(cur as usize) {
                 return Some((cur as u8, self.node.children[cur as usize]));
-            } else {
-                continue;
             }
         }
     }
 }
 
-impl Node for Node256 {
+impl<S: Copy> Node for Node256<S> {
+    type Slot = S;
+
     fn get_type() -> NodeType {
         NodeType::N256
     }
 
-    fn get_children(&self, start: u8, end: u8) -> NodeIter {
+    fn get_children(&self, start: u8, end: u8) -> NodeIter<'_, S> {
         NodeIter::N256(Node256Iter {
-            start,
-            end,
-            idx: 0,
+            start: start as u16,
+            end: end as u16 + 1,
             node: self,
         })
     }
 
-    fn copy_to<N: Node>(&self, dst: &mut N) {
+    fn copy_to<N: Node<Slot = S>>(&self, dst: &mut N) {
         for (i, c) in self.children.iter().enumerate() {
             if self.get_mask(i) {
                 dst.insert(i as u8, *c);
@@ -99,13 +117,19 @@ impl Node for Node256 {
         false
     }
 
-    fn insert(&mut self, key: u8, node: NodePtr) {
+    fn is_underfull(&self) -> bool {
+        // without payload, i.e., in a set, a `Node256` is only a bit mask and smaller than a `Node48`
+        std::mem::size_of::<S>() != 0
+            && self.base().get_count() <= NodeType::N256.shrink_threshold() + 1
+    }
+
+    fn insert(&mut self, key: u8, node: S) {
         self.children[key as usize] = node;
         self.set_mask(key as usize);
         self.base.meta.count += 1;
     }
 
-    fn change(&mut self, key: u8, val: NodePtr) -> NodePtr {
+    fn change(&mut self, key: u8, val: S) -> S {
         let old = self.children[key as usize];
         self.children[key as usize] = val;
         old
@@ -116,7 +140,7 @@ impl Node for Node256 {
         self.base.meta.count -= 1;
     }
 
-    fn get_child(&self, key: u8) -> Option<NodePtr> {
+    fn get_child(&self, key: u8) -> Option<S> {
         if self.get_mask(key as usize) {
             let child = unsafe { self.children.get_unchecked(key as usize) };
             Some(*child)
@@ -126,18 +150,20 @@ impl Node for Node256 {
     }
 
     #[cfg(feature = "db_extension")]
-    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, NodePtr)> {
+    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, S)> {
         if self.base.meta.count == 0 {
             return None;
         }
+        // the count and the mask may disagree under a concurrent write, the caller's version check catches that
         let mut scan_cnt = rng.gen_range(1..=self.base.meta.count);
-        let mut idx = 0;
-        while scan_cnt > 0 {
+        for idx in 0..256 {
             if self.get_mask(idx) {
                 scan_cnt -= 1;
+                if scan_cnt == 0 {
+                    return Some((idx as u8, self.children[idx]));
+                }
             }
-            idx += 1;
         }
-        Some(((idx - 1) as u8, self.children[idx - 1]))
+        None
     }
 }
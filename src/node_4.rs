@@ -5,28 +5,31 @@ use crate::{
 
 #[repr(C)]
 #[repr(align(64))]
-pub(crate) struct Node4 {
+pub(crate) struct Node4<S = NodePtr> {
     base: BaseNode,
     keys: [u8; 4],
-    children: [NodePtr; 4],
+    children: [S; 4],
 }
 
-#[cfg(not(feature = "shuttle"))]
+#[cfg(not(any(feature = "shuttle", feature = "order_statistics")))]
 const _: () = assert!(std::mem::size_of::<Node4>() == 64);
 
+#[cfg(all(not(feature = "shuttle"), feature = "order_statistics"))]
+const _: () = assert!(std::mem::size_of::<Node4>() == 128);
+
 #[cfg(not(feature = "shuttle"))]
 const _: () = assert!(std::mem::align_of::<Node4>() == 64);
 
-pub(crate) struct Node4Iter<'a> {
+pub(crate) struct Node4Iter<'a, S> {
     start: u8,
     end: u8,
     idx: u8,
     cnt: u8,
-    node: &'a Node4,
+    node: &'a Node4<S>,
 }
 
-impl Iterator for Node4Iter<'_> {
-    type Item = (u8, NodePtr);
+impl<S: Copy> Iterator for Node4Iter<'_, S> {
+    type Item = (u8, S);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -44,7 +47,26 @@ impl Iterator for Node4Iter<'_> {
     }
 }
 
-impl Node for Node4 {
+impl<S: Copy> DoubleEndedIterator for Node4Iter<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.idx >= self.cnt {
+                return None;
+            }
+            self.cnt -= 1;
+            let cur = self.cnt;
+
+            let key = self.node.keys[cur as usize];
+            if key >= self.start && key <= self.end {
+                return Some((key, self.node.children[cur as usize]));
+            }
+        }
+    }
+}
+
+impl<S: Copy> Node for Node4<S> {
+    type Slot = S;
+
     fn get_type() -> NodeType {
         NodeType::N4
     }
@@ -71,7 +93,7 @@ impl Node for Node4 {
         }
     }
 
-    fn get_children(&self, start: u8, end: u8) -> NodeIter {
+    fn get_children(&self, start: u8, end: u8) -> NodeIter<'_, S> {
         NodeIter::N4(Node4Iter {
             start,
             end,
@@ -81,7 +103,7 @@ impl Node for Node4 {
         })
     }
 
-    fn copy_to<N: Node>(&self, dst: &mut N) {
+    fn copy_to<N: Node<Slot = S>>(&self, dst: &mut N) {
         for i in 0..self.base.meta.count {
             dst.insert(self.keys[i as usize], self.children[i as usize]);
         }
@@ -95,7 +117,11 @@ impl Node for Node4 {
         self.base.meta.count == 4
     }
 
-    fn insert(&mut self, key: u8, node: NodePtr) {
+    fn is_underfull(&self) -> bool {
+        false
+    }
+
+    fn insert(&mut self, key: u8, node: S) {
         let mut pos: usize = 0;
 
         while (pos as u16) < self.base.meta.count {
@@ -126,7 +152,7 @@ impl Node for Node4 {
         self.base.meta.count += 1;
     }
 
-    fn change(&mut self, key: u8, val: NodePtr) -> NodePtr {
+    fn change(&mut self, key: u8, val: S) -> S {
         for i in 0..self.base.meta.count {
             if self.keys[i as usize] == key {
                 let old = self.children[i as usize];
@@ -137,7 +163,7 @@ impl Node for Node4 {
         unreachable!("The key should always exist in the node");
     }
 
-    fn get_child(&self, key: u8) -> Option<NodePtr> {
+    fn get_child(&self, key: u8) -> Option<S> {
         self.keys
             .iter()
             .zip(self.children.iter())
@@ -147,7 +173,7 @@ impl Node for Node4 {
     }
 
     #[cfg(feature = "db_extension")]
-    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, NodePtr)> {
+    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, S)> {
         if self.base.meta.count == 0 {
             return None;
         }
@@ -7,43 +7,45 @@ pub(crate) const EMPTY_MARKER: u8 = 48;
 
 #[repr(C)]
 #[repr(align(8))]
-pub(crate) struct Node48 {
+pub(crate) struct Node48<S = NodePtr> {
     base: BaseNode,
 
     pub(crate) child_idx: [u8; 256],
-    next_empty: u8,
-    children: [NodePtr; 48],
+    // bitmap of the occupied `children` slots
+    occupied: u64,
+    children: [S; 48],
 }
-#[cfg(not(feature = "shuttle"))]
+#[cfg(not(any(feature = "shuttle", feature = "order_statistics")))]
 const _: () = assert!(std::mem::size_of::<Node48>() == 672);
 
+#[cfg(all(not(feature = "shuttle"), feature = "order_statistics"))]
+const _: () = assert!(std::mem::size_of::<Node48>() == 680);
+
 #[cfg(not(feature = "shuttle"))]
 const _: () = assert!(std::mem::align_of::<Node48>() == 8);
 
-impl Node48 {
+impl<S> Node48<S> {
     pub(crate) fn init_empty(&mut self) {
         for v in self.child_idx.iter_mut() {
             *v = EMPTY_MARKER;
         }
-        self.next_empty = 0;
-        for (i, child) in self.children.iter_mut().enumerate() {
-            *child = NodePtr::from_tid(i + 1);
-        }
+        self.occupied = 0;
     }
 }
 
-pub(crate) struct Node48Iter<'a> {
+pub(crate) struct Node48Iter<'a, S> {
     start: u16,
+    // exclusive
     end: u16,
-    node: &'a Node48,
+    node: &'a Node48<S>,
 }
 
-impl<'a> Iterator for Node48Iter<'a> {
-    type Item = (u8, NodePtr);
+impl<S: Copy> Iterator for Node48Iter<'_, S> {
+    type Item = (u8, S);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            if self.start > self.end {
+            if self.start >= self.end {
                 return None;
             }
 
@@ -58,7 +60,27 @@ impl<'a> Iterator for Node48Iter<'a> {
     }
 }
 
-impl Node for Node48 {
+impl<S: Copy> DoubleEndedIterator for Node48Iter<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.start >= self.end {
+                return None;
+            }
+
+            self.end -= 1;
+            let key = self.end as usize;
+
+            let child_loc = self.node.child_idx[key];
+            if child_loc != EMPTY_MARKER {
+                return Some((key as u8, self.node.children[child_loc as usize]));
+            }
+        }
+    }
+}
+
+impl<S: Copy> Node for Node48<S> {
+    type Slot = S;
+
     fn get_type() -> NodeType {
         NodeType::N48
     }
@@ -66,22 +88,21 @@ impl Node for Node48 {
     fn remove(&mut self, k: u8) {
         debug_assert!(self.child_idx[k as usize] != EMPTY_MARKER);
         let pos = self.child_idx[k as usize];
-        self.children[pos as usize] = NodePtr::from_tid(self.next_empty as usize);
+        self.occupied &= !(1 << pos);
         self.child_idx[k as usize] = EMPTY_MARKER;
-        self.next_empty = pos;
         self.base.meta.count -= 1;
         debug_assert!(self.get_child(k).is_none());
     }
 
-    fn get_children(&self, start: u8, end: u8) -> NodeIter {
+    fn get_children(&self, start: u8, end: u8) -> NodeIter<'_, S> {
         NodeIter::N48(Node48Iter {
             start: start as u16,
-            end: end as u16,
+            end: end as u16 + 1,
             node: self,
         })
     }
 
-    fn copy_to<N: Node>(&self, dst: &mut N) {
+    fn copy_to<N: Node<Slot = S>>(&self, dst: &mut N) {
         for (i, c) in self.child_idx.iter().enumerate() {
             if *c != EMPTY_MARKER {
                 dst.insert(i as u8, self.children[*c as usize]);
@@ -97,9 +118,9 @@ impl Node for Node48 {
         self.base.meta.count == 48
     }
 
-    fn insert(&mut self, key: u8, node: NodePtr) {
-        let pos = self.next_empty as usize;
-        self.next_empty = self.children[pos].as_tid() as u8;
+    fn insert(&mut self, key: u8, node: S) {
+        let pos = self.occupied.trailing_ones() as usize;
+        self.occupied |= 1 << pos;
 
         debug_assert!(pos < 48);
 
@@ -108,13 +129,13 @@ impl Node for Node48 {
         self.base.meta.count += 1;
     }
 
-    fn change(&mut self, key: u8, val: NodePtr) -> NodePtr {
+    fn change(&mut self, key: u8, val: S) -> S {
         let old = self.children[self.child_idx[key as usize] as usize];
         self.children[self.child_idx[key as usize] as usize] = val;
         old
     }
 
-    fn get_child(&self, key: u8) -> Option<NodePtr> {
+    fn get_child(&self, key: u8) -> Option<S> {
         let pos = unsafe { self.child_idx.get_unchecked(key as usize) };
         if *pos == EMPTY_MARKER {
             None
@@ -125,23 +146,21 @@ impl Node for Node48 {
     }
 
     #[cfg(feature = "db_extension")]
-    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, NodePtr)> {
+    fn get_random_child(&self, rng: &mut impl rand::Rng) -> Option<(u8, S)> {
         if self.base.meta.count == 0 {
             return None;
         }
 
+        // the count and the indices may disagree under a concurrent write, the caller's version check catches that
         let mut scan_cnt = rng.gen_range(1..=self.base.meta.count);
-        let mut idx = 0;
-        while scan_cnt > 0 {
-            if self.child_idx[idx] != EMPTY_MARKER {
+        for (idx, child) in self.child_idx.iter().enumerate() {
+            if *child != EMPTY_MARKER {
                 scan_cnt -= 1;
+                if scan_cnt == 0 {
+                    return Some((idx as u8, self.children[*child as usize]));
+                }
             }
-            idx += 1;
         }
-
-        Some((
-            (idx - 1) as u8,
-            self.children[self.child_idx[idx - 1] as usize],
-        ))
+        None
     }
 }
@@ -1,5 +1,8 @@
 use crate::base_node::BaseNode;
 
+// Leaves of variable length trees are tagged pointers, nodes are at least 8 byte aligned.
+const LEAF_TAG: usize = 1;
+
 #[derive(Clone, Copy)]
 pub(crate) union NodePtr {
     tid: usize,
@@ -12,11 +15,6 @@ impl NodePtr {
         Self { sub_node: ptr }
     }
 
-    #[inline]
-    pub(crate) fn from_tid(tid: usize) -> Self {
-        Self { tid }
-    }
-
     #[inline]
     pub(crate) fn as_tid(&self) -> usize {
         unsafe { self.tid }
@@ -26,4 +24,21 @@ impl NodePtr {
     pub(crate) fn as_ptr(&self) -> *const BaseNode {
         unsafe { self.sub_node }
     }
+
+    #[inline]
+    pub(crate) fn from_leaf<T>(ptr: *const T) -> Self {
+        Self {
+            tid: ptr as usize | LEAF_TAG,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.as_tid() & LEAF_TAG == LEAF_TAG
+    }
+
+    #[inline]
+    pub(crate) fn as_leaf<T>(&self) -> *const T {
+        (self.as_tid() & !LEAF_TAG) as *const T
+    }
 }
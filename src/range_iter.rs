@@ -0,0 +1,104 @@
+use std::ops::Bound;
+
+use crossbeam_epoch::Guard;
+
+use crate::{key::FixedKey, tree::RawTree, Allocator};
+
+/// Number of keys the first scan of a [RangeIter] fetches, later scans fetch more up to [MAX_BATCH].
+const MIN_BATCH: usize = 16;
+const MAX_BATCH: usize = 256;
+
+/// A lazy cursor over the keys within a range of a [RawTree].
+///
+/// It scans a batch of keys at a time, every scan restarts from the key after (or before, if reversed)
+/// the last returned one, i.e., the last returned key becomes an excluded bound.
+/// A scan that fails the version check is retried by [RawTree::range], so the caller never re-issues it.
+/// Keys inserted or removed during the iteration may or may not be returned.
+pub(crate) struct RangeIter<
+    'a,
+    V: Copy + PartialEq,
+    A: Allocator + Clone + 'static,
+    const K_LEN: usize,
+> {
+    tree: &'a RawTree<FixedKey<K_LEN>, V, A, K_LEN>,
+    guard: &'a Guard,
+    batch: Vec<Option<(FixedKey<K_LEN>, V)>>,
+    cnt: usize,
+    pos: usize,
+    start: Bound<FixedKey<K_LEN>>,
+    end: Bound<FixedKey<K_LEN>>,
+    reverse: bool,
+    done: bool,
+}
+
+impl<'a, V: Copy + PartialEq, A: Allocator + Clone + Send + 'static, const K_LEN: usize>
+    RangeIter<'a, V, A, K_LEN>
+{
+    pub(crate) fn new(
+        tree: &'a RawTree<FixedKey<K_LEN>, V, A, K_LEN>,
+        (start, end): (Bound<FixedKey<K_LEN>>, Bound<FixedKey<K_LEN>>),
+        reverse: bool,
+        guard: &'a Guard,
+    ) -> Self {
+        Self {
+            tree,
+            guard,
+            batch: Vec::new(),
+            cnt: 0,
+            pos: 0,
+            start,
+            end,
+            reverse,
+            done: false,
+        }
+    }
+
+    /// Scans the next batch, returns false if there are no more keys.
+    fn fetch(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        let batch_size = (self.batch.len() * 2).clamp(MIN_BATCH, MAX_BATCH);
+        self.batch.resize(batch_size, None);
+        self.pos = 0;
+
+        let range = (self.start.as_ref(), self.end.as_ref());
+        let mut fill = |slot: &mut Option<(FixedKey<K_LEN>, V)>, key: &[u8], v| {
+            *slot = Some((FixedKey::from_bytes(key), v))
+        };
+        self.cnt = if self.reverse {
+            self.tree
+                .range_rev(&range, &mut self.batch, &mut fill, self.guard)
+        } else {
+            self.tree
+                .range(&range, &mut self.batch, &mut fill, self.guard)
+        };
+
+        if self.cnt == self.batch.len() {
+            let (last, _) = self.batch[self.cnt - 1].as_ref().unwrap();
+            let last = Bound::Excluded(last.clone());
+            if self.reverse {
+                self.end = last;
+            } else {
+                self.start = last;
+            }
+        } else {
+            self.done = true;
+        }
+        self.cnt > 0
+    }
+}
+
+impl<V: Copy + PartialEq, A: Allocator + Clone + Send + 'static, const K_LEN: usize> Iterator
+    for RangeIter<'_, V, A, K_LEN>
+{
+    type Item = (FixedKey<K_LEN>, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.cnt && !self.fetch() {
+            return None;
+        }
+        self.pos += 1;
+        self.batch[self.pos - 1].take()
+    }
+}
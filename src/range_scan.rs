@@ -1,9 +1,8 @@
-use crate::base_node::MAX_KEY_LEN;
 use crate::error::ArtError;
 use crate::{
     base_node::BaseNode, key::RawKey, lock::ReadGuard, node_ptr::NodePtr, utils::KeyTracker,
 };
-use std::cmp;
+use std::{cmp, marker::PhantomData, ops::Bound};
 
 enum PrefixCheckEqualsResult {
     BothMatch,
@@ -11,54 +10,92 @@ enum PrefixCheckEqualsResult {
     NotMatch,
 }
 
-pub(crate) struct RangeScan<'a, T: RawKey> {
-    start: &'a T,
-    end: &'a T,
-    result: &'a mut [(usize, usize)],
+pub(crate) struct RangeScan<
+    'a,
+    T: RawKey,
+    V: Copy,
+    R,
+    F: FnMut(&mut R, &[u8], V),
+    const K_LEN: usize,
+> {
+    start: Bound<&'a T>,
+    end: Bound<&'a T>,
+    result: &'a mut [R],
+    fill: &'a mut F,
     root: *const BaseNode,
-    to_continue: usize,
+    // scans from `end` down to `start`
+    reverse: bool,
+    to_continue: bool,
     result_found: usize,
+    _pt_val: PhantomData<V>,
 }
 
-impl<'a, T: RawKey> RangeScan<'a, T> {
+impl<'a, T: RawKey, V: Copy, R, F: FnMut(&mut R, &[u8], V), const K_LEN: usize>
+    RangeScan<'a, T, V, R, F, K_LEN>
+{
     pub(crate) fn new(
-        start: &'a T,
-        end: &'a T,
-        result: &'a mut [(usize, usize)],
+        start: Bound<&'a T>,
+        end: Bound<&'a T>,
+        result: &'a mut [R],
+        fill: &'a mut F,
         root: *const BaseNode,
+        reverse: bool,
     ) -> Self {
         Self {
             start,
             end,
             result,
+            fill,
             root,
-            to_continue: 0,
+            reverse,
+            to_continue: false,
             result_found: 0,
+            _pt_val: PhantomData,
         }
     }
 
+    /// Returns false if the range is empty, e.g., `start > end` or `start == end` and either side is excluded.
     pub(crate) fn is_valid_key_pair(&self) -> bool {
-        self.start < self.end
+        match (self.start, self.end) {
+            (Bound::Included(start), Bound::Included(end)) => start <= end,
+            (
+                Bound::Included(start) | Bound::Excluded(start),
+                Bound::Included(end) | Bound::Excluded(end),
+            ) => start < end,
+            _ => true,
+        }
     }
 
-    fn key_in_range(&self, key: &KeyTracker) -> bool {
-        debug_assert_eq!(key.len(), 8);
-        let cur_key = key.to_usize_key();
-
-        let start_key = unsafe { *(self.start.as_bytes().as_ptr() as *const usize) }.swap_bytes();
-        let end_key = unsafe { *(self.end.as_bytes().as_ptr() as *const usize) }.swap_bytes();
+    fn key_in_range(&self, key: &KeyTracker<K_LEN>) -> bool {
+        debug_assert_eq!(key.len(), K_LEN);
+        let cur_key = key.as_bytes();
+
+        let after_start = match self.start {
+            Bound::Included(start) => start.as_bytes() <= cur_key,
+            Bound::Excluded(start) => start.as_bytes() < cur_key,
+            Bound::Unbounded => true,
+        };
+        let before_end = match self.end {
+            Bound::Included(end) => cur_key <= end.as_bytes(),
+            Bound::Excluded(end) => cur_key < end.as_bytes(),
+            Bound::Unbounded => true,
+        };
+        after_start && before_end
+    }
 
-        if start_key <= cur_key && cur_key < end_key {
-            return true;
+    /// The byte of the `bound` key at `level`, `fill` if the key is unbounded or shorter than `level`.
+    fn bound_byte(bound: Bound<&T>, level: usize, fill: u8) -> u8 {
+        match bound {
+            Bound::Included(k) | Bound::Excluded(k) if k.len() > level => k.as_bytes()[level],
+            _ => fill,
         }
-        false
     }
 
     pub(crate) fn scan(&mut self) -> Result<usize, ArtError> {
         let mut node: ReadGuard;
         let mut next_node = self.root;
         let mut parent_node: Option<ReadGuard> = None;
-        self.to_continue = 0;
+        self.to_continue = false;
         self.result_found = 0;
 
         let mut key_tracker = KeyTracker::default();
@@ -68,8 +105,8 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
 
             let prefix_check_result = self.check_prefix_equals(node.as_ref(), &mut key_tracker);
 
-            if parent_node.is_some() {
-                parent_node.as_ref().unwrap().check_version()?;
+            if let Some(p) = &parent_node {
+                p.check_version()?;
             }
 
             node.check_version()?;
@@ -77,28 +114,23 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
             match prefix_check_result {
                 PrefixCheckEqualsResult::BothMatch => {
                     let level = key_tracker.len();
-                    let start_level = if self.start.len() > level {
-                        self.start.as_bytes()[level]
-                    } else {
-                        0
-                    };
-                    let end_level = if self.end.len() > level {
-                        self.end.as_bytes()[level]
-                    } else {
-                        255
-                    };
+                    let start_level = Self::bound_byte(self.start, level, 0);
+                    let end_level = Self::bound_byte(self.end, level, 255);
 
-                    if start_level != end_level {
-                        let children = node.as_ref().get_children(start_level, end_level);
+                    if level == K_LEN - 1 {
+                        self.copy_values(&node, start_level, end_level, &mut key_tracker)?;
+                    } else if start_level != end_level {
+                        let children = node
+                            .as_ref()
+                            .get_children(start_level, end_level)
+                            .directed(self.reverse);
 
                         for (k, n) in children {
                             node.check_version()?;
 
                             key_tracker.push(k);
 
-                            if key_tracker.len() == MAX_KEY_LEN {
-                                self.copy_node(n, &key_tracker)?;
-                            } else if k == start_level {
+                            if k == start_level {
                                 self.find_start(n, &node, key_tracker.clone())?;
                             } else if k > start_level && k < end_level {
                                 let cur_key = KeyTracker::append_prefix(n, &key_tracker);
@@ -108,7 +140,7 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
                             }
                             key_tracker.pop();
 
-                            if self.to_continue > 0 {
+                            if self.to_continue {
                                 return Ok(self.result_found);
                             }
                         }
@@ -120,10 +152,6 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
                         };
                         node.check_version()?;
 
-                        if key_tracker.len() == (MAX_KEY_LEN - 1) {
-                            self.copy_node(next_node_tmp, &key_tracker)?;
-                            return Ok(self.result_found);
-                        }
                         key_tracker.push(start_level);
                         next_node = next_node_tmp.as_ptr();
 
@@ -147,9 +175,9 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
         &mut self,
         node: NodePtr,
         parent_node: &ReadGuard,
-        mut key_tracker: KeyTracker,
+        mut key_tracker: KeyTracker<K_LEN>,
     ) -> Result<(), ArtError> {
-        debug_assert!(key_tracker.len() != 8);
+        debug_assert!(key_tracker.len() != K_LEN);
 
         let node = unsafe { &*node.as_ptr() }.read_lock()?;
         let prefix_result =
@@ -162,28 +190,30 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
         match prefix_result {
             cmp::Ordering::Greater => Ok(()),
             cmp::Ordering::Equal => {
-                let end_level = if self.end.len() > level {
-                    self.end.as_bytes()[level]
-                } else {
-                    255
-                };
+                let end_level = Self::bound_byte(self.end, level, 255);
 
-                let children = node.as_ref().get_children(0, end_level);
+                if level == K_LEN - 1 {
+                    self.copy_values(&node, 0, end_level, &mut key_tracker)?;
+                    return Ok(());
+                }
+
+                let children = node
+                    .as_ref()
+                    .get_children(0, end_level)
+                    .directed(self.reverse);
                 for (k, n) in children {
                     node.check_version()?;
 
                     key_tracker.push(k);
 
-                    if key_tracker.len() == MAX_KEY_LEN {
-                        self.copy_node(n, &key_tracker)?;
-                    } else if k == end_level {
+                    if k == end_level {
                         self.find_end(n, &node, key_tracker.clone())?;
                     } else if k < end_level {
                         let cur_key = KeyTracker::append_prefix(n, &key_tracker);
                         self.copy_node(n, &cur_key)?;
                     }
                     key_tracker.pop();
-                    if self.to_continue != 0 {
+                    if self.to_continue {
                         break;
                     }
                 }
@@ -197,9 +227,9 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
         &mut self,
         node: NodePtr,
         parent_node: &ReadGuard,
-        mut key_tracker: KeyTracker,
+        mut key_tracker: KeyTracker<K_LEN>,
     ) -> Result<(), ArtError> {
-        debug_assert!(key_tracker.len() != 8);
+        debug_assert!(key_tracker.len() != K_LEN);
 
         let node = unsafe { &*node.as_ptr() }.read_lock()?;
         let prefix_result =
@@ -213,28 +243,30 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
                 self.copy_node(NodePtr::from_node(node.as_ref()), &key_tracker)
             }
             cmp::Ordering::Equal => {
-                let start_level = if self.start.len() > key_tracker.len() {
-                    self.start.as_bytes()[key_tracker.len()]
-                } else {
-                    0
-                };
+                let start_level = Self::bound_byte(self.start, key_tracker.len(), 0);
+
+                if key_tracker.len() == K_LEN - 1 {
+                    self.copy_values(&node, start_level, 255, &mut key_tracker)?;
+                    return Ok(());
+                }
 
-                let children = node.as_ref().get_children(start_level, 255);
+                let children = node
+                    .as_ref()
+                    .get_children(start_level, 255)
+                    .directed(self.reverse);
 
                 for (k, n) in children {
                     node.check_version()?;
 
                     key_tracker.push(k);
-                    if key_tracker.len() == MAX_KEY_LEN {
-                        self.copy_node(n, &key_tracker)?;
-                    } else if k == start_level {
+                    if k == start_level {
                         self.find_start(n, &node, key_tracker.clone())?;
                     } else if k > start_level {
                         let cur_key = KeyTracker::append_prefix(n, &key_tracker);
                         self.copy_node(n, &cur_key)?;
                     }
                     key_tracker.pop();
-                    if self.to_continue != 0 {
+                    if self.to_continue {
                         break;
                     }
                 }
@@ -244,80 +276,103 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
         }
     }
 
-    fn copy_node(&mut self, node: NodePtr, key_tracker: &KeyTracker) -> Result<(), ArtError> {
-        if key_tracker.len() == MAX_KEY_LEN {
-            if self.key_in_range(key_tracker) {
-                if self.result_found == self.result.len() {
-                    self.to_continue = node.as_tid();
-                    return Ok(());
-                }
-                self.result[self.result_found] = (key_tracker.to_usize_key(), node.as_tid());
-                self.result_found += 1;
-            };
-        } else {
-            let node = unsafe { &*node.as_ptr() }.read_lock()?;
-            let mut key_tracker = key_tracker.clone();
+    /// Copies all values in the subtree of `node`, `key_tracker` is the key up to the node's branching byte.
+    fn copy_node(
+        &mut self,
+        node: NodePtr,
+        key_tracker: &KeyTracker<K_LEN>,
+    ) -> Result<(), ArtError> {
+        let node = unsafe { &*node.as_ptr() }.read_lock()?;
+        let mut key_tracker = key_tracker.clone();
 
-            let children = node.as_ref().get_children(0, 255);
+        if key_tracker.len() == K_LEN - 1 {
+            return self.copy_values(&node, 0, 255, &mut key_tracker);
+        }
 
-            for (k, c) in children {
-                node.check_version()?;
+        let children = node.as_ref().get_children(0, 255).directed(self.reverse);
 
-                key_tracker.push(k);
+        for (k, c) in children {
+            node.check_version()?;
 
-                let cur_key = KeyTracker::append_prefix(c, &key_tracker);
-                self.copy_node(c, &cur_key)?;
+            key_tracker.push(k);
 
-                if self.to_continue != 0 {
-                    break;
-                }
+            let cur_key = KeyTracker::append_prefix(c, &key_tracker);
+            self.copy_node(c, &cur_key)?;
 
-                key_tracker.pop();
+            if self.to_continue {
+                break;
             }
+
+            key_tracker.pop();
         }
         Ok(())
     }
 
+    /// Copies the values of the last level `node` within [start, end].
+    fn copy_values(
+        &mut self,
+        node: &ReadGuard,
+        start: u8,
+        end: u8,
+        key_tracker: &mut KeyTracker<K_LEN>,
+    ) -> Result<(), ArtError> {
+        for (k, v) in node
+            .as_ref()
+            .get_values::<V>(start, end)
+            .directed(self.reverse)
+        {
+            node.check_version()?;
+
+            key_tracker.push(k);
+            self.copy_value(v, key_tracker);
+            key_tracker.pop();
+
+            if self.to_continue {
+                break;
+            }
+        }
+        node.check_version()?;
+        Ok(())
+    }
+
+    fn copy_value(&mut self, value: V, key_tracker: &KeyTracker<K_LEN>) {
+        if self.key_in_range(key_tracker) {
+            if self.result_found == self.result.len() {
+                self.to_continue = true;
+                return;
+            }
+            (self.fill)(
+                &mut self.result[self.result_found],
+                key_tracker.as_bytes(),
+                value,
+            );
+            self.result_found += 1;
+        }
+    }
+
     fn check_prefix_compare(
         &self,
         n: &BaseNode,
-        k: &T,
+        k: Bound<&T>,
         fill_key: u8,
-        key_tracker: &mut KeyTracker,
+        key_tracker: &mut KeyTracker<K_LEN>,
     ) -> cmp::Ordering {
         let n_prefix = n.prefix();
-        if !n_prefix.is_empty() {
-            let skip_len = key_tracker.len();
-            for (i, cur_key) in n_prefix.iter().skip(skip_len).enumerate() {
-                let k_level = if k.len() > key_tracker.len() {
-                    k.as_bytes()[key_tracker.len()]
-                } else {
-                    fill_key
-                };
+        for (i, cur_key) in n_prefix.iter().enumerate() {
+            let k_level = Self::bound_byte(k, key_tracker.len(), fill_key);
 
-                key_tracker.push(*cur_key);
+            key_tracker.push(*cur_key);
 
-                if *cur_key < k_level {
-                    for v in n_prefix
-                        .iter()
-                        .skip(skip_len)
-                        .take(n_prefix.len() - skip_len)
-                        .skip(i + 1)
-                    {
-                        key_tracker.push(*v);
-                    }
-                    return cmp::Ordering::Less;
-                } else if *cur_key > k_level {
-                    for v in n_prefix
-                        .iter()
-                        .skip(skip_len)
-                        .take(n_prefix.len() - skip_len)
-                        .skip(i + 1)
-                    {
-                        key_tracker.push(*v);
-                    }
-                    return cmp::Ordering::Greater;
+            if *cur_key < k_level {
+                for v in n_prefix.iter().skip(i + 1) {
+                    key_tracker.push(*v);
                 }
+                return cmp::Ordering::Less;
+            } else if *cur_key > k_level {
+                for v in n_prefix.iter().skip(i + 1) {
+                    key_tracker.push(*v);
+                }
+                return cmp::Ordering::Greater;
             }
         }
         cmp::Ordering::Equal
@@ -326,43 +381,26 @@ impl<'a, T: RawKey> RangeScan<'a, T> {
     fn check_prefix_equals(
         &self,
         n: &BaseNode,
-        key_tracker: &mut KeyTracker,
+        key_tracker: &mut KeyTracker<K_LEN>,
     ) -> PrefixCheckEqualsResult {
         let n_prefix = n.prefix();
 
-        if !n_prefix.is_empty() {
-            let skip_len = key_tracker.len();
-            for (i, cur_key) in n_prefix.iter().skip(skip_len).enumerate() {
-                let level = key_tracker.len();
-                let start_level = if self.start.len() > level {
-                    self.start.as_bytes()[level]
-                } else {
-                    0
-                };
-
-                let end_level = if self.end.len() > level {
-                    self.end.as_bytes()[level]
-                } else {
-                    255
-                };
-
-                if (*cur_key == start_level) && (*cur_key == end_level) {
-                    key_tracker.push(*cur_key);
-                    continue;
-                } else if (*cur_key >= start_level) && (*cur_key <= end_level) {
-                    key_tracker.push(*cur_key);
-                    for v in n_prefix
-                        .iter()
-                        .skip(skip_len)
-                        .take(n_prefix.len() - skip_len)
-                        .skip(i + 1)
-                    {
-                        key_tracker.push(*v);
-                    }
-                    return PrefixCheckEqualsResult::Contained;
-                } else if *cur_key < start_level || *cur_key > end_level {
-                    return PrefixCheckEqualsResult::NotMatch;
+        for (i, cur_key) in n_prefix.iter().enumerate() {
+            let level = key_tracker.len();
+            let start_level = Self::bound_byte(self.start, level, 0);
+            let end_level = Self::bound_byte(self.end, level, 255);
+
+            if (*cur_key == start_level) && (*cur_key == end_level) {
+                key_tracker.push(*cur_key);
+                continue;
+            } else if (*cur_key >= start_level) && (*cur_key <= end_level) {
+                key_tracker.push(*cur_key);
+                for v in n_prefix.iter().skip(i + 1) {
+                    key_tracker.push(*v);
                 }
+                return PrefixCheckEqualsResult::Contained;
+            } else if *cur_key < start_level || *cur_key > end_level {
+                return PrefixCheckEqualsResult::NotMatch;
             }
         }
         PrefixCheckEqualsResult::BothMatch
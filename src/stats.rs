@@ -1,9 +1,6 @@
 use std::fmt::Display;
 
-use crate::{
-    base_node::{BaseNode, NodeType, MAX_KEY_LEN},
-    Allocator, RawKey, RawTree,
-};
+use crate::{base_node::NodeType, key::RawKey, node_ptr::NodePtr, Allocator, RawTree};
 
 #[derive(Default, Debug, serde::Serialize)]
 pub struct NodeStats(Vec<LevelStats>);
@@ -79,10 +76,10 @@ impl LevelStats {
     }
 
     fn memory_size(&self) -> usize {
-        self.n4.0 * NodeType::N4.node_layout().size()
-            + self.n16.0 * NodeType::N16.node_layout().size()
-            + self.n48.0 * NodeType::N48.node_layout().size()
-            + self.n256.0 * NodeType::N256.node_layout().size()
+        self.n4.0 * NodeType::N4.node_layout::<NodePtr>().size()
+            + self.n16.0 * NodeType::N16.node_layout::<NodePtr>().size()
+            + self.n48.0 * NodeType::N48.node_layout::<NodePtr>().size()
+            + self.n256.0 * NodeType::N256.node_layout::<NodePtr>().size()
     }
 
     fn total_nodes(&self) -> usize {
@@ -90,12 +87,14 @@ impl LevelStats {
     }
 }
 
-impl<T: RawKey, A: Allocator + Clone> RawTree<T, A> {
+impl<T: RawKey, V: Copy + PartialEq, A: Allocator + Clone, const K_LEN: usize>
+    RawTree<T, V, A, K_LEN>
+{
     /// Returns the node stats for the tree.
     pub fn stats(&self) -> NodeStats {
         let mut node_stats = NodeStats::default();
 
-        let mut sub_nodes = vec![(0, 0, self.root as *const BaseNode)];
+        let mut sub_nodes = vec![(0, 0, self.root)];
 
         while let Some((level, key_level, node)) = sub_nodes.pop() {
             let node = unsafe { &*node };
@@ -123,15 +122,18 @@ impl<T: RawKey, A: Allocator + Clone> RawTree<T, A> {
                 }
             }
 
+            if key_level == (K_LEN - 1) {
+                // the slots of the last level nodes are values
+                continue;
+            }
+
             let children = node.get_children(0, 255);
             for (_k, n) in children {
-                if key_level != (MAX_KEY_LEN - 1) {
-                    sub_nodes.push((
-                        level + 1,
-                        key_level + 1 + unsafe { &*n.as_ptr() }.prefix().len(),
-                        n.as_ptr(),
-                    ));
-                }
+                sub_nodes.push((
+                    level + 1,
+                    key_level + 1 + unsafe { &*n.as_ptr() }.prefix().len(),
+                    n.as_ptr(),
+                ));
             }
         }
         node_stats
@@ -3,7 +3,7 @@ use std::sync::{
     Arc,
 };
 
-use crate::{error::OOMError, node_256::Node256, node_4::Node4, Allocator, Art};
+use crate::{error::OOMError, node_256::Node256, node_4::Node4, Allocator, Art, ArtSet};
 
 struct SmallAllocatorInner {
     max_size: AtomicUsize,
@@ -71,3 +71,51 @@ fn insert_but_only_once() {
     let rv = art.insert(usize::MAX, 100, &guard);
     assert!(rv.is_err());
 }
+
+#[test]
+fn long_path_only_partially_allocated() {
+    // a 16 byte key needs a chain of two `Node4`s below the root
+    let allocator =
+        SmallAllocator::new(std::mem::size_of::<Node256>() + std::mem::size_of::<Node4>());
+    let art = Art::<u128, usize, SmallAllocator, 16>::new(allocator.clone());
+    let guard = art.pin();
+    let rv = art.insert(u128::MAX, 100, &guard);
+    assert!(rv.is_err());
+    assert!(art.get(&u128::MAX, &guard).is_none());
+}
+
+#[test]
+fn set_leaves_are_bitmaps() {
+    // keys sharing the first 7 bytes fit into a single last level node of the set
+    let allocator =
+        SmallAllocator::new(std::mem::size_of::<Node256>() + std::mem::size_of::<Node256<()>>());
+    let set = ArtSet::<u64, SmallAllocator>::new(allocator.clone());
+    let guard = set.pin();
+    for k in 0..256 {
+        assert!(set.insert(k, &guard).unwrap());
+    }
+    assert!(set.insert(256, &guard).is_err());
+    assert_eq!(set.iter(&guard).count(), 256);
+}
+
+#[test]
+fn remove_after_oom() {
+    // shrinking a node on remove is optional, without memory the keys are removed in place
+    let allocator = SmallAllocator::new(64 * 1024);
+    let art = Art::<usize, usize, SmallAllocator>::new(allocator.clone());
+    let guard = art.pin();
+    let inserted = (0..)
+        .map(|k| k * 3)
+        .take_while(|k| art.insert(*k, *k, &guard).is_ok())
+        .count();
+    assert!(inserted > 1_000);
+    allocator.0.max_size.store(0, Ordering::Relaxed);
+
+    let (a, b) = (inserted / 3 * 3, inserted * 2 / 3 * 3);
+    for k in (0..a).step_by(3) {
+        assert_eq!(art.remove(&k, &guard), Some(k));
+    }
+    assert_eq!(art.remove_range(a..b, &guard), (b - a) / 3);
+    assert_eq!(art.retain(|_, _| false, &guard), inserted - b / 3);
+    assert!(art.is_empty());
+}
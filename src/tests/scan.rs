@@ -1,12 +1,27 @@
 use std::sync::Arc;
 use std::thread;
 
-use crate::{key::TestingKey, tree::RawTree, RawKey};
+use crate::{key::TestingKey, tree::RawTree};
 
 use rand::prelude::StdRng;
 use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 
+fn range(
+    tree: &RawTree<TestingKey>,
+    start: &TestingKey,
+    end: &TestingKey,
+    result: &mut [(usize, usize)],
+    guard: &crossbeam_epoch::Guard,
+) -> usize {
+    tree.range(
+        &(start..end),
+        result,
+        &mut |slot, key, value| *slot = (usize::from_be_bytes(key.try_into().unwrap()), value),
+        guard,
+    )
+}
+
 #[test]
 fn small_scan() {
     let tree = RawTree::default();
@@ -23,11 +38,11 @@ fn small_scan() {
     let high_key = TestingKey::key_from(low_v + scan_cnt);
 
     let mut results = [(0, 0); 20];
-    let scan_r = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scan_r = range(&tree, &low_key, &high_key, &mut results, &guard);
 
     assert_eq!(scan_r, scan_cnt);
-    for i in 0..scan_r {
-        assert_eq!(results[i].1, low_v + i);
+    for (i, r) in results.iter().take(scan_r).enumerate() {
+        assert_eq!(r.1, low_v + i);
     }
 }
 
@@ -60,7 +75,7 @@ fn large_scan() {
 
         let mut scan_results = vec![(0, 0); *scan_cnt];
 
-        let r_found = tree.range(&low_key, &high_key, &mut scan_results, &guard);
+        let r_found = range(&tree, &low_key, &high_key, &mut scan_results, &guard);
         assert_eq!(r_found, *scan_cnt);
 
         for (i, v) in scan_results.iter().enumerate() {
@@ -77,7 +92,7 @@ fn large_scan() {
         let high_key = TestingKey::key_from(low_key_v + scan_cnt);
 
         let mut scan_results = vec![(0, 0); *scan_cnt];
-        let r_found = tree.range(&low_key, &high_key, &mut scan_results, &guard);
+        let r_found = range(&tree, &low_key, &high_key, &mut scan_results, &guard);
         assert_eq!(r_found, 0);
     }
 }
@@ -111,7 +126,7 @@ fn large_scan_small_buffer() {
 
         let mut scan_results = vec![(0, 0); (*scan_cnt) / 2];
 
-        let r_found = tree.range(&low_key, &high_key, &mut scan_results, &guard);
+        let r_found = range(&tree, &low_key, &high_key, &mut scan_results, &guard);
         assert_eq!(r_found, *scan_cnt / 2);
 
         for (i, v) in scan_results.iter().enumerate() {
@@ -125,7 +140,7 @@ fn large_scan_small_buffer() {
         let high_key = TestingKey::key_from(0x6_ffff);
         let mut scan_results = vec![(0, 0); *scan_cnt];
 
-        let r_found = tree.range(&low_key, &high_key, &mut scan_results, &guard);
+        let r_found = range(&tree, &low_key, &high_key, &mut scan_results, &guard);
         assert_eq!(r_found, *scan_cnt);
 
         for (i, v) in scan_results.iter().enumerate() {
@@ -167,7 +182,7 @@ fn test_insert_and_scan() {
             let high_key = TestingKey::key_from(low_key_v + scan_cnt);
 
             let mut scan_results = vec![(0, 0); *scan_cnt];
-            let _v = tree.range(&low_key, &high_key, &mut scan_results, &guard);
+            let _v = range(&tree, &low_key, &high_key, &mut scan_results, &guard);
         }));
     }
 
@@ -208,7 +223,7 @@ fn fuzz_0() {
     let high_key = TestingKey::key_from(0);
 
     let mut results = vec![(0, 0); 255];
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 0);
 }
 
@@ -225,12 +240,12 @@ fn fuzz_1() {
     let high_key = TestingKey::key_from(scan_key + 255);
 
     let mut results = vec![(0, 0); 256];
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 0);
 
     let low_key = TestingKey::key_from(key);
     let high_key = TestingKey::key_from(key + 255);
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 1);
 }
 
@@ -249,7 +264,7 @@ fn fuzz_2() {
     let high_key = TestingKey::key_from(scan_key + 253);
 
     let mut results = vec![(0, 0); 256];
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 0);
 }
 
@@ -268,14 +283,14 @@ fn fuzz_3() {
     let high_key = TestingKey::key_from(scan_key + 253);
 
     let mut results = vec![(0, 0); 256];
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 2);
 
     let scan_key = 4294967000;
     let low_key = TestingKey::key_from(scan_key);
     let high_key = TestingKey::key_from(scan_key + 253);
 
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 1);
 }
 
@@ -294,7 +309,7 @@ fn fuzz_4() {
     let high_key = TestingKey::key_from(scan_key + 253);
 
     let mut results = vec![(0, 0); 256];
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 0);
 }
 
@@ -313,7 +328,7 @@ fn fuzz_5() {
     let high_key = TestingKey::key_from(scan_key + 255);
 
     let mut results = vec![(0, 0); 256];
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 1);
 }
 
@@ -332,6 +347,6 @@ fn fuzz_6() {
     let high_key = TestingKey::key_from(scan_key + 255);
 
     let mut results = vec![(0, 0); 256];
-    let scanned = tree.range(&low_key, &high_key, &mut results, &guard);
+    let scanned = range(&tree, &low_key, &high_key, &mut results, &guard);
     assert_eq!(scanned, 1);
 }
@@ -4,8 +4,10 @@ use shuttle::thread;
 #[cfg(not(all(feature = "shuttle", test)))]
 use std::thread;
 
-use crate::key::{RawKey, TestingKey};
+use crate::error::ArtError;
+use crate::key::TestingKey;
 use crate::tree::RawTree;
+use crate::utils::RetryBudget;
 use std::sync::Arc;
 
 #[test]
@@ -52,6 +54,7 @@ fn test_sparse_keys() {
         let v = tree.get(&TestingKey::key_from(*i), &guard).unwrap();
         assert_eq!(v, *i);
     }
+    assert_eq!(tree.len(), key_cnt - delete_cnt);
 
     #[cfg(feature = "stats")]
     println!("{}", tree.stats());
@@ -100,6 +103,7 @@ fn test_concurrent_insert() {
         let val = tree.get(&TestingKey::key_from(*v), &guard).unwrap();
         assert_eq!(val, *v);
     }
+    assert_eq!(tree.len(), key_space.len());
 }
 
 #[cfg(all(feature = "shuttle", test))]
@@ -185,3 +189,612 @@ fn shuttle_concurrent_insert_read() {
 
     runner.run(test_concurrent_insert_read);
 }
+
+#[test]
+fn try_ops_give_up() {
+    // the fail points make 5% of the lock attempts fail, so a budget without restarts gives up sometimes
+    let key_cnt = 10_000;
+    let tree = RawTree::default();
+    let guard = crossbeam_epoch::pin();
+    let no_restart = RetryBudget::restarts(0);
+    let enough = RetryBudget::restarts(1_000);
+
+    let mut contended = 0;
+    for k in 0..key_cnt {
+        let key = TestingKey::key_from(k);
+        match tree.try_compute_or_insert(key.clone(), &mut |_| k, &no_restart, &guard) {
+            Ok(old) => assert_eq!(old, None),
+            Err(ArtError::Oom) => unreachable!(),
+            Err(_) => {
+                contended += 1;
+                assert_eq!(tree.get(&key, &guard), None);
+                let old = tree.try_compute_or_insert(key, &mut |_| k, &enough, &guard);
+                assert_eq!(old.unwrap(), None);
+            }
+        }
+    }
+    assert!(contended > 0);
+    assert_eq!(tree.len(), key_cnt);
+
+    let mut contended = 0;
+    for k in 0..key_cnt {
+        let key = TestingKey::key_from(k);
+        match tree.try_get(&key, &no_restart, &guard) {
+            Ok(v) => assert_eq!(v, Some(k)),
+            Err(_) => contended += 1,
+        }
+        assert_eq!(tree.try_get(&key, &enough, &guard).unwrap(), Some(k));
+    }
+    assert!(contended > 0);
+
+    // a past deadline still makes the first attempt
+    let expired = RetryBudget::deadline(std::time::Instant::now());
+    let mut contended = 0;
+    for k in 0..key_cnt {
+        let key = TestingKey::key_from(k);
+        match tree.try_compute_if_present(&key, &mut |_| None, &expired, &guard) {
+            Ok(removed) => assert_eq!(removed, Some((k, None))),
+            Err(_) => {
+                contended += 1;
+                let removed = tree.try_compute_if_present(&key, &mut |_| None, &enough, &guard);
+                assert_eq!(removed.unwrap(), Some((k, None)));
+            }
+        }
+    }
+    assert!(contended > 0);
+    assert_eq!(tree.len(), 0);
+}
+
+#[test]
+fn node_shrink_on_remove() {
+    use crate::base_node::NodeType;
+
+    // the type of the node below the root that all keys share
+    fn shared_node_type(tree: &RawTree<TestingKey>) -> NodeType {
+        let child = unsafe { &*tree.root }.get_child(0).unwrap();
+        unsafe { &*child.as_ptr() }.get_type()
+    }
+
+    let guard = crossbeam_epoch::pin();
+    let remove = |tree: &RawTree<TestingKey>, k: usize, shift: usize| {
+        let key = TestingKey::key_from(k << shift);
+        let removed = tree.compute_if_present(&key, &mut |_| None, &guard);
+        assert_eq!(removed, Some((k, None)));
+    };
+
+    // a last level node holding the values, and an inner node whose children are removed with their last value
+    for shift in [0, 8] {
+        let tree = RawTree::default();
+        for k in 0..256 {
+            tree.insert(TestingKey::key_from(k << shift), k, &guard)
+                .unwrap();
+        }
+        assert_eq!(shared_node_type(&tree), NodeType::N256);
+
+        let mut remaining = 256;
+        for (threshold, shrunk) in [(37, NodeType::N48), (12, NodeType::N16), (3, NodeType::N4)] {
+            while remaining > threshold + 1 {
+                remaining -= 1;
+                remove(&tree, remaining, shift);
+            }
+            assert_ne!(shared_node_type(&tree), shrunk);
+            remaining -= 1;
+            remove(&tree, remaining, shift);
+            assert_eq!(shared_node_type(&tree), shrunk);
+        }
+
+        // one more key grows it back, removing that key again does not shrink it right away
+        for k in 3..5 {
+            tree.insert(TestingKey::key_from(k << shift), k, &guard)
+                .unwrap();
+        }
+        assert_eq!(shared_node_type(&tree), NodeType::N16);
+        remove(&tree, 4, shift);
+        assert_eq!(shared_node_type(&tree), NodeType::N16);
+        remove(&tree, 3, shift);
+        assert_eq!(shared_node_type(&tree), NodeType::N4);
+
+        for k in 0..256 {
+            let v = tree.get(&TestingKey::key_from(k << shift), &guard);
+            assert_eq!(v, (k < 3).then_some(k));
+        }
+        assert_eq!(tree.len(), 3);
+    }
+}
+
+#[test]
+fn short_keys_share_last_level_nodes() {
+    use crate::key::{FixedKey, RawKey};
+    use crate::DefaultAllocator;
+
+    // the padding of a `u32` key is a prefix shared by all keys, consecutive keys fill a last level node
+    let guard = crossbeam_epoch::pin();
+    let tree = RawTree::<FixedKey<8>, usize, DefaultAllocator, 8>::new(DefaultAllocator {});
+    for k in 0..1024u32 {
+        tree.insert(FixedKey::new(&k), k as usize, &guard).unwrap();
+    }
+    let key = FixedKey::<8>::new(&0x300u32);
+    assert_eq!(&key.as_bytes()[..4], &[0; 4]);
+    assert_eq!(key.decode::<u32>(), 0x300);
+
+    let mut node = unsafe { &*tree.root };
+    let mut level = 0;
+    loop {
+        level += node.prefix_len();
+        if level == 7 {
+            break;
+        }
+        node = unsafe { &*node.get_child(key.as_bytes()[level]).unwrap().as_ptr() };
+        level += 1;
+    }
+    assert_eq!(node.get_count(), 256);
+}
+
+#[cfg(feature = "db_extension")]
+#[test]
+fn sample_descents_on_random_keys() {
+    use crate::key::FixedKey;
+    use crate::DefaultAllocator;
+
+    // uniformly random keys are the common case, the descents should pick them without scanning the keys
+    let guard = crossbeam_epoch::pin();
+    let mut r = StdRng::seed_from_u64(42);
+    let tree = RawTree::<FixedKey<8>, usize, DefaultAllocator, 8>::new(DefaultAllocator {});
+    for _ in 0..100_000 {
+        tree.insert(FixedKey::new(&r.gen::<u64>()), 0, &guard)
+            .unwrap();
+    }
+
+    let n = 2_000;
+    let attempts = tree
+        .sample_attempts(n, tree.len())
+        .expect("the descents are expected to be cheaper than a scan");
+    let scans = (0..n)
+        .filter(|_| tree.sample(&mut r, attempts, &guard).is_err())
+        .count();
+    // a sample gives up with a probability of about e^-8
+    assert!(scans <= 5, "{scans} of {n} samples fell back to a scan");
+}
+
+#[test]
+fn path_recompression_on_remove() {
+    use crate::key::{FixedKey, RawKey};
+    use crate::DefaultAllocator;
+
+    // the number of nodes on the path to `key`
+    fn depth<T: RawKey, const K_LEN: usize>(
+        tree: &RawTree<T, usize, DefaultAllocator, K_LEN>,
+        key: &T,
+    ) -> usize {
+        let mut node = tree.root;
+        let mut level = 0;
+        let mut depth = 1;
+        loop {
+            let n = unsafe { &*node };
+            level += n.prefix_len();
+            if level == K_LEN - 1 {
+                return depth;
+            }
+            node = n.get_child(key.as_bytes()[level]).unwrap().as_ptr();
+            level += 1;
+            depth += 1;
+        }
+    }
+
+    fn is_empty<T: RawKey, const K_LEN: usize>(
+        tree: &RawTree<T, usize, DefaultAllocator, K_LEN>,
+    ) -> bool {
+        unsafe { &*tree.root }.get_children(0, 255).next().is_none()
+    }
+
+    let guard = crossbeam_epoch::pin();
+    let tree = RawTree::default();
+    let key = TestingKey::key_from;
+    let remove = |tree: &RawTree<TestingKey>, k: usize| {
+        let removed = tree.compute_if_present(&key(k), &mut |_| None, &guard);
+        assert_eq!(removed, Some((k, None)));
+    };
+
+    tree.insert(key(0), 0, &guard).unwrap();
+    assert_eq!(depth(&tree, &key(0)), 2);
+    tree.insert(key(1 << 8), 1 << 8, &guard).unwrap();
+    assert_eq!(depth(&tree, &key(0)), 3);
+    tree.insert(key(1 << 16), 1 << 16, &guard).unwrap();
+    assert_eq!(depth(&tree, &key(0)), 4);
+
+    // the inner nodes left with a single child are merged into it, reversing the splits
+    remove(&tree, 1 << 16);
+    assert_eq!(depth(&tree, &key(0)), 3);
+    remove(&tree, 1 << 8);
+    assert_eq!(depth(&tree, &key(0)), 2);
+    assert_eq!(tree.get(&key(0), &guard), Some(0));
+
+    // the path is unlinked with the last value
+    remove(&tree, 0);
+    assert!(is_empty(&tree));
+
+    // a 16 byte key hangs below a chain of nodes, merging stops where the prefix would not fit into a node
+    let tree = RawTree::<FixedKey<16>, usize, DefaultAllocator, 16>::new(DefaultAllocator {});
+    let key = |k: u128| FixedKey::<16>::new(&k);
+    tree.insert(key(0), 0, &guard).unwrap();
+    assert_eq!(depth(&tree, &key(0)), 3);
+    tree.insert(key(1), 1, &guard).unwrap();
+    tree.insert(key(1 << 8), 2, &guard).unwrap();
+    assert_eq!(depth(&tree, &key(0)), 4);
+    tree.compute_if_present(&key(1 << 8), &mut |_| None, &guard);
+    assert_eq!(depth(&tree, &key(0)), 3);
+    tree.compute_if_present(&key(1), &mut |_| None, &guard);
+    assert_eq!(depth(&tree, &key(0)), 3);
+    tree.compute_if_present(&key(0), &mut |_| None, &guard);
+    assert!(is_empty(&tree));
+    assert_eq!(tree.len(), 0);
+
+    // churn on a small key space, the tree must stay consistent
+    let tree = RawTree::default();
+    let mut r = StdRng::seed_from_u64(42);
+    let mut expected = std::collections::BTreeSet::new();
+    for _ in 0..100_000 {
+        let k = r.gen_range(0..1 << 12) << (8 * r.gen_range(0..3));
+        if r.gen_bool(0.5) {
+            tree.insert(TestingKey::key_from(k), k, &guard).unwrap();
+            expected.insert(k);
+        } else {
+            let removed = tree.compute_if_present(&TestingKey::key_from(k), &mut |_| None, &guard);
+            assert_eq!(removed.is_some(), expected.remove(&k));
+        }
+    }
+    for k in expected.iter() {
+        assert_eq!(tree.get(&TestingKey::key_from(*k), &guard), Some(*k));
+    }
+    assert_eq!(tree.len(), expected.len());
+    for k in std::mem::take(&mut expected) {
+        tree.compute_if_present(&TestingKey::key_from(k), &mut |_| None, &guard)
+            .unwrap();
+    }
+    assert!(is_empty(&tree));
+}
+
+#[test]
+fn remove_covered_subtrees() {
+    use std::ops::Bound;
+
+    // the fail points make every lock attempt fail at times, a step locks every unlinked node,
+    // so the subtrees are kept small here
+    let guard = crossbeam_epoch::pin();
+    let key = TestingKey::key_from;
+    let tree = RawTree::default();
+    for k in 0..0x400 {
+        tree.insert(key(k), k, &guard).unwrap();
+    }
+    let shared = || unsafe { &*(*tree.root).get_child(0).unwrap().as_ptr() };
+
+    // the last level nodes of the keys 0x100..0x300 are unlinked from their parent in one update
+    let (start, end) = (key(0x100), key(0x300));
+    let removed = tree.remove_covered(Bound::Included(&start), Bound::Excluded(&end), &guard);
+    assert_eq!(removed, 0x200);
+    assert_eq!(shared().get_count(), 2);
+    assert_eq!(tree.len(), 0x200);
+
+    // the values next to the bounds are left in place
+    let (start, end) = (key(0x80), key(0x380));
+    let removed = tree.remove_covered(Bound::Excluded(&start), Bound::Included(&end), &guard);
+    assert_eq!(removed, 0);
+    assert_eq!(tree.len(), 0x200);
+
+    // a last level node whose values are all within the range is unlinked, though its key span straddles the bound,
+    // and the node left with one child is merged into it
+    for k in [0x300, 0x301, 0x3ff] {
+        tree.compute_if_present(&key(k), &mut |_| None, &guard);
+    }
+    let start = key(0x302);
+    let removed = tree.remove_covered(Bound::Included(&start), Bound::Unbounded, &guard);
+    assert_eq!(removed, 0xfd);
+    assert_eq!(shared().get_count(), 0x100);
+
+    for k in 0..0x400 {
+        assert_eq!(tree.get(&key(k), &guard), (k < 0x100).then_some(k));
+    }
+    assert_eq!(tree.len(), 0x100);
+
+    // the root is not unlinked, its children are
+    let removed = tree.remove_covered(Bound::Unbounded, Bound::Unbounded, &guard);
+    assert_eq!(removed, 0x100);
+    assert!(unsafe { &*tree.root }.get_children(0, 255).next().is_none());
+    assert_eq!(tree.len(), 0);
+}
+
+/// Asserts that the subtrees of `a` and `b` have the same nodes, `level` is the key position they branch on.
+fn assert_same_nodes(a: &crate::base_node::BaseNode, b: &crate::base_node::BaseNode, level: usize) {
+    assert_eq!(a.get_type(), b.get_type());
+    assert_eq!(a.prefix(), b.prefix());
+    assert_eq!(a.get_count(), b.get_count());
+    if level == 7 {
+        assert!(a
+            .get_values::<usize>(0, 255)
+            .eq(b.get_values::<usize>(0, 255)));
+        return;
+    }
+    for ((ka, ca), (kb, cb)) in a.get_children(0, 255).zip(b.get_children(0, 255)) {
+        assert_eq!(ka, kb);
+        let (ca, cb) = unsafe { (&*ca.as_ptr(), &*cb.as_ptr()) };
+        assert_same_nodes(ca, cb, level + 1 + ca.prefix_len());
+    }
+}
+
+#[test]
+fn bulk_load_final_node_sizes() {
+    use crate::utils::BackoffPolicy;
+    use crate::DefaultAllocator;
+
+    // inserts grow every node to the smallest type that fits its children, the loaded nodes must be the same
+    let guard = crossbeam_epoch::pin();
+    let mut r = StdRng::seed_from_u64(42);
+    let mut keys: Vec<usize> = (0..20_000)
+        .map(|_| r.gen::<usize>() >> r.gen_range(0..64))
+        .chain(0..1_000)
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    let inserted = RawTree::default();
+    for k in keys.iter() {
+        inserted
+            .insert(TestingKey::key_from(*k), *k, &guard)
+            .unwrap();
+    }
+    let entry = |k: &usize| (TestingKey::key_from(*k), *k);
+    let loaded: RawTree<TestingKey> = RawTree::from_sorted(
+        keys.iter().map(entry),
+        DefaultAllocator {},
+        BackoffPolicy::default(),
+    )
+    .unwrap();
+    let loaded_par: RawTree<TestingKey> = RawTree::from_sorted_par(
+        &keys,
+        entry,
+        4,
+        DefaultAllocator {},
+        BackoffPolicy::default(),
+    )
+    .unwrap();
+    for tree in [&loaded, &loaded_par] {
+        assert_same_nodes(unsafe { &*inserted.root }, unsafe { &*tree.root }, 0);
+        assert_eq!(tree.len(), keys.len());
+        for k in keys.iter() {
+            assert_eq!(tree.get(&TestingKey::key_from(*k), &guard), Some(*k));
+        }
+    }
+
+    // keys with a common prefix are split at the first byte in which they differ,
+    // the loaded subtrees hang below a node at that level
+    let mut keys: Vec<usize> = keys
+        .iter()
+        .map(|k| (0x42 << 40) | (k & 0xff_ffff))
+        .collect();
+    keys.sort();
+    keys.dedup();
+    let inserted: RawTree<TestingKey> = RawTree::default();
+    for k in keys.iter() {
+        inserted
+            .insert(TestingKey::key_from(*k), *k, &guard)
+            .unwrap();
+    }
+    let loaded_par: RawTree<TestingKey> = RawTree::from_sorted_par(
+        &keys,
+        entry,
+        4,
+        DefaultAllocator {},
+        BackoffPolicy::default(),
+    )
+    .unwrap();
+    assert_same_nodes(unsafe { &*inserted.root }, unsafe { &*loaded_par.root }, 0);
+    assert_eq!(loaded_par.len(), keys.len());
+}
+
+#[test]
+fn get_many_interleaved() {
+    let guard = crossbeam_epoch::pin();
+    let mut r = StdRng::seed_from_u64(42);
+    let tree = RawTree::default();
+    for _ in 0..10_000 {
+        let k = r.gen::<usize>() >> r.gen_range(0..64);
+        tree.insert(TestingKey::key_from(k), k, &guard).unwrap();
+    }
+
+    // present and missing keys, with a batch that is not a multiple of the group size
+    let mut keys: Vec<usize> = (0..1_000)
+        .map(|_| r.gen::<usize>() >> r.gen_range(0..64))
+        .chain(0..100)
+        .collect();
+    let check = |keys: &[usize]| {
+        let encoded: Vec<_> = keys.iter().map(|k| TestingKey::key_from(*k)).collect();
+        let mut values = vec![Some(usize::MAX); keys.len()];
+        tree.get_many(&encoded, &mut values, &guard);
+        for (k, v) in encoded.iter().zip(values) {
+            assert_eq!(v, tree.get(k, &guard));
+        }
+    };
+    check(&keys);
+    // sorted keys share their paths, repeated keys their whole lookup
+    keys.sort();
+    check(&keys);
+    keys.extend_from_within(..);
+    keys.sort();
+    check(&keys);
+    check(&[]);
+}
+
+#[test]
+fn commit_makes_room_and_unlinks() {
+    let guard = crossbeam_epoch::pin();
+    let tree: RawTree<TestingKey> = RawTree::default();
+    let inserted: RawTree<TestingKey> = RawTree::default();
+    let commit = |writes: &[(usize, Option<usize>)]| {
+        let mut keys: Vec<_> = writes
+            .iter()
+            .map(|(k, v)| (TestingKey::key_from(*k), *v))
+            .collect();
+        keys.sort_by(|a, b| a.0.cmp(&b.0));
+        let writes: Vec<_> = keys.iter().map(|(k, v)| (k, *v)).collect();
+        while tree.commit(&[], &writes, &guard).is_err() {}
+    };
+
+    // new subtrees are loaded at their final size, full nodes grow and compressed paths split like on insert
+    let steps: [&[usize]; 4] = [
+        &[0x100, 0x101, 0x2_0000],
+        &[0x102, 0x103, 0x104, 0x105, 0x2_0001],
+        &[0x1234_5678, 0x1200_0000, 0x1234_5600],
+        &[0x2_0100, 0x1234_0000],
+    ];
+    for keys in steps {
+        commit(&keys.iter().map(|k| (*k, Some(*k))).collect::<Vec<_>>());
+        for k in keys {
+            inserted
+                .insert(TestingKey::key_from(*k), *k, &guard)
+                .unwrap();
+        }
+        assert_same_nodes(unsafe { &*inserted.root }, unsafe { &*tree.root }, 0);
+        assert_eq!(tree.len(), inserted.len());
+    }
+
+    // removing the last values unlinks their nodes and the ancestors that only lead to them
+    let removed = [0x1234_5678, 0x1234_5600, 0x1234_0000, 0x1200_0000];
+    commit(&removed.map(|k| (k, None)));
+    assert_eq!(tree.len(), inserted.len() - removed.len());
+    assert!(removed
+        .iter()
+        .all(|k| tree.get(&TestingKey::key_from(*k), &guard).is_none()));
+    // the node branching on the fifth byte only keeps the child of the small keys
+    let node = unsafe { &*(*tree.root).get_child(0).unwrap().as_ptr() };
+    assert_eq!(node.prefix(), &[0, 0, 0]);
+    assert!(node.get_children(0, 255).map(|(k, _)| k).eq([0]));
+
+    let all: Vec<_> = steps
+        .iter()
+        .flat_map(|keys| keys.iter())
+        .map(|k| (*k, None))
+        .collect();
+    commit(&all);
+    assert_eq!(tree.len(), 0);
+    assert_eq!(unsafe { &*tree.root }.get_count(), 0);
+}
+
+/// Asserts that the subtree count of `node` and of every node below it is the number of values in the subtree,
+/// returns that number. `level` is the key position that `node` branches on.
+#[cfg(feature = "order_statistics")]
+fn assert_subtree_counts(node: &crate::base_node::BaseNode, level: usize) -> usize {
+    let values = if level == 7 {
+        node.get_count()
+    } else {
+        node.get_children(0, 255)
+            .map(|(_, c)| {
+                let c = unsafe { &*c.as_ptr() };
+                assert_subtree_counts(c, level + 1 + c.prefix_len())
+            })
+            .sum()
+    };
+    assert_eq!(node.keys(), Some(values));
+    values
+}
+
+#[cfg(feature = "order_statistics")]
+#[test]
+fn subtree_counts_follow_updates() {
+    use crate::utils::BackoffPolicy;
+    use crate::DefaultAllocator;
+    use std::ops::Bound;
+
+    let guard = crossbeam_epoch::pin();
+    let mut r = StdRng::seed_from_u64(42);
+    let mut keys: Vec<usize> = (0..5_000)
+        .map(|_| r.gen::<usize>() >> r.gen_range(0..64))
+        .chain(0..500)
+        .collect();
+    keys.sort();
+    keys.dedup();
+    let entry = |k: &usize| (TestingKey::key_from(*k), *k);
+    let loaded: RawTree<TestingKey> = RawTree::from_sorted_par(
+        &keys,
+        entry,
+        4,
+        DefaultAllocator {},
+        BackoffPolicy::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        assert_subtree_counts(unsafe { &*loaded.root }, 0),
+        keys.len()
+    );
+    let tree: Arc<RawTree<TestingKey>> = Arc::new(
+        RawTree::from_sorted(
+            keys.iter().map(entry),
+            DefaultAllocator {},
+            BackoffPolicy::default(),
+        )
+        .unwrap(),
+    );
+    assert_eq!(assert_subtree_counts(unsafe { &*tree.root }, 0), keys.len());
+
+    // inserts, removes, renames and transactions on overlapping keys grow, shrink, split and unlink nodes
+    let mut handlers = Vec::new();
+    for t in 0..4 {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            let guard = crossbeam_epoch::pin();
+            let mut r = StdRng::seed_from_u64(t);
+            for _ in 0..2_000 {
+                let k = r.gen_range(0..2_000usize) << r.gen_range(0..40);
+                let key = TestingKey::key_from(k);
+                match r.gen_range(0..4) {
+                    0 => {
+                        tree.insert(key, k, &guard).unwrap();
+                    }
+                    1 => {
+                        tree.compute_if_present(&key, &mut |_| None, &guard);
+                    }
+                    2 => {
+                        let new = TestingKey::key_from(k ^ (1 << r.gen_range(0..48)));
+                        tree.rename(&key, &new, &guard).unwrap();
+                    }
+                    _ => {
+                        let keys: Vec<_> = (0..4)
+                            .map(|i| {
+                                (
+                                    TestingKey::key_from(k + (i << 16)),
+                                    (i % 2 == 0).then_some(k),
+                                )
+                            })
+                            .collect();
+                        let writes: Vec<_> = keys.iter().map(|(k, v)| (k, *v)).collect();
+                        while tree.commit(&[], &writes, &guard).is_err() {}
+                    }
+                }
+            }
+        }));
+    }
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+    assert_eq!(assert_subtree_counts(unsafe { &*tree.root }, 0), tree.len());
+
+    // the fail points make every lock attempt fail at times, the range only covers a single last level node
+    let (start, end) = (TestingKey::key_from(0), TestingKey::key_from(0x100));
+    tree.remove_covered(Bound::Included(&start), Bound::Excluded(&end), &guard);
+    let len = assert_subtree_counts(unsafe { &*tree.root }, 0);
+    assert_eq!(len, tree.len());
+
+    // the ranks of the selected keys count up, the ranges between them hold one key each
+    let selected: Vec<_> = (0..len)
+        .map(|i| {
+            let (key, _) = tree.select(i, &guard).unwrap();
+            TestingKey::key_from(usize::from_be_bytes(key.as_bytes().try_into().unwrap()))
+        })
+        .collect();
+    assert!(tree.select(len, &guard).is_none());
+    for (i, key) in selected.iter().enumerate() {
+        assert_eq!(tree.rank(key, &guard), (i, true));
+        assert_eq!(tree.count_range(&(key..=key), &guard), 1);
+    }
+    assert_eq!(tree.count_range(&(start..end), &guard), 0);
+    assert_eq!(tree.count_range(&(..), &guard), len);
+}
@@ -0,0 +1,101 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use crate::{
+    epoch,
+    error::ArtError,
+    key::FixedKey,
+    lock::ReadGuard,
+    tree::{RawTree, Spot},
+    Allocator, ArtKey,
+};
+
+/// The reads and buffered writes of one attempt of [Art::transaction](crate::Art::transaction).
+///
+/// [Transaction::get] records the version of the node that holds the key, or the node where its lookup ended
+/// if the key is absent; [Transaction::put] and [Transaction::remove] are only applied when the transaction commits.
+/// The commit succeeds if none of the recorded nodes has changed in the meantime, otherwise the transaction is retried.
+pub struct Transaction<
+    'a,
+    K: ArtKey,
+    V: Clone + From<usize>,
+    A: Allocator + Clone + Send + 'static,
+    const K_LEN: usize,
+> where
+    usize: From<V>,
+{
+    tree: &'a RawTree<FixedKey<K_LEN>, usize, A, K_LEN>,
+    reads: Vec<ReadGuard<'a>>,
+    writes: BTreeMap<FixedKey<K_LEN>, Option<usize>>,
+    pt_key: PhantomData<K>,
+    pt_val: PhantomData<V>,
+}
+
+impl<'a, K: ArtKey, V: Clone + From<usize>, A: Allocator + Clone + Send, const K_LEN: usize>
+    Transaction<'a, K, V, A, K_LEN>
+where
+    usize: From<V>,
+{
+    pub(crate) fn new(tree: &'a RawTree<FixedKey<K_LEN>, usize, A, K_LEN>) -> Self {
+        Transaction {
+            tree,
+            reads: Vec::new(),
+            writes: BTreeMap::new(),
+            pt_key: PhantomData,
+            pt_val: PhantomData,
+        }
+    }
+
+    /// Returns the value of the key, as written by this transaction if it did.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use congee::Art;
+    /// let tree = Art::default();
+    /// let guard = tree.pin();
+    /// tree.insert(1, 42, &guard).unwrap();
+    ///
+    /// tree.transaction(
+    ///     |tx| {
+    ///         assert_eq!(tx.get(&1), Some(42));
+    ///         tx.put(1, 43);
+    ///         assert_eq!(tx.get(&1), Some(43));
+    ///     },
+    ///     &guard,
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn get(&mut self, k: &K) -> Option<V> {
+        let key = FixedKey::new(k);
+        if let Some(v) = self.writes.get(&key) {
+            return v.map(V::from);
+        }
+        let backoff = self.tree.backoff();
+        let located = loop {
+            match self.tree.locate(&key) {
+                Ok(located) => break located,
+                Err(_) => backoff.wait(),
+            }
+        };
+        self.reads.push(located.node);
+        match located.spot {
+            Spot::Value(v) => Some(V::from(v)),
+            _ => None,
+        }
+    }
+
+    /// Sets the value of the key when the transaction commits.
+    pub fn put(&mut self, k: K, v: V) {
+        self.writes.insert(FixedKey::new(&k), Some(usize::from(v)));
+    }
+
+    /// Removes the key when the transaction commits.
+    pub fn remove(&mut self, k: &K) {
+        self.writes.insert(FixedKey::new(k), None);
+    }
+
+    pub(crate) fn commit(self, guard: &epoch::Guard) -> Result<(), ArtError> {
+        let writes: Vec<_> = self.writes.iter().map(|(k, v)| (k, *v)).collect();
+        self.tree.commit(&self.reads, &writes, guard)
+    }
+}
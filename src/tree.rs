@@ -1,31 +1,59 @@
-use std::marker::PhantomData;
+use std::{
+    cmp,
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use crossbeam_epoch::Guard;
 
 use crate::{
-    base_node::{BaseNode, Node, Prefix, MAX_KEY_LEN},
+    base_node::{BaseNode, Node, NodeType, Prefix, MAX_PREFIX_LEN},
+    bulk_load::{make_inner, BulkLoader},
     error::{ArtError, OOMError},
     key::RawKey,
-    lock::ReadGuard,
+    lock::{ReadGuard, WriteGuard},
     node_256::Node256,
     node_4::Node4,
     node_ptr::NodePtr,
     range_scan::RangeScan,
-    utils::Backoff,
+    utils::{prefetch, Backoff, BackoffPolicy, Counter, KeyTracker, RetryBudget},
     Allocator, DefaultAllocator,
 };
 
+#[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+use crate::utils::FanOut;
+
 /// Raw interface to the ART tree.
 /// The `Art` is a wrapper around the `RawArt` that provides a safe interface.
 /// Unlike `Art`, it supports arbitrary `Key` types, see also `RawKey`.
-pub(crate) struct RawTree<K: RawKey, A: Allocator + Clone + 'static = DefaultAllocator> {
-    pub(crate) root: *const Node256,
+///
+/// All keys are `K_LEN` bytes long, the values are stored inline at depth `K_LEN - 1`:
+/// the nodes branching on the last key byte use `V` as their slot type instead of [NodePtr].
+pub(crate) struct RawTree<
+    K: RawKey,
+    V: Copy + PartialEq = usize,
+    A: Allocator + Clone + 'static = DefaultAllocator,
+    const K_LEN: usize = 8,
+> {
+    pub(crate) root: *const BaseNode,
     allocator: A,
+    len: Counter,
+    backoff: BackoffPolicy,
+    #[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+    fan_out: FanOut<K_LEN>,
     _pt_key: PhantomData<K>,
+    _pt_val: PhantomData<V>,
 }
 
-unsafe impl<K: RawKey, A: Allocator + Clone> Send for RawTree<K, A> {}
-unsafe impl<K: RawKey, A: Allocator + Clone> Sync for RawTree<K, A> {}
+unsafe impl<K: RawKey, V: Copy + PartialEq + Send, A: Allocator + Clone, const K_LEN: usize> Send
+    for RawTree<K, V, A, K_LEN>
+{
+}
+unsafe impl<K: RawKey, V: Copy + PartialEq + Sync, A: Allocator + Clone, const K_LEN: usize> Sync
+    for RawTree<K, V, A, K_LEN>
+{
+}
 
 impl<K: RawKey> Default for RawTree<K> {
     fn default() -> Self {
@@ -33,92 +61,865 @@ impl<K: RawKey> Default for RawTree<K> {
     }
 }
 
-impl<T: RawKey, A: Allocator + Clone> Drop for RawTree<T, A> {
+impl<T: RawKey, V: Copy + PartialEq, A: Allocator + Clone, const K_LEN: usize> Drop
+    for RawTree<T, V, A, K_LEN>
+{
     fn drop(&mut self) {
-        let mut sub_nodes = vec![(self.root as *const BaseNode, 0)];
+        unsafe {
+            self.drop_subtree(self.root, 0);
+        }
+    }
+}
+
+/// Where the keys of a subtree are relative to a range.
+enum Span {
+    Outside,
+    Inside,
+    Straddling,
+}
+
+/// The outcome of [RawTree::remove_covered_node] for a subtree.
+enum Covered {
+    /// Nothing was unlinked.
+    Nothing,
+    /// All keys of the subtree are within the range, it is left to the parent to unlink it.
+    All,
+    /// This many values were unlinked, the tree has changed.
+    Removed(usize),
+}
+
+/// Number of lookups that [RawTree::get_many] keeps in flight.
+pub(crate) const GET_MANY_GROUP: usize = 16;
+
+/// The fewest rejected descents after which [RawTree::sample] gives up.
+#[cfg(feature = "db_extension")]
+const SAMPLE_ATTEMPTS: usize = 64;
+
+/// [RawTree::sample] gives up after this many times the expected number of descents,
+/// i.e., with a probability of about `e^-8` if the descents are kept as often as expected.
+#[cfg(feature = "db_extension")]
+const SAMPLE_PATIENCE: f64 = 8.0;
+
+/// The state of a lookup of [RawTree::get_many].
+#[derive(Clone)]
+enum Lookup<'a, V> {
+    /// The node to read next, which is prefetched, its key position and its locked parent.
+    Next {
+        node: &'a BaseNode,
+        level: u32,
+        parent: Option<ReadGuard<'a>>,
+    },
+    Done(Option<V>),
+}
+
+/// Where the lookup of [RawTree::locate] ended.
+pub(crate) enum Spot<V> {
+    /// The last level node holds this value for the key.
+    Value(V),
+    /// The last level node has no value for the key.
+    NoValue,
+    /// The inner node has no child for the key.
+    NoChild,
+    /// The key leaves the compressed path of the node after this many bytes.
+    Mismatch(usize),
+}
+
+/// The nodes visited by [RawTree::locate].
+pub(crate) struct Located<'a, V> {
+    /// The ancestors of `node`, from the root, and the keys to their children.
+    pub(crate) path: Vec<(ReadGuard<'a>, u8)>,
+    /// The node where the lookup ended.
+    pub(crate) node: ReadGuard<'a>,
+    /// The key position that `node` branches on, where its prefix starts for a [Spot::Mismatch].
+    pub(crate) level: usize,
+    pub(crate) spot: Spot<V>,
+}
+
+/// The writes of [RawTree::commit] to one last level node.
+struct LeafWrites<'a, 'k, V> {
+    path: Vec<(ReadGuard<'a>, u8)>,
+    node: ReadGuard<'a>,
+    /// The first written key.
+    #[cfg_attr(not(feature = "order_statistics"), allow(dead_code))]
+    key: &'k [u8],
+    /// The number of values once the writes are applied.
+    count: usize,
+    /// The key bytes, their new values or `None` to remove them, and whether they are present.
+    writes: Vec<(u8, Option<V>, bool)>,
+}
+
+/// The values that [RawTree::commit] inserts below an inner node without a child for their keys.
+struct NewChildren<'a, 'k, V> {
+    path: Vec<(ReadGuard<'a>, u8)>,
+    node: ReadGuard<'a>,
+    level: usize,
+    values: Vec<(&'k [u8], V)>,
+    /// The children holding the values, once they are loaded.
+    loaded: Vec<(u8, NodePtr)>,
+}
+
+/// The changes of the subtree counts of [RawTree::commit].
+#[cfg(feature = "order_statistics")]
+struct CommitCounts<'k> {
+    /// The changes of the locked nodes from `top` down to the nodes that gain or lose values, by address.
+    deltas: HashMap<usize, isize>,
+    /// The address of the highest locked node, the ancestors above it are counted with [RawTree::count_keys].
+    top: usize,
+    /// A key below `top`.
+    key: &'k [u8],
+}
+
+impl<V> NewChildren<'_, '_, V> {
+    /// The number of children the values add to the node.
+    fn added(&self) -> usize {
+        let mut keys: Vec<u8> = self.values.iter().map(|(k, _)| k[self.level]).collect();
+        keys.dedup();
+        keys.len()
+    }
+}
+
+impl<T: RawKey, V: Copy + PartialEq, A: Allocator + Clone, const K_LEN: usize>
+    RawTree<T, V, A, K_LEN>
+{
+    pub fn new(allocator: A) -> Self {
+        Self::with_backoff(allocator, BackoffPolicy::default())
+    }
+
+    pub(crate) fn with_backoff(allocator: A, backoff: BackoffPolicy) -> Self {
+        let root = if K_LEN == 1 {
+            BaseNode::make_node::<Node256<V>>(&[], &allocator).map(|n| n as *const BaseNode)
+        } else {
+            BaseNode::make_node::<Node256>(&[], &allocator).map(|n| n as *const BaseNode)
+        };
+        RawTree {
+            root: root.expect("Can't allocate memory for root node!"),
+            allocator,
+            len: Counter::default(),
+            backoff,
+            #[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+            fan_out: FanOut::default(),
+            _pt_key: PhantomData,
+            _pt_val: PhantomData,
+        }
+    }
+
+    /// Builds a tree from `(key, value)` pairs in ascending key order, every node is created at its final size.
+    /// The value of a repeated key replaces the previous one.
+    ///
+    /// # Panics
+    /// Panics if the keys are not sorted.
+    pub(crate) fn from_sorted(
+        iter: impl IntoIterator<Item = (T, V)>,
+        allocator: A,
+        backoff: BackoffPolicy,
+    ) -> Result<Self, ArtError> {
+        let tree = Self::with_backoff(allocator, backoff);
+        let root = unsafe { &mut *(tree.root as *mut BaseNode) };
+        if K_LEN == 1 {
+            // the root is the only node
+            let mut last = None;
+            for (k, v) in iter {
+                let k = k.as_bytes()[0];
+                if last == Some(k) {
+                    root.change_value(k, v);
+                } else {
+                    assert!(last < Some(k), "the keys are not sorted");
+                    root.insert_value(k, v);
+                }
+                last = Some(k);
+            }
+            tree.note_fan_out(0, root.get_count());
+            root.set_keys(root.get_count());
+            tree.len.add(root.get_count() as isize);
+            return Ok(tree);
+        }
+
+        let mut loader = BulkLoader::new(&tree, 0);
+        for (k, v) in iter {
+            loader.push(k.as_bytes(), v)?;
+        }
+        let (children, count) = loader.finish()?;
+        tree.note_fan_out(0, children.len());
+        for (k, child) in children {
+            root.insert_value(k, child);
+        }
+        root.set_keys(count);
+        tree.len.add(count as isize);
+        Ok(tree)
+    }
+
+    /// Same as [RawTree::from_sorted], with the subtrees loaded by up to `threads` threads.
+    ///
+    /// The `items` are split at the first key byte in which the keys differ,
+    /// so that every thread loads whole subtrees below the node that branches on that byte.
+    pub(crate) fn from_sorted_par<I: Sync>(
+        items: &[I],
+        entry: impl Fn(&I) -> (T, V) + Sync,
+        threads: usize,
+        allocator: A,
+        backoff: BackoffPolicy,
+    ) -> Result<Self, ArtError>
+    where
+        V: Send + Sync,
+        A: Send + Sync,
+    {
+        let key_of = |i: usize| entry(&items[i]).0;
+        let level = match items.len() {
+            0 => K_LEN,
+            len => {
+                let (first, last) = (key_of(0), key_of(len - 1));
+                let common = first.as_bytes().iter().zip(last.as_bytes());
+                common.take_while(|(f, l)| f == l).count()
+            }
+        };
+        if K_LEN == 1 || level >= K_LEN - 1 || threads <= 1 {
+            return Self::from_sorted(items.iter().map(entry), allocator, backoff);
+        }
+
+        let mut splits = vec![0];
+        for t in 1..threads {
+            let byte = key_of(items.len() * t / threads).as_bytes()[level];
+            let split = items.partition_point(|i| entry(i).0.as_bytes()[level] < byte);
+            if split > *splits.last().unwrap() {
+                splits.push(split);
+                // the threads only check the order of their own keys
+                assert!(key_of(split - 1) < key_of(split), "the keys are not sorted");
+            }
+        }
+        splits.push(items.len());
+
+        let tree = Self::with_backoff(allocator, backoff);
+        let loaded: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = splits
+                .windows(2)
+                .map(|w| {
+                    let (tree, entry) = (&tree, &entry);
+                    s.spawn(move || -> Result<_, ArtError> {
+                        let mut loader = BulkLoader::new(tree, level);
+                        for item in &items[w[0]..w[1]] {
+                            let (k, v) = entry(item);
+                            loader.push(k.as_bytes(), v)?;
+                        }
+                        let (children, count) = loader.finish()?;
+                        let children: Vec<_> = children
+                            .into_iter()
+                            .map(|(k, child)| (k, child.as_ptr() as usize))
+                            .collect();
+                        Ok((children, count))
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        });
+
+        let mut children = Vec::new();
+        let mut count = 0;
+        let mut failed = None;
+        for result in loaded {
+            match result {
+                Ok((loaded, n)) => {
+                    let loaded = loaded.into_iter();
+                    children
+                        .extend(loaded.map(|(k, n)| (k, NodePtr::from_node(n as *const BaseNode))));
+                    count += n;
+                }
+                Err(e) => failed = Some(e),
+            }
+        }
+        if let Some(e) = failed {
+            for (_, child) in children {
+                unsafe { tree.drop_child(child, level) };
+            }
+            return Err(e);
+        }
+
+        let root = unsafe { &mut *(tree.root as *mut BaseNode) };
+        if level == 0 {
+            for (k, child) in children {
+                root.insert_value(k, child);
+            }
+        } else {
+            let key = key_of(0);
+            let node = make_inner(&tree, children, key.as_bytes(), 0, level)?;
+            root.insert_value(key.as_bytes()[0], node);
+        }
+        tree.note_fan_out(0, root.get_count());
+        root.set_keys(count);
+        tree.len.add(count as isize);
+        Ok(tree)
+    }
+
+    /// The allocator of the tree's nodes.
+    pub(crate) fn allocator(&self) -> &A {
+        &self.allocator
+    }
+
+    /// Notes that a node branching on `level` holds `count` slots, before other threads can see them.
+    #[inline]
+    pub(crate) fn note_fan_out(&self, _level: usize, _count: usize) {
+        #[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+        self.fan_out.raise(_level, _count);
+    }
+
+    /// A fresh backoff of the tree's policy, for the retry loop of an operation.
+    #[inline]
+    pub(crate) fn backoff(&self) -> Backoff {
+        Backoff::with_policy(self.backoff)
+    }
+
+    /// Adds `delta` to the subtree counts of the ancestors of `stop` on the path of `key`.
+    ///
+    /// The caller has write-locked `stop` and updated the counts of the nodes it changed below,
+    /// it keeps them locked until the ancestors are counted as well, so that the counts of a key's ancestors
+    /// follow the changes of the key in order. The ancestors are not locked, an ancestor that is replaced
+    /// meanwhile moves its count to its copy, see [BaseNode::try_add_keys], and the walk is restarted
+    /// from the root, skipping the levels that are counted already.
+    #[inline]
+    pub(crate) fn count_keys(&self, _key: &[u8], _stop: &BaseNode, _delta: isize) {
+        #[cfg(feature = "order_statistics")]
+        {
+            let backoff = self.backoff();
+            // the levels above this one are counted
+            let mut counted = 0;
+            while self
+                .count_keys_inner(_key, _stop, _delta, &mut counted)
+                .is_err()
+            {
+                backoff.wait();
+            }
+        }
+    }
+
+    #[cfg(feature = "order_statistics")]
+    fn count_keys_inner(
+        &self,
+        key: &[u8],
+        stop: &BaseNode,
+        delta: isize,
+        counted: &mut usize,
+    ) -> Result<(), ArtError> {
+        let mut next = self.root;
+        let mut parent: Option<ReadGuard> = None;
+        let mut level = 0;
+        while !std::ptr::eq(next, stop) {
+            let node = unsafe { &*next }.read_lock()?;
+            if let Some(p) = parent {
+                p.check_version()?;
+            }
+            level += node.as_ref().prefix_len();
+            // `stop` is on the path, only a node that was changed while it was read leads elsewhere
+            let Some(&node_key) = key.get(level).filter(|_| level < K_LEN - 1) else {
+                return Err(ArtError::VersionNotMatch);
+            };
+            if level >= *counted {
+                node.as_ref().try_add_keys(delta)?;
+                *counted = level + 1;
+            }
+
+            let child = node.as_ref().get_child(node_key);
+            node.check_version()?;
+            next = child.ok_or(ArtError::VersionNotMatch)?.as_ptr();
+            parent = Some(node);
+            level += 1;
+        }
+        Ok(())
+    }
+
+    /// Frees `node` and all of its descendants, `level` is the key position that `node` branches on.
+    ///
+    /// # Safety
+    /// The subtree must not be reachable by any other thread.
+    unsafe fn drop_subtree(&self, node: *const BaseNode, level: usize) {
+        let mut sub_nodes = vec![(node, level)];
 
         while let Some((node, level)) = sub_nodes.pop() {
+            if level == K_LEN - 1 {
+                unsafe {
+                    BaseNode::drop_node_of::<V, A>(node as *mut BaseNode, self.allocator.clone());
+                }
+                continue;
+            }
             let children = unsafe { &*node }.get_children(0, 255);
             for (_k, n) in children {
-                if level != (MAX_KEY_LEN - 1) {
-                    sub_nodes.push((n.as_ptr(), unsafe { &*n.as_ptr() }.prefix().len()));
-                }
+                let child_level = level + 1 + unsafe { &*n.as_ptr() }.prefix().len();
+                sub_nodes.push((n.as_ptr(), child_level));
             }
             unsafe {
                 BaseNode::drop_node(node as *mut BaseNode, self.allocator.clone());
             }
         }
     }
-}
 
-impl<T: RawKey, A: Allocator + Clone> RawTree<T, A> {
-    pub fn new(allocator: A) -> Self {
-        RawTree {
-            root: BaseNode::make_node::<Node256>(&[], &allocator)
-                .expect("Can't allocate memory for root node!") as *const Node256,
-            allocator,
-            _pt_key: PhantomData,
+    /// Calls `f` on every value in the tree.
+    pub(crate) fn for_each_value(&mut self, mut f: impl FnMut(V)) {
+        let mut sub_nodes = vec![(self.root, 0)];
+
+        while let Some((node, level)) = sub_nodes.pop() {
+            if level == K_LEN - 1 {
+                for (_k, v) in unsafe { &*node }.get_values::<V>(0, 255) {
+                    f(v);
+                }
+                continue;
+            }
+            let children = unsafe { &*node }.get_children(0, 255);
+            for (_k, n) in children {
+                let child_level = level + 1 + unsafe { &*n.as_ptr() }.prefix().len();
+                sub_nodes.push((n.as_ptr(), child_level));
+            }
+        }
+    }
+
+    /// The number of values in the tree, exact if there are no concurrent updates.
+    pub(crate) fn len(&self) -> usize {
+        self.len.sum()
+    }
+
+    /// The number of values in the tree, without summing up the per-thread counters.
+    pub(crate) fn approximate_len(&self) -> usize {
+        self.len.approximate()
+    }
+
+    /// Frees the node `child` and its descendants, `level` is the key position of `child` in its parent.
+    ///
+    /// # Safety
+    /// The subtree must not be reachable by any other thread.
+    pub(crate) unsafe fn drop_child(&self, child: NodePtr, level: usize) {
+        let n = child.as_ptr();
+        self.drop_subtree(n, level + 1 + (*n).prefix().len());
+    }
+
+    /// Creates a last level node holding a single value.
+    ///
+    /// Values without payload, i.e., the keys of a set, are only a bit in the mask of a `Node256`,
+    /// which is smaller than any other node type in that case.
+    fn make_leaf_node(&self, prefix: &[u8], key: u8, val: V) -> Result<*mut BaseNode, ArtError> {
+        let n = if std::mem::size_of::<V>() == 0 {
+            let n = BaseNode::make_node::<Node256<V>>(prefix, &self.allocator)?;
+            unsafe { &mut *n }.insert(key, val);
+            n as *mut BaseNode
+        } else {
+            let n = BaseNode::make_node::<Node4<V>>(prefix, &self.allocator)?;
+            unsafe { &mut *n }.insert(key, val);
+            n as *mut BaseNode
+        };
+        unsafe { &*n }.set_keys(1);
+        Ok(n)
+    }
+
+    /// Creates the path to the value of `k` below the node at `level`, `level` must not be the last level.
+    fn new_leaf(&self, k: &T, level: usize, val: V) -> Result<NodePtr, ArtError> {
+        debug_assert!(level < K_LEN - 1);
+        let key = k.as_bytes();
+
+        let prefix_start = std::cmp::max(level + 1, (K_LEN - 1).saturating_sub(MAX_PREFIX_LEN));
+        let leaf = self.make_leaf_node(&key[prefix_start..K_LEN - 1], key[K_LEN - 1], val)?;
+        self.chain_above(key, level, NodePtr::from_node(leaf), prefix_start - 1)
+    }
+
+    /// Links `child`, whose key position is `child_level`, to the node at `level` through the bytes of `key` between them.
+    /// On failure, `child` is freed.
+    ///
+    /// A node can store at most `MAX_PREFIX_LEN` prefix bytes, so longer compressed paths
+    /// are split into a chain of `Node4`s.
+    pub(crate) fn chain_above(
+        &self,
+        key: &[u8],
+        level: usize,
+        mut child: NodePtr,
+        mut child_level: usize,
+    ) -> Result<NodePtr, ArtError> {
+        while child_level > level {
+            let prefix_start = std::cmp::max(level + 1, child_level.saturating_sub(MAX_PREFIX_LEN));
+            let n4 = match BaseNode::make_node::<Node4>(
+                &key[prefix_start..child_level],
+                &self.allocator,
+            ) {
+                Ok(n) => n,
+                Err(e) => {
+                    unsafe { self.drop_child(child, child_level) };
+                    return Err(e);
+                }
+            };
+            unsafe { &mut *n4 }.insert(key[child_level], child);
+            unsafe { &*(n4 as *const BaseNode) }.set_keys(unsafe { &*n4 }.base().child_keys());
+            child = NodePtr::from_node(n4 as *mut BaseNode);
+            child_level = prefix_start - 1;
         }
+        Ok(child)
     }
 }
 
-impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
+impl<T: RawKey, V: Copy + PartialEq, A: Allocator + Clone + Send, const K_LEN: usize>
+    RawTree<T, V, A, K_LEN>
+{
     #[inline]
-    pub(crate) fn get(&self, key: &T, _guard: &Guard) -> Option<usize> {
-        'outer: loop {
-            let mut level = 0;
+    pub(crate) fn get(&self, key: &T, _guard: &Guard) -> Option<V> {
+        let backoff = self.backoff();
+        loop {
+            match self.get_inner(key) {
+                Ok(v) => return v,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
 
-            let mut node = if let Ok(v) = unsafe { &*self.root }.base().read_lock() {
-                v
-            } else {
-                continue;
+    #[inline]
+    pub(crate) fn try_get(
+        &self,
+        key: &T,
+        budget: &RetryBudget,
+        _guard: &Guard,
+    ) -> Result<Option<V>, ArtError> {
+        self.retry_within(budget, || self.get_inner(key))
+    }
+
+    #[inline]
+    fn get_inner(&self, key: &T) -> Result<Option<V>, ArtError> {
+        let mut level = 0;
+        let mut node = unsafe { &*self.root }.read_lock()?;
+
+        loop {
+            level = match Self::check_prefix(node.as_ref(), key.as_bytes(), level) {
+                Some(level) => level,
+                None => return Ok(None),
+            };
+
+            if key.len() <= level as usize {
+                return Ok(None);
+            }
+
+            let node_key = unsafe { *key.as_bytes().get_unchecked(level as usize) };
+
+            if level == (K_LEN - 1) as u32 {
+                // the last level, we can return the value
+                let value = node.as_ref().get_value::<V>(node_key);
+                node.check_version()?;
+                return Ok(value);
+            }
+
+            let child_node = node.as_ref().get_child(node_key);
+            node.check_version()?;
+
+            let Some(child_node) = child_node else {
+                return Ok(None);
             };
 
-            loop {
-                level = Self::check_prefix(node.as_ref(), key, level)?;
+            level += 1;
+
+            // the child may have been moved below a new node since it was read, its prefix would be off then
+            let child = unsafe { &*child_node.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            node = child;
+        }
+    }
+
+    /// The number of keys smaller than `key`, and whether `key` is present.
+    ///
+    /// It descends along `key` and adds up the subtree counts of the children left of the path,
+    /// exact if there are no concurrent updates.
+    #[cfg(feature = "order_statistics")]
+    pub(crate) fn rank(&self, key: &T, _guard: &Guard) -> (usize, bool) {
+        let backoff = self.backoff();
+        loop {
+            match self.rank_inner(key.as_bytes()) {
+                Ok(rank) => return rank,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    #[cfg(feature = "order_statistics")]
+    fn rank_inner(&self, key: &[u8]) -> Result<(usize, bool), ArtError> {
+        let mut node = unsafe { &*self.root }.read_lock()?;
+        let mut level = 0;
+        let mut less = 0;
 
-                if key.len() <= level as usize {
-                    return None;
+        loop {
+            let prefix = node.as_ref().prefix();
+            if level + prefix.len() >= K_LEN {
+                node.check_version()?;
+                return Err(ArtError::VersionNotMatch);
+            }
+            match prefix.cmp(&key[level..level + prefix.len()]) {
+                cmp::Ordering::Less => {
+                    let keys = node.as_ref().keys();
+                    node.check_version()?;
+                    return Ok((less + keys.ok_or(ArtError::VersionNotMatch)?, false));
+                }
+                cmp::Ordering::Greater => {
+                    node.check_version()?;
+                    return Ok((less, false));
                 }
+                cmp::Ordering::Equal => level += prefix.len(),
+            }
+            let node_key = key[level];
+
+            if level == K_LEN - 1 {
+                let below = match node_key {
+                    0 => 0,
+                    k => node
+                        .as_ref()
+                        .get_values::<V>(0, k - 1)
+                        .filter(|(v, _)| *v < k)
+                        .count(),
+                };
+                let found = node.as_ref().get_value::<V>(node_key).is_some();
+                node.check_version()?;
+                return Ok((less + below, found));
+            }
 
-                let child_node = node
+            // a `Node16` may return more children than asked for if `end` is absent
+            let below: Vec<_> = match node_key {
+                0 => Vec::new(),
+                k => node
                     .as_ref()
-                    .get_child(unsafe { *key.as_bytes().get_unchecked(level as usize) });
-                if node.check_version().is_err() {
-                    continue 'outer;
-                }
+                    .get_children(0, k - 1)
+                    .filter(|(c, _)| *c < k)
+                    .collect(),
+            };
+            let child = node.as_ref().get_child(node_key);
+            node.check_version()?;
+            // the children are only read once the version confirms them, a moved count means the tree changed
+            for (_k, n) in below {
+                less += unsafe { &*n.as_ptr() }
+                    .keys()
+                    .ok_or(ArtError::VersionNotMatch)?;
+            }
+            let Some(child) = child else {
+                return Ok((less, false));
+            };
+            level += 1;
 
-                let child_node = child_node?;
+            let child = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            node = child;
+        }
+    }
+
+    /// The `i`-th smallest key, counting from zero, and its value.
+    ///
+    /// It descends to the child whose subtree count covers `i`, exact if there are no concurrent updates.
+    #[cfg(feature = "order_statistics")]
+    pub(crate) fn select(&self, i: usize, _guard: &Guard) -> Option<(KeyTracker<K_LEN>, V)> {
+        let backoff = self.backoff();
+        loop {
+            match self.select_inner(i) {
+                Ok(selected) => return selected,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    #[cfg(feature = "order_statistics")]
+    fn select_inner(&self, mut i: usize) -> Result<Option<(KeyTracker<K_LEN>, V)>, ArtError> {
+        let mut node = unsafe { &*self.root }.read_lock()?;
+        let mut key = KeyTracker::<K_LEN>::default();
+        let keys = node.as_ref().keys();
+        node.check_version()?;
+        if i >= keys.ok_or(ArtError::VersionNotMatch)? {
+            return Ok(None);
+        }
+
+        loop {
+            let prefix = node.as_ref().prefix();
+            if key.len() + prefix.len() >= K_LEN {
+                node.check_version()?;
+                return Err(ArtError::VersionNotMatch);
+            }
+            for k in prefix {
+                key.push(*k);
+            }
+
+            if key.len() == K_LEN - 1 {
+                let value = node.as_ref().get_values::<V>(0, 255).nth(i);
+                node.check_version()?;
+                // the counts above did not match this node, a concurrent update changed it
+                let (k, value) = value.ok_or(ArtError::VersionNotMatch)?;
+                key.push(k);
+                return Ok(Some((key, value)));
+            }
 
-                if level == (MAX_KEY_LEN - 1) as u32 {
-                    // the last level, we can return the value
-                    let tid = child_node.as_tid();
-                    return Some(tid);
+            let children: Vec<_> = node.as_ref().get_children(0, 255).collect();
+            node.check_version()?;
+            let mut next = None;
+            for (k, n) in children {
+                let keys = unsafe { &*n.as_ptr() }
+                    .keys()
+                    .ok_or(ArtError::VersionNotMatch)?;
+                if i < keys {
+                    next = Some((k, n));
+                    break;
                 }
+                i -= keys;
+            }
+            let (k, child) = next.ok_or(ArtError::VersionNotMatch)?;
+            key.push(k);
 
-                level += 1;
+            let child = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            node = child;
+        }
+    }
 
-                node = if let Ok(n) = unsafe { &*child_node.as_ptr() }.read_lock() {
-                    n
-                } else {
-                    continue 'outer;
-                };
+    /// The number of keys within `range`, from the ranks of its bounds.
+    #[cfg(feature = "order_statistics")]
+    pub(crate) fn count_range(&self, range: &impl RangeBounds<T>, guard: &Guard) -> usize {
+        let start = match range.start_bound() {
+            Bound::Included(k) => self.rank(k, guard).0,
+            Bound::Excluded(k) => {
+                let (less, found) = self.rank(k, guard);
+                less + found as usize
+            }
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(k) => {
+                let (less, found) = self.rank(k, guard);
+                less + found as usize
+            }
+            Bound::Excluded(k) => self.rank(k, guard).0,
+            // the root is never replaced, its count is never moved
+            Bound::Unbounded => unsafe { &*self.root }.keys().unwrap_or(0),
+        };
+        end.saturating_sub(start)
+    }
+
+    /// Looks up the `keys`, sets `values[i]` to the value of `keys[i]`.
+    ///
+    /// The lookups of a group of [GET_MANY_GROUP] keys step one level down per round, each prefetches its next node,
+    /// which it reads in the next round, after the other lookups had their turn. A lookup whose key shares the bytes
+    /// branched on so far with the previous key takes over its step, so sorted keys walk their common path once.
+    pub(crate) fn get_many(&self, keys: &[T], values: &mut [Option<V>], _guard: &Guard) {
+        debug_assert_eq!(keys.len(), values.len());
+        let root = Lookup::Next {
+            node: unsafe { &*self.root },
+            level: 0,
+            parent: None,
+        };
+        let backoff = self.backoff();
+        for (keys, values) in keys
+            .chunks(GET_MANY_GROUP)
+            .zip(values.chunks_mut(GET_MANY_GROUP))
+        {
+            let mut lookups: [Lookup<V>; GET_MANY_GROUP] = std::array::from_fn(|_| root.clone());
+            // the number of leading bytes a key shares with the previous one
+            let mut common = [0; GET_MANY_GROUP];
+            for (i, w) in keys.windows(2).enumerate() {
+                let (a, b) = (w[0].as_bytes(), w[1].as_bytes());
+                common[i + 1] = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+            }
+
+            let mut pending = keys.len();
+            while pending > 0 {
+                // the node and level the previous lookup stepped from in this round
+                let mut stepped = None;
+                for i in 0..keys.len() {
+                    let Lookup::Next {
+                        node,
+                        level,
+                        parent,
+                    } = &lookups[i]
+                    else {
+                        stepped = None;
+                        continue;
+                    };
+                    let from = (*node as *const BaseNode, *level);
+                    let shared = stepped == Some(from)
+                        && match &lookups[i - 1] {
+                            Lookup::Next { level, .. } => common[i] >= *level as usize,
+                            Lookup::Done(_) => keys[i] == keys[i - 1],
+                        };
+                    let next = if shared {
+                        lookups[i - 1].clone()
+                    } else {
+                        Self::get_step(&keys[i], node, *level, parent.as_ref()).unwrap_or_else(
+                            |_| {
+                                backoff.wait();
+                                root.clone()
+                            },
+                        )
+                    };
+                    stepped = Some(from);
+                    if let Lookup::Done(v) = &next {
+                        values[i] = *v;
+                        pending -= 1;
+                    }
+                    lookups[i] = next;
+                }
             }
         }
     }
 
+    /// Reads the node of a lookup of [RawTree::get_many] and prefetches the next one.
+    #[inline]
+    fn get_step<'a>(
+        key: &T,
+        node: &'a BaseNode,
+        level: u32,
+        parent: Option<&ReadGuard<'a>>,
+    ) -> Result<Lookup<'a, V>, ArtError> {
+        let node = node.read_lock()?;
+        if let Some(parent) = parent {
+            // the node may have been moved below a new node since it was read, see `get_inner`
+            parent.check_version()?;
+        }
+        let level = match Self::check_prefix(node.as_ref(), key.as_bytes(), level) {
+            Some(level) if (level as usize) < key.len() => level,
+            _ => return Ok(Lookup::Done(None)),
+        };
+
+        let node_key = key.as_bytes()[level as usize];
+        if level == (K_LEN - 1) as u32 {
+            let value = node.as_ref().get_value::<V>(node_key);
+            node.check_version()?;
+            return Ok(Lookup::Done(value));
+        }
+
+        let child = node.as_ref().get_child(node_key);
+        node.check_version()?;
+        let Some(child) = child else {
+            return Ok(Lookup::Done(None));
+        };
+        prefetch(child.as_ptr());
+        Ok(Lookup::Next {
+            node: unsafe { &*child.as_ptr() },
+            level: level + 1,
+            parent: Some(node),
+        })
+    }
+
+    /// Runs `op` again on every restart until the `budget` runs out, then returns the restart error.
     #[inline]
-    fn insert_inner<F>(
+    fn retry_within<R>(
         &self,
-        k: &T,
-        tid_func: &mut F,
-        guard: &Guard,
-    ) -> Result<Option<usize>, ArtError>
+        budget: &RetryBudget,
+        mut op: impl FnMut() -> Result<R, ArtError>,
+    ) -> Result<R, ArtError> {
+        let backoff = self.backoff();
+        let mut restarts = 0;
+        loop {
+            match op() {
+                Err(ArtError::Locked | ArtError::VersionNotMatch)
+                    if budget.allows(restarts + 1) =>
+                {
+                    restarts += 1;
+                    backoff.wait();
+                }
+                r => return r,
+            }
+        }
+    }
+
+    #[inline]
+    fn insert_inner<F>(&self, k: &T, tid_func: &mut F, guard: &Guard) -> Result<Option<V>, ArtError>
     where
-        F: FnMut(Option<usize>) -> usize,
+        F: FnMut(Option<V>) -> V,
     {
         let mut parent_node = None;
-        let mut next_node = self.root as *const BaseNode;
+        let mut next_node = self.root;
         let mut parent_key: u8;
         let mut node_key: u8 = 0;
         let mut level = 0;
@@ -136,6 +937,45 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
                     level = next_level;
                     node_key = k.as_bytes()[level as usize];
 
+                    if level == (K_LEN - 1) as u32 {
+                        // At this point, the level must point to the last u8 of the key,
+                        // we are either inserting a new value or updating an existing one.
+                        let old = node.as_ref().get_value::<V>(node_key);
+                        node.check_version()?;
+
+                        let old = match old {
+                            Some(old) => old,
+                            None => {
+                                self.note_fan_out(K_LEN - 1, node.as_ref().get_count() + 1);
+                                BaseNode::insert_and_unlock(
+                                    node,
+                                    (parent_key, parent_node),
+                                    (node_key, tid_func(None)),
+                                    &self.allocator,
+                                    guard,
+                                    |top| self.count_keys(k.as_bytes(), top, 1),
+                                )?;
+                                self.len.add(1);
+                                return Ok(None);
+                            }
+                        };
+
+                        if let Some(p) = parent_node {
+                            p.unlock()?;
+                        }
+
+                        let new = tid_func(Some(old));
+                        if old == new {
+                            node.check_version()?;
+                            return Ok(Some(old));
+                        }
+
+                        let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+
+                        let old = write_n.as_mut().change_value(node_key, new);
+                        return Ok(Some(old));
+                    }
+
                     let next_node_tmp = node.as_ref().get_child(node_key);
 
                     node.check_version()?;
@@ -143,42 +983,22 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
                     let next_node_tmp = if let Some(n) = next_node_tmp {
                         n
                     } else {
-                        let new_leaf = {
-                            if level == (MAX_KEY_LEN - 1) as u32 {
-                                // last key, just insert the tid
-                                NodePtr::from_tid(tid_func(None))
-                            } else {
-                                let new_prefix = k.as_bytes();
-                                let n4 = BaseNode::make_node::<Node4>(
-                                    &new_prefix[..k.len() - 1],
-                                    &self.allocator,
-                                )?;
-                                unsafe { &mut *n4 }.insert(
-                                    k.as_bytes()[k.len() - 1],
-                                    NodePtr::from_tid(tid_func(None)),
-                                );
-                                NodePtr::from_node(n4 as *mut BaseNode)
-                            }
-                        };
+                        let new_leaf = self.new_leaf(k, level as usize, tid_func(None))?;
 
+                        self.note_fan_out(level as usize, node.as_ref().get_count() + 1);
                         if let Err(e) = BaseNode::insert_and_unlock(
                             node,
                             (parent_key, parent_node),
                             (node_key, new_leaf),
                             &self.allocator,
                             guard,
+                            |top| self.count_keys(k.as_bytes(), top, 1),
                         ) {
-                            if level != (MAX_KEY_LEN - 1) as u32 {
-                                unsafe {
-                                    BaseNode::drop_node(
-                                        new_leaf.as_ptr() as *mut BaseNode,
-                                        self.allocator.clone(),
-                                    );
-                                }
-                            }
+                            unsafe { self.drop_child(new_leaf, level as usize) };
                             return Err(e);
                         }
 
+                        self.len.add(1);
                         return Ok(None);
                     };
 
@@ -186,68 +1006,64 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
                         p.unlock()?;
                     }
 
-                    if level == (MAX_KEY_LEN - 1) as u32 {
-                        // At this point, the level must point to the last u8 of the key,
-                        // meaning that we are updating an existing value.
-
-                        let old = node.as_ref().get_child(node_key).unwrap().as_tid();
-                        let new = tid_func(Some(old));
-                        if old == new {
-                            node.check_version()?;
-                            return Ok(Some(old));
-                        }
-
-                        let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
-
-                        let old = write_n.as_mut().change(node_key, NodePtr::from_tid(new));
-                        return Ok(Some(old.as_tid()));
-                    }
-                    next_node = next_node_tmp.as_ptr();
-                    level += 1;
-                }
+                    next_node = next_node_tmp.as_ptr();
+                    level += 1;
+                }
 
                 Some(no_match_key) => {
                     let mut write_p = parent_node.unwrap().upgrade().map_err(|(_n, v)| v)?;
-                    let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+                    let write_n = node.upgrade().map_err(|(_n, v)| v)?;
 
                     // 1) Create new node which will be parent of node, Set common prefix, level to this node
-                    // let prefix_len = write_n.as_ref().prefix().len();
+                    let matched = (next_level - level) as usize;
                     let new_middle_node = BaseNode::make_node::<Node4>(
-                        write_n.as_ref().prefix()[0..next_level as usize].as_ref(),
+                        write_n.as_ref().prefix()[..matched].as_ref(),
                         &self.allocator,
                     )?;
 
                     // 2)  add node and (tid, *k) as children
-                    if next_level == (MAX_KEY_LEN - 1) as u32 {
-                        // this is the last key, just insert to node
-                        unsafe { &mut *new_middle_node }.insert(
-                            k.as_bytes()[next_level as usize],
-                            NodePtr::from_tid(tid_func(None)),
-                        );
-                    } else {
-                        // otherwise create a new node
-                        let single_new_node = BaseNode::make_node::<Node4>(
-                            &k.as_bytes()[..k.len() - 1],
-                            &self.allocator,
-                        )?;
-
-                        unsafe { &mut *single_new_node }
-                            .insert(k.as_bytes()[k.len() - 1], NodePtr::from_tid(tid_func(None)));
-                        unsafe { &mut *new_middle_node }.insert(
-                            k.as_bytes()[next_level as usize],
-                            NodePtr::from_node(single_new_node as *const BaseNode),
-                        );
-                    }
-
+                    let new_leaf = match self.new_leaf(k, next_level as usize, tid_func(None)) {
+                        Ok(n) => n,
+                        Err(e) => {
+                            unsafe {
+                                BaseNode::drop_node(
+                                    new_middle_node as *mut BaseNode,
+                                    self.allocator.clone(),
+                                );
+                            }
+                            return Err(e);
+                        }
+                    };
                     unsafe { &mut *new_middle_node }
-                        .insert(no_match_key, NodePtr::from_node(write_n.as_mut()));
+                        .insert(k.as_bytes()[next_level as usize], new_leaf);
+
+                    // the node now hangs below the middle node, keep only the part of its prefix after `no_match_key`
+                    let n = match self.cut_prefix(write_n, level as usize, matched + 1, guard) {
+                        Ok(n) => n,
+                        Err(e) => {
+                            unsafe {
+                                self.drop_subtree(
+                                    new_middle_node as *const BaseNode,
+                                    next_level as usize,
+                                )
+                            };
+                            return Err(e);
+                        }
+                    };
+                    unsafe { &mut *new_middle_node }.insert(no_match_key, n);
+                    let middle = unsafe { &*(new_middle_node as *const BaseNode) };
+                    middle.set_keys(middle.child_keys());
+                    self.note_fan_out(next_level as usize, 2);
 
                     // 3) update parentNode to point to the new node, unlock
                     write_p.as_mut().change(
                         parent_key,
                         NodePtr::from_node(new_middle_node as *mut BaseNode),
                     );
+                    write_p.as_ref().add_keys(1);
+                    self.count_keys(k.as_bytes(), write_p.as_ref(), 1);
 
+                    self.len.add(1);
                     return Ok(None);
                 }
             }
@@ -256,19 +1072,14 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
     }
 
     #[inline]
-    pub(crate) fn insert(
-        &self,
-        k: T,
-        tid: usize,
-        guard: &Guard,
-    ) -> Result<Option<usize>, OOMError> {
-        let backoff = Backoff::new();
+    pub(crate) fn insert(&self, k: T, tid: V, guard: &Guard) -> Result<Option<V>, OOMError> {
+        let backoff = self.backoff();
         loop {
             match self.insert_inner(&k, &mut |_| tid, guard) {
                 Ok(v) => return Ok(v),
                 Err(e) => match e {
                     ArtError::Locked | ArtError::VersionNotMatch => {
-                        backoff.spin();
+                        backoff.wait();
                         continue;
                     }
                     ArtError::Oom => return Err(OOMError::new()),
@@ -283,17 +1094,17 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
         k: T,
         insert_func: &mut F,
         guard: &Guard,
-    ) -> Result<Option<usize>, OOMError>
+    ) -> Result<Option<V>, OOMError>
     where
-        F: FnMut(Option<usize>) -> usize,
+        F: FnMut(Option<V>) -> V,
     {
-        let backoff = Backoff::new();
+        let backoff = self.backoff();
         loop {
             match self.insert_inner(&k, insert_func, guard) {
                 Ok(v) => return Ok(v),
                 Err(e) => match e {
                     ArtError::Locked | ArtError::VersionNotMatch => {
-                        backoff.spin();
+                        backoff.wait();
                         continue;
                     }
                     ArtError::Oom => return Err(OOMError::new()),
@@ -302,72 +1113,97 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
         }
     }
 
-    fn check_prefix(node: &BaseNode, key: &T, mut level: u32) -> Option<u32> {
-        let node_prefix = node.prefix();
-        let key_prefix = key.as_bytes();
-
-        // let found = node_prefix
-        //     .iter()
-        //     .zip(key_prefix)
-        //     .skip(level as usize)
-        //     .find(|(n, k)| n != k);
-
-        // debug_assert!(key_prefix.len() >= node_prefix.len());
+    /// Same as [RawTree::compute_or_insert], but gives up once the `budget` runs out.
+    #[inline]
+    pub(crate) fn try_compute_or_insert<F>(
+        &self,
+        k: T,
+        insert_func: &mut F,
+        budget: &RetryBudget,
+        guard: &Guard,
+    ) -> Result<Option<V>, ArtError>
+    where
+        F: FnMut(Option<V>) -> V,
+    {
+        self.retry_within(budget, || self.insert_inner(&k, insert_func, guard))
+    }
 
-        // if found.is_some() {
-        //     return None;
-        // } else {
-        //     Some(node_prefix.len() as u32)
-        // }
+    /// Matches the compressed path of `node` against `key` from `level`, returns the level after the path.
+    /// Only the bytes present in `key` are compared, so `key` may also be a prefix of the full key.
+    fn check_prefix(node: &BaseNode, key_prefix: &[u8], mut level: u32) -> Option<u32> {
+        let node_prefix = node.prefix();
 
-        for (n, k) in node_prefix.iter().zip(key_prefix).skip(level as usize) {
+        for (n, k) in node_prefix
+            .iter()
+            .zip(key_prefix.iter().skip(level as usize))
+        {
             if n != k {
                 return None;
             }
             level += 1;
         }
-        debug_assert!(level == node_prefix.len() as u32);
         Some(level)
     }
 
     #[inline]
     fn check_prefix_not_match(&self, n: &BaseNode, key: &T, level: &mut u32) -> Option<u8> {
-        let n_prefix = n.prefix();
-        if !n_prefix.is_empty() {
-            let p_iter = n_prefix.iter().skip(*level as usize);
-            for (i, v) in p_iter.enumerate() {
-                if *v != key.as_bytes()[*level as usize] {
-                    let no_matching_key = *v;
-
-                    let mut prefix = Prefix::default();
-                    for (j, v) in prefix.iter_mut().enumerate().take(n_prefix.len() - i - 1) {
-                        *v = n_prefix[j + 1 + i];
-                    }
-
-                    return Some(no_matching_key);
-                }
-                *level += 1;
+        for v in n.prefix().iter() {
+            if *v != key.as_bytes()[*level as usize] {
+                return Some(*v);
             }
+            *level += 1;
         }
 
         None
     }
 
+    /// Scans the keys within `range` in ascending order, `fill` writes a key and its value to a result slot.
+    /// Returns the number of the keys scanned, at most `result.len()`.
+    #[inline]
+    pub(crate) fn range<R>(
+        &self,
+        range: &impl RangeBounds<T>,
+        result: &mut [R],
+        fill: &mut impl FnMut(&mut R, &[u8], V),
+        guard: &Guard,
+    ) -> usize {
+        self.scan(range, result, fill, false, guard)
+    }
+
+    /// Same as [RawTree::range], but scans the keys in descending order, i.e., it finds the largest keys in the range.
     #[inline]
-    pub(crate) fn range(
+    pub(crate) fn range_rev<R>(
+        &self,
+        range: &impl RangeBounds<T>,
+        result: &mut [R],
+        fill: &mut impl FnMut(&mut R, &[u8], V),
+        guard: &Guard,
+    ) -> usize {
+        self.scan(range, result, fill, true, guard)
+    }
+
+    fn scan<R>(
         &self,
-        start: &T,
-        end: &T,
-        result: &mut [(usize, usize)],
+        range: &impl RangeBounds<T>,
+        result: &mut [R],
+        fill: &mut impl FnMut(&mut R, &[u8], V),
+        reverse: bool,
         _guard: &Guard,
     ) -> usize {
-        let mut range_scan = RangeScan::new(start, end, result, self.root as *const BaseNode);
+        let mut range_scan = RangeScan::<_, _, _, _, K_LEN>::new(
+            range.start_bound(),
+            range.end_bound(),
+            result,
+            fill,
+            self.root,
+            reverse,
+        );
 
         if !range_scan.is_valid_key_pair() {
             return 0;
         }
 
-        let backoff = Backoff::new();
+        let backoff = self.backoff();
         loop {
             let scanned = range_scan.scan();
             match scanned {
@@ -375,29 +1211,185 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
                     return n;
                 }
                 Err(_) => {
-                    backoff.spin();
+                    backoff.wait();
                 }
             }
         }
     }
 
+    /// Finds the smallest key within `bound..`, or the largest key within `..bound` if `reverse`;
+    /// an unbounded `bound` finds the first (last) key of the tree.
+    pub(crate) fn seek(
+        &self,
+        bound: Bound<&T>,
+        reverse: bool,
+        _guard: &Guard,
+    ) -> Option<(KeyTracker<K_LEN>, V)> {
+        let bound = match bound {
+            Bound::Included(k) => Some((k.as_bytes(), true)),
+            Bound::Excluded(k) => Some((k.as_bytes(), false)),
+            Bound::Unbounded => None,
+        };
+        debug_assert!(bound.is_none_or(|(k, _)| k.len() == K_LEN));
+
+        let backoff = self.backoff();
+        loop {
+            let found = unsafe { &*self.root }
+                .read_lock()
+                .and_then(|root| Self::seek_node(&root, bound, reverse, KeyTracker::default()));
+            match found {
+                Ok(found) => return found,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    /// Seeks in the subtree of `node`, `key` is the key up to the node's prefix.
+    /// `bound` is the (key, inclusive) bound if `key` is a prefix of it, otherwise every key of the subtree
+    /// is on the right side of the bound and the first (last) key is taken.
+    fn seek_node(
+        node: &ReadGuard,
+        mut bound: Option<(&[u8], bool)>,
+        reverse: bool,
+        mut key: KeyTracker<K_LEN>,
+    ) -> Result<Option<(KeyTracker<K_LEN>, V)>, ArtError> {
+        for p in node.as_ref().prefix() {
+            if let Some((b, _)) = bound {
+                match (p.cmp(&b[key.len()]), reverse) {
+                    (cmp::Ordering::Equal, _) => {}
+                    (cmp::Ordering::Greater, false) | (cmp::Ordering::Less, true) => bound = None,
+                    _ => {
+                        // the whole subtree is on the wrong side of the bound
+                        node.check_version()?;
+                        return Ok(None);
+                    }
+                }
+            }
+            key.push(*p);
+        }
+
+        let level = key.len();
+        let (start, end) = match bound {
+            Some((b, _)) if reverse => (0, b[level]),
+            Some((b, _)) => (b[level], 255),
+            None => (0, 255),
+        };
+
+        if level == K_LEN - 1 {
+            let values = node.as_ref().get_values::<V>(start, end).directed(reverse);
+            for (k, v) in values.filter(|(k, _)| (start..=end).contains(k)) {
+                if matches!(bound, Some((b, false)) if b[level] == k) {
+                    continue;
+                }
+                node.check_version()?;
+                key.push(k);
+                return Ok(Some((key, v)));
+            }
+            node.check_version()?;
+            return Ok(None);
+        }
+
+        let children = node.as_ref().get_children(start, end).directed(reverse);
+        for (k, child) in children.filter(|(k, _)| (start..=end).contains(k)) {
+            node.check_version()?;
+            let child = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+
+            let mut child_key = key.clone();
+            child_key.push(k);
+            let child_bound = bound.filter(|(b, _)| b[level] == k);
+            if let Some(found) = Self::seek_node(&child, child_bound, reverse, child_key)? {
+                return Ok(Some(found));
+            }
+        }
+        node.check_version()?;
+        Ok(None)
+    }
+
+    /// Counts the keys starting with `prefix`, at most `K_LEN` bytes.
+    pub(crate) fn count_prefix(&self, prefix: &[u8], _guard: &Guard) -> usize {
+        debug_assert!(prefix.len() <= K_LEN);
+        let backoff = self.backoff();
+        loop {
+            match self.count_prefix_inner(prefix) {
+                Ok(n) => return n,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    fn count_prefix_inner(&self, prefix: &[u8]) -> Result<usize, ArtError> {
+        let mut node = unsafe { &*self.root }.read_lock()?;
+        let mut level = 0;
+        loop {
+            // the level the node branches on
+            let depth = level + node.as_ref().prefix_len();
+            let matched = Self::check_prefix(node.as_ref(), prefix, level as u32);
+            node.check_version()?;
+
+            if matched.is_none() {
+                return Ok(0);
+            }
+            if depth >= prefix.len() {
+                // every key in the subtree starts with the prefix
+                return Self::count_node(&node, depth);
+            }
+
+            let k = prefix[depth];
+            if depth == K_LEN - 1 {
+                let v = node.as_ref().get_value::<V>(k);
+                node.check_version()?;
+                return Ok(v.is_some() as usize);
+            }
+            let child = node.as_ref().get_child(k);
+            node.check_version()?;
+            let Some(child) = child else {
+                return Ok(0);
+            };
+            let child = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            node = child;
+            level = depth + 1;
+        }
+    }
+
+    /// Counts the values in the subtree of `node`, `depth` is the level the node branches on.
+    fn count_node(node: &ReadGuard, depth: usize) -> Result<usize, ArtError> {
+        if depth == K_LEN - 1 {
+            let cnt = node.as_ref().get_values::<V>(0, 255).count();
+            node.check_version()?;
+            return Ok(cnt);
+        }
+
+        let mut cnt = 0;
+        for (_, child) in node.as_ref().get_children(0, 255) {
+            node.check_version()?;
+            let child = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            cnt += Self::count_node(&child, depth + 1 + child.as_ref().prefix_len())?;
+        }
+        node.check_version()?;
+        Ok(cnt)
+    }
+
     #[inline]
     fn compute_if_present_inner<F>(
         &self,
         k: &T,
         remapping_function: &mut F,
         guard: &Guard,
-    ) -> Result<Option<(usize, Option<usize>)>, ArtError>
+    ) -> Result<Option<(V, Option<V>)>, ArtError>
     where
-        F: FnMut(usize) -> Option<usize>,
+        F: FnMut(V) -> Option<V>,
     {
-        let mut parent: Option<(ReadGuard, u8)> = None;
+        // the ancestors of the node and the keys to their children, a removal may need to modify them
+        let mut path: Vec<(ReadGuard, u8)> = Vec::new();
         let mut node_key: u8;
         let mut level = 0;
-        let mut node = unsafe { &*self.root }.base().read_lock()?;
+        let mut node = unsafe { &*self.root }.read_lock()?;
 
         loop {
-            level = if let Some(v) = Self::check_prefix(node.as_ref(), k, level) {
+            level = if let Some(v) = Self::check_prefix(node.as_ref(), k.as_bytes(), level) {
                 v
             } else {
                 return Ok(None);
@@ -405,16 +1397,14 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
 
             node_key = k.as_bytes()[level as usize];
 
-            let child_node = node.as_ref().get_child(node_key);
-            node.check_version()?;
+            if level == (K_LEN - 1) as u32 {
+                let tid = node.as_ref().get_value::<V>(node_key);
+                node.check_version()?;
 
-            let child_node = match child_node {
-                Some(n) => n,
-                None => return Ok(None),
-            };
-
-            if level == (MAX_KEY_LEN - 1) as u32 {
-                let tid = child_node.as_tid();
+                let tid = match tid {
+                    Some(v) => v,
+                    None => return Ok(None),
+                };
                 let new_v = remapping_function(tid);
 
                 match new_v {
@@ -424,79 +1414,1182 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
                             return Ok(Some((tid, Some(tid))));
                         }
                         let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
-                        let old = write_n
-                            .as_mut()
-                            .change(k.as_bytes()[level as usize], NodePtr::from_tid(new_v));
+                        let old = write_n.as_mut().change_value(node_key, new_v);
 
-                        debug_assert_eq!(tid, old.as_tid());
+                        debug_assert!(tid == old);
 
-                        return Ok(Some((old.as_tid(), Some(new_v))));
+                        return Ok(Some((old, Some(new_v))));
                     }
                     None => {
                         // new value is none, we need to delete this entry
-                        debug_assert!(parent.is_some()); // reaching leaf means we must have parent, bcs root can't be leaf
-                        if node.as_ref().get_count() == 1 {
-                            let (parent_node, parent_key) = parent.unwrap();
-                            let mut write_p = parent_node.upgrade().map_err(|(_n, v)| v)?;
+                        // the root is the leaf node only if `K_LEN` is 1, it is never removed
+                        if node.as_ref().get_count() == 1 && !path.is_empty() {
+                            self.unlink_last_value(node, path, k.as_bytes(), guard)?;
+                        } else {
+                            BaseNode::remove_and_unlock::<V, A>(
+                                node,
+                                path.pop().map_or((0, None), |(n, k)| (k, Some(n))),
+                                node_key,
+                                &self.allocator,
+                                guard,
+                                |top| self.count_keys(k.as_bytes(), top, -1),
+                            )?;
+                        }
+                        self.len.add(-1);
+                        return Ok(Some((tid, None)));
+                    }
+                }
+            }
+
+            let child_node = node.as_ref().get_child(node_key);
+            node.check_version()?;
+
+            let child_node = match child_node {
+                Some(n) => n,
+                None => return Ok(None),
+            };
 
-                            let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+            level += 1;
+            let child = unsafe { &*child_node.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            path.push((node, node_key));
+            node = child;
+        }
+    }
 
-                            write_p.as_mut().remove(parent_key);
+    /// Removes the last level `node` holding a single value, the value of `key`,
+    /// together with its ancestors that only lead to it.
+    /// `path` holds the ancestors of `node`, from the root, and the keys to their children.
+    ///
+    /// If the remaining ancestor is left with one child, it is merged into the prefix of that child,
+    /// unless the merged prefix is too long to be stored in a node.
+    fn unlink_last_value<'a>(
+        &self,
+        node: ReadGuard<'a>,
+        mut path: Vec<(ReadGuard<'a>, u8)>,
+        key: &[u8],
+        guard: &Guard,
+    ) -> Result<(), ArtError> {
+        let mut unlinked = vec![node];
+        while path.len() > 1 && path.last().unwrap().0.as_ref().get_count() == 1 {
+            unlinked.push(path.pop().unwrap().0);
+        }
+        let (parent, parent_key) = path.pop().unwrap();
 
-                            write_n.mark_obsolete();
-                            let allocator = self.allocator.clone();
-                            guard.defer(move || unsafe {
-                                BaseNode::drop_node(write_n.as_mut(), allocator);
-                                std::mem::forget(write_n);
-                            });
-                        } else {
-                            let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+        let write_unlinked = match path.pop() {
+            Some((grandparent, grandparent_key)) if parent.as_ref().get_count() == 2 => {
+                let sibling = parent
+                    .as_ref()
+                    .get_children(0, 255)
+                    .find(|(k, _)| *k != parent_key);
+                parent.check_version()?;
+                let (sibling_key, sibling) = sibling.ok_or(ArtError::VersionNotMatch)?;
+                let sibling = unsafe { &*sibling.as_ptr() }.read_lock()?;
+                parent.check_version()?;
+
+                if Self::can_merge(&parent, &sibling) {
+                    let mut write_gp = grandparent.upgrade().map_err(|(_n, v)| v)?;
+                    let write_p = parent.upgrade().map_err(|(_n, v)| v)?;
+                    let write_unlinked = Self::upgrade_all(unlinked)?;
+                    let mut write_s = sibling.upgrade().map_err(|(_n, v)| v)?;
+
+                    self.merge_into_child(
+                        &mut write_gp,
+                        grandparent_key,
+                        write_p,
+                        (sibling_key, &mut write_s),
+                        guard,
+                    );
+                    write_gp.as_ref().add_keys(-1);
+                    self.count_keys(key, write_gp.as_ref(), -1);
+                    write_unlinked
+                } else {
+                    let write_unlinked = Self::upgrade_all(unlinked)?;
+                    BaseNode::remove_and_unlock::<NodePtr, A>(
+                        parent,
+                        (grandparent_key, Some(grandparent)),
+                        parent_key,
+                        &self.allocator,
+                        guard,
+                        |top| self.count_keys(key, top, -1),
+                    )?;
+                    write_unlinked
+                }
+            }
+            grandparent => {
+                let write_unlinked = Self::upgrade_all(unlinked)?;
+                BaseNode::remove_and_unlock::<NodePtr, A>(
+                    parent,
+                    grandparent.map_or((0, None), |(n, k)| (k, Some(n))),
+                    parent_key,
+                    &self.allocator,
+                    guard,
+                    |top| self.count_keys(key, top, -1),
+                )?;
+                write_unlinked
+            }
+        };
+
+        // the first unlinked node is the last level node, its slots are values
+        for (i, mut write_n) in write_unlinked.into_iter().enumerate() {
+            write_n.mark_obsolete();
+            let delete_n = write_n.as_mut() as *mut BaseNode as usize;
+            std::mem::forget(write_n);
+            let allocator = self.allocator.clone();
+            guard.defer(move || unsafe {
+                if i == 0 {
+                    BaseNode::drop_node_of::<V, A>(delete_n as *mut BaseNode, allocator);
+                } else {
+                    BaseNode::drop_node(delete_n as *mut BaseNode, allocator);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the inner `node` can be merged into its `child`, i.e., the merged prefix fits into a node.
+    fn can_merge(node: &ReadGuard, child: &ReadGuard) -> bool {
+        node.as_ref().prefix_len() + 1 + child.as_ref().prefix_len() <= MAX_PREFIX_LEN
+    }
+
+    /// Replaces the inner node `write_n`, the child `key` of `write_p`, with its only remaining child,
+    /// whose prefix becomes the prefix of `write_n`, the child key and its own prefix.
+    fn merge_into_child(
+        &self,
+        write_p: &mut WriteGuard,
+        key: u8,
+        mut write_n: WriteGuard,
+        (child_key, write_c): (u8, &mut WriteGuard),
+        guard: &Guard,
+    ) {
+        let n_len = write_n.as_ref().prefix_len();
+        let merged_len = n_len + 1 + write_c.as_ref().prefix_len();
+        let mut merged = Prefix::default();
+        merged[..n_len].copy_from_slice(write_n.as_ref().prefix());
+        merged[n_len] = child_key;
+        merged[n_len + 1..merged_len].copy_from_slice(write_c.as_ref().prefix());
+        write_c
+            .as_mut()
+            .set_prefix(&merged[..merged_len], merged_len);
+
+        write_p
+            .as_mut()
+            .change(key, NodePtr::from_node(write_c.as_mut() as *mut BaseNode));
+
+        write_n.mark_obsolete();
+        let delete_n = write_n.as_mut() as *mut BaseNode as usize;
+        std::mem::forget(write_n);
+        let allocator = self.allocator.clone();
+        guard.defer(move || unsafe {
+            BaseNode::drop_node(delete_n as *mut BaseNode, allocator);
+        });
+    }
+
+    fn upgrade_all(nodes: Vec<ReadGuard>) -> Result<Vec<WriteGuard>, ArtError> {
+        nodes
+            .into_iter()
+            .map(|n| n.upgrade().map_err(|(_n, v)| v))
+            .collect()
+    }
+
+    /// Unlinks the subtrees whose keys are all within the range, returns the number of removed values.
+    ///
+    /// Every step unlinks the covered children of one node in a single update of that node,
+    /// or of its parent if the node shrinks or is merged into its remaining child, and frees them as a unit.
+    /// The values of the last level nodes that straddle a bound are left in place.
+    pub(crate) fn remove_covered(&self, start: Bound<&T>, end: Bound<&T>, guard: &Guard) -> usize {
+        let bounds = (start.map(|k| k.as_bytes()), end.map(|k| k.as_bytes()));
+        let backoff = self.backoff();
+        let mut removed = 0;
+        loop {
+            let step = unsafe { &*self.root }.read_lock().and_then(|root| {
+                self.remove_covered_node(
+                    root,
+                    &mut Vec::new(),
+                    KeyTracker::default(),
+                    bounds,
+                    guard,
+                )
+            });
+            match step {
+                Ok(Covered::Removed(n)) => {
+                    removed += n;
+                    backoff.reset();
+                }
+                Ok(_) => return removed,
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    /// Unlinks the covered children of the first node below `node` that has any, see [RawTree::remove_covered].
+    /// `path` holds the ancestors of `node` and the keys to their children, `key` the key bytes above `node`.
+    fn remove_covered_node<'a>(
+        &self,
+        node: ReadGuard<'a>,
+        path: &mut Vec<(ReadGuard<'a>, u8)>,
+        mut key: KeyTracker<K_LEN>,
+        bounds: (Bound<&[u8]>, Bound<&[u8]>),
+        guard: &Guard,
+    ) -> Result<Covered, ArtError> {
+        for p in node.as_ref().prefix() {
+            key.push(*p);
+        }
+        let level = key.len();
+        match Self::span_of(key.as_bytes(), bounds) {
+            Span::Outside => {
+                node.check_version()?;
+                return Ok(Covered::Nothing);
+            }
+            // the root is never unlinked
+            Span::Inside if !path.is_empty() => {
+                node.check_version()?;
+                return Ok(Covered::All);
+            }
+            _ => {}
+        }
+        if level == K_LEN - 1 {
+            let mut values = node.as_ref().get_values::<V>(0, 255);
+            let first = values.next().map(|(k, _)| k);
+            let last = values.next_back().map(|(k, _)| k).or(first);
+            node.check_version()?;
+
+            let mut inside = |k: u8| {
+                key.push(k);
+                let span = Self::span_of(key.as_bytes(), bounds);
+                key.pop();
+                matches!(span, Span::Inside)
+            };
+            return Ok(match (first, last) {
+                (Some(first), Some(last)) if !path.is_empty() && inside(first) && inside(last) => {
+                    Covered::All
+                }
+                _ => Covered::Nothing,
+            });
+        }
+
+        let mut covered = Vec::new();
+        let mut straddling = Vec::new();
+        for (k, child) in node.as_ref().get_children(0, 255) {
+            key.push(k);
+            match Self::span_of(key.as_bytes(), bounds) {
+                Span::Outside => {}
+                Span::Inside => covered.push((k, child)),
+                Span::Straddling => straddling.push((k, child)),
+            }
+            key.pop();
+        }
+        node.check_version()?;
+
+        let mut node = node;
+        for (k, child) in straddling {
+            let child_node = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+
+            let mut child_key = key.clone();
+            child_key.push(k);
+            path.push((node, k));
+            match self.remove_covered_node(child_node, path, child_key, bounds, guard)? {
+                Covered::Nothing => {}
+                Covered::All => covered.push((k, child)),
+                removed => return Ok(removed),
+            }
+            node = path.pop().unwrap().0;
+        }
+
+        if covered.is_empty() {
+            return Ok(Covered::Nothing);
+        }
+        if covered.len() == node.as_ref().get_count() && !path.is_empty() {
+            node.check_version()?;
+            return Ok(Covered::All);
+        }
+        let removed = self.unlink_children(node, path.pop(), &covered, key.as_bytes(), guard)?;
+        Ok(Covered::Removed(removed))
+    }
+
+    /// Where the keys starting with `prefix` are relative to the range.
+    fn span_of(prefix: &[u8], (start, end): (Bound<&[u8]>, Bound<&[u8]>)) -> Span {
+        let mut min = [0; K_LEN];
+        let mut max = [u8::MAX; K_LEN];
+        min[..prefix.len()].copy_from_slice(prefix);
+        max[..prefix.len()].copy_from_slice(prefix);
+
+        let after_start = |k: &[u8]| match start {
+            Bound::Included(s) => k >= s,
+            Bound::Excluded(s) => k > s,
+            Bound::Unbounded => true,
+        };
+        let before_end = |k: &[u8]| match end {
+            Bound::Included(e) => k <= e,
+            Bound::Excluded(e) => k < e,
+            Bound::Unbounded => true,
+        };
+
+        if !after_start(&max) || !before_end(&min) {
+            Span::Outside
+        } else if after_start(&min) && before_end(&max) {
+            Span::Inside
+        } else {
+            Span::Straddling
+        }
+    }
+
+    /// Unlinks the `covered` children of the inner `node`, the node at the end of the path of `key`,
+    /// and frees them as a unit once no thread can reach them.
+    ///
+    /// Unless `node` is the root, it is merged into its remaining child if only one is left,
+    /// or replaced by a smaller node if it becomes underfull, by an update of its `parent`.
+    fn unlink_children<'a>(
+        &self,
+        node: ReadGuard<'a>,
+        parent: Option<(ReadGuard<'a>, u8)>,
+        covered: &[(u8, NodePtr)],
+        key: &[u8],
+        guard: &Guard,
+    ) -> Result<usize, ArtError> {
+        // the key position that `node` branches on
+        let level = key.len();
+        // the nodes of the covered subtrees, their versions are validated when they are locked
+        let mut subtree = Vec::new();
+        let mut last_level = Vec::new();
+        let mut values = 0;
+        let mut pending: Vec<_> = covered.iter().map(|(_, c)| (*c, level + 1)).collect();
+        while let Some((n, n_level)) = pending.pop() {
+            let n = unsafe { &*n.as_ptr() }.read_lock()?;
+            let n_level = n_level + n.as_ref().prefix_len();
+            n.check_version()?;
+            if n_level == K_LEN - 1 {
+                values += n.as_ref().get_count();
+            } else {
+                let children: Vec<_> = n.as_ref().get_children(0, 255).collect();
+                n.check_version()?;
+                pending.extend(children.into_iter().map(|(_, c)| (c, n_level + 1)));
+            }
+            subtree.push(n);
+            last_level.push(n_level == K_LEN - 1);
+        }
+
+        let mut is_covered = [false; 256];
+        for (k, _) in covered {
+            is_covered[*k as usize] = true;
+        }
+        let remaining = node.as_ref().get_count() - covered.len();
+        let n_type = node.as_ref().get_type();
+        node.check_version()?;
 
-                            write_n.as_mut().remove(node_key);
+        let write_subtree = match parent {
+            Some((parent, parent_key)) if remaining == 1 => {
+                let child = node
+                    .as_ref()
+                    .get_children(0, 255)
+                    .find(|(k, _)| !is_covered[*k as usize]);
+                node.check_version()?;
+                let (child_key, child) = child.ok_or(ArtError::VersionNotMatch)?;
+                let child = unsafe { &*child.as_ptr() }.read_lock()?;
+                node.check_version()?;
+                let merge = Self::can_merge(&node, &child);
+
+                let mut write_p = parent.upgrade().map_err(|(_n, v)| v)?;
+                let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+                let write_subtree = Self::upgrade_all(subtree)?;
+                if merge {
+                    let mut write_c = child.upgrade().map_err(|(_n, v)| v)?;
+                    self.merge_into_child(
+                        &mut write_p,
+                        parent_key,
+                        write_n,
+                        (child_key, &mut write_c),
+                        guard,
+                    );
+                } else {
+                    for (k, _) in covered {
+                        write_n.as_mut().remove(*k);
+                    }
+                    write_n.as_ref().add_keys(-(values as isize));
+                }
+                write_p.as_ref().add_keys(-(values as isize));
+                self.count_keys(key, write_p.as_ref(), -(values as isize));
+                write_subtree
+            }
+            Some((parent, parent_key)) if remaining <= n_type.shrink_threshold() => {
+                let mut write_p = parent.upgrade().map_err(|(_n, v)| v)?;
+                let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+                let write_subtree = Self::upgrade_all(subtree)?;
+                write_p.as_ref().add_keys(-(values as isize));
+                self.count_keys(key, write_p.as_ref(), -(values as isize));
+                match write_n.as_ref().copy_children::<NodePtr>(
+                    NodeType::fitting(remaining),
+                    |k| !is_covered[k as usize],
+                    &self.allocator,
+                ) {
+                    Ok(n_small) => {
+                        write_n
+                            .as_ref()
+                            .move_keys(unsafe { &*n_small }, -(values as isize));
+                        write_p
+                            .as_mut()
+                            .change(parent_key, NodePtr::from_node(n_small));
+                        write_n.mark_obsolete();
+                        let delete_n = write_n.as_mut() as *mut BaseNode as usize;
+                        std::mem::forget(write_n);
+                        let allocator = self.allocator.clone();
+                        guard.defer(move || unsafe {
+                            BaseNode::drop_node(delete_n as *mut BaseNode, allocator);
+                        });
+                    }
+                    // shrinking is optional
+                    Err(_) => {
+                        for (k, _) in covered {
+                            write_n.as_mut().remove(*k);
                         }
-                        return Ok(Some((child_node.as_tid(), None)));
+                        write_n.as_ref().add_keys(-(values as isize));
                     }
                 }
+                write_subtree
+            }
+            _ => {
+                let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+                let write_subtree = Self::upgrade_all(subtree)?;
+                for (k, _) in covered {
+                    write_n.as_mut().remove(*k);
+                }
+                write_n.as_ref().add_keys(-(values as isize));
+                self.count_keys(key, write_n.as_ref(), -(values as isize));
+                write_subtree
+            }
+        };
+
+        let mut retired = Vec::with_capacity(write_subtree.len());
+        for (mut write_n, last_level) in write_subtree.into_iter().zip(last_level) {
+            write_n.mark_obsolete();
+            retired.push((write_n.as_mut() as *mut BaseNode as usize, last_level));
+            std::mem::forget(write_n);
+        }
+        let allocator = self.allocator.clone();
+        guard.defer(move || unsafe {
+            for (n, last_level) in retired {
+                if last_level {
+                    BaseNode::drop_node_of::<V, A>(n as *mut BaseNode, allocator.clone());
+                } else {
+                    BaseNode::drop_node(n as *mut BaseNode, allocator.clone());
+                }
+            }
+        });
+        self.len.add(-(values as isize));
+        Ok(values)
+    }
+
+    /// Looks up `key` and keeps the read locks of the nodes on its path.
+    pub(crate) fn locate(&self, key: &T) -> Result<Located<'_, V>, ArtError> {
+        let key = key.as_bytes();
+        let mut path = Vec::new();
+        let mut level = 0;
+        let mut node = unsafe { &*self.root }.read_lock()?;
+
+        loop {
+            let prefix = node.as_ref().prefix();
+            let matched = prefix
+                .iter()
+                .zip(&key[level..])
+                .take_while(|(n, k)| n == k)
+                .count();
+            if matched < prefix.len() {
+                node.check_version()?;
+                let spot = Spot::Mismatch(matched);
+                return Ok(Located {
+                    path,
+                    node,
+                    level,
+                    spot,
+                });
+            }
+            level += matched;
+            let Some(&node_key) = key.get(level) else {
+                // only a node that was changed while it was read has a path beyond the key
+                return Err(ArtError::VersionNotMatch);
+            };
+
+            if level == K_LEN - 1 {
+                let value = node.as_ref().get_value::<V>(node_key);
+                node.check_version()?;
+                let spot = value.map_or(Spot::NoValue, Spot::Value);
+                return Ok(Located {
+                    path,
+                    node,
+                    level,
+                    spot,
+                });
             }
 
+            let child = node.as_ref().get_child(node_key);
+            node.check_version()?;
+            let Some(child) = child else {
+                return Ok(Located {
+                    path,
+                    node,
+                    level,
+                    spot: Spot::NoChild,
+                });
+            };
+
+            let child = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            path.push((node, node_key));
+            node = child;
             level += 1;
-            parent = Some((node, node_key));
-            node = unsafe { &*child_node.as_ptr() }.read_lock()?;
         }
     }
 
+    /// Applies the `writes` of a transaction in ascending key order, `None` removes the key,
+    /// if none of the `reads` nodes has changed.
+    ///
+    /// The nodes that the writes change are write-locked in the order of their addresses, then the versions
+    /// of all nodes the transaction relies on are validated. Last level nodes that lose all values are unlinked
+    /// together with the ancestors that only lead to them, unlike a single removal the remaining ancestor
+    /// is not merged into its remaining child. New keys below an inner node are bulk loaded.
+    /// A write that needs more room first grows the full node or splits the compressed path it leaves,
+    /// which keeps the values as they are, and the commit fails so that the transaction is retried.
+    pub(crate) fn commit(
+        &self,
+        reads: &[ReadGuard],
+        writes: &[(&T, Option<V>)],
+        guard: &Guard,
+    ) -> Result<(), ArtError> {
+        let mut expected = HashMap::new();
+        for node in reads {
+            Self::expect(&mut expected, node)?;
+        }
+
+        let mut leaves: BTreeMap<usize, LeafWrites<V>> = BTreeMap::new();
+        let mut new_children: BTreeMap<usize, NewChildren<V>> = BTreeMap::new();
+        for &(key, val) in writes {
+            let Located {
+                path,
+                node,
+                level,
+                spot,
+            } = self.locate(key)?;
+            // every write relies on the node where its lookup ended, removing an absent key only on that
+            Self::expect(&mut expected, &node)?;
+            let addr = node.as_ref() as *const BaseNode as usize;
+            let key = key.as_bytes();
+            let (val, present) = match (spot, val) {
+                (Spot::Value(_), val) => (val, true),
+                (Spot::NoValue, Some(v)) => (Some(v), false),
+                (Spot::NoChild, Some(v)) => {
+                    new_children
+                        .entry(addr)
+                        .or_insert_with(|| NewChildren {
+                            path,
+                            node,
+                            level,
+                            values: Vec::new(),
+                            loaded: Vec::new(),
+                        })
+                        .values
+                        .push((key, v));
+                    continue;
+                }
+                (Spot::Mismatch(matched), Some(_)) => {
+                    let parent = path.last().cloned().ok_or(ArtError::VersionNotMatch)?;
+                    self.split_prefix(parent, node, level, matched, guard)?;
+                    return Err(ArtError::VersionNotMatch);
+                }
+                (_, None) => continue,
+            };
+            let leaf = leaves.entry(addr).or_insert_with(|| LeafWrites {
+                count: node.as_ref().get_count(),
+                path,
+                node,
+                key,
+                writes: Vec::new(),
+            });
+            match (present, val) {
+                (true, None) => leaf.count = leaf.count.saturating_sub(1),
+                (false, Some(_)) => leaf.count += 1,
+                _ => {}
+            }
+            leaf.writes.push((key[K_LEN - 1], val, present));
+        }
+
+        for leaf in leaves.values() {
+            if leaf.count > leaf.node.as_ref().get_type().capacity() {
+                self.grow_node::<V>(
+                    leaf.path.last().cloned(),
+                    leaf.node.clone(),
+                    NodeType::fitting(leaf.count),
+                    guard,
+                )?;
+                return Err(ArtError::VersionNotMatch);
+            }
+            self.note_fan_out(K_LEN - 1, leaf.count);
+        }
+        for parent in new_children.values() {
+            let count = parent.node.as_ref().get_count() + parent.added();
+            if count > parent.node.as_ref().get_type().capacity() {
+                self.grow_node::<NodePtr>(
+                    parent.path.last().cloned(),
+                    parent.node.clone(),
+                    NodeType::fitting(count),
+                    guard,
+                )?;
+                return Err(ArtError::VersionNotMatch);
+            }
+            self.note_fan_out(parent.level, count);
+        }
+
+        let mut to_lock: BTreeSet<usize> =
+            leaves.keys().chain(new_children.keys()).copied().collect();
+        // the nodes to unlink and whether they are last level nodes, the remaining nodes to unlink them from
+        let mut unlinked = Vec::new();
+        let mut detached = Vec::new();
+        let mut children_left = HashMap::new();
+        for (addr, leaf) in leaves
+            .iter()
+            .filter(|(_, l)| l.count == 0 && !l.path.is_empty())
+        {
+            unlinked.push((*addr, true));
+            for (i, (node, key)) in leaf.path.iter().enumerate().rev() {
+                Self::expect(&mut expected, node)?;
+                let addr = node.as_ref() as *const BaseNode as usize;
+                to_lock.insert(addr);
+                let left = children_left.entry(addr).or_insert_with(|| {
+                    node.as_ref().get_count() + new_children.get(&addr).map_or(0, |c| c.added())
+                });
+                // every child is unlinked once, fewer children than that are only read from a changing node
+                *left = left.checked_sub(1).ok_or(ArtError::VersionNotMatch)?;
+                if *left > 0 || i == 0 {
+                    detached.push((addr, *key));
+                    break;
+                }
+                unlinked.push((addr, false));
+            }
+        }
+
+        #[cfg(feature = "order_statistics")]
+        let counts = Self::commit_counts(&leaves, &new_children, &mut expected, &mut to_lock)?;
+
+        let mut added = 0;
+        for parent in new_children.values_mut() {
+            let mut loader = BulkLoader::new(self, parent.level);
+            let children = parent
+                .values
+                .iter()
+                .try_for_each(|(k, v)| loader.push(k, *v))
+                .and_then(|()| loader.finish());
+            match children {
+                Ok((children, count)) => {
+                    added += count as isize;
+                    parent.loaded = children;
+                }
+                Err(e) => {
+                    self.drop_loaded(new_children);
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut locked = match Self::lock_expected(&expected, &to_lock) {
+            Ok(locked) => locked,
+            Err(e) => {
+                self.drop_loaded(new_children);
+                return Err(e);
+            }
+        };
+
+        let gone: HashSet<usize> = unlinked.iter().map(|(addr, _)| *addr).collect();
+        for (addr, mut leaf) in leaves {
+            let write_n = locked.get_mut(&addr).unwrap();
+            // removals first, the node only has room for the values it holds in the end
+            leaf.writes.sort_by_key(|(_, val, _)| val.is_some());
+            for (key, val, present) in leaf.writes {
+                match val {
+                    None => {
+                        added -= 1;
+                        if !gone.contains(&addr) {
+                            write_n.as_mut().remove_value::<V>(key);
+                        }
+                    }
+                    Some(v) if present => {
+                        write_n.as_mut().change_value::<V>(key, v);
+                    }
+                    Some(v) => {
+                        added += 1;
+                        write_n.as_mut().insert_value::<V>(key, v);
+                    }
+                }
+            }
+        }
+        for (addr, parent) in new_children {
+            let write_n = locked.get_mut(&addr).unwrap();
+            for (key, child) in parent.loaded {
+                write_n.as_mut().insert_value::<NodePtr>(key, child);
+            }
+        }
+        for (addr, key) in detached {
+            if !gone.contains(&addr) {
+                locked.get_mut(&addr).unwrap().as_mut().remove(key);
+            }
+        }
+        #[cfg(feature = "order_statistics")]
+        if let Some(counts) = counts {
+            for (addr, delta) in &counts.deltas {
+                locked[addr].as_ref().add_keys(*delta);
+            }
+            let top = locked[&counts.top].as_ref();
+            self.count_keys(counts.key, top, counts.deltas[&counts.top]);
+        }
+
+        let mut retired = Vec::new();
+        for (addr, last_level) in unlinked {
+            let mut write_n = locked.remove(&addr).unwrap();
+            write_n.mark_obsolete();
+            retired.push((write_n.as_mut() as *mut BaseNode as usize, last_level));
+            std::mem::forget(write_n);
+        }
+        let allocator = self.allocator.clone();
+        guard.defer(move || unsafe {
+            for (n, last_level) in retired {
+                if last_level {
+                    BaseNode::drop_node_of::<V, A>(n as *mut BaseNode, allocator.clone());
+                } else {
+                    BaseNode::drop_node(n as *mut BaseNode, allocator.clone());
+                }
+            }
+        });
+        self.len.add(added);
+        Ok(())
+    }
+
+    /// Moves the value of `old` to `new` as one step, replacing the value of `new` if present.
+    /// Returns the moved value, `None` if `old` is absent.
+    ///
+    /// The removal and the insert are a single commit that relies on the node holding the value of `old`:
+    /// it locks that node alone if both keys are in it, or together with the node of `new`.
+    pub(crate) fn rename(&self, old: &T, new: &T, guard: &Guard) -> Result<Option<V>, OOMError> {
+        let backoff = self.backoff();
+        loop {
+            let located = match self.locate(old) {
+                Ok(located) => located,
+                Err(_) => {
+                    backoff.wait();
+                    continue;
+                }
+            };
+            let Spot::Value(v) = located.spot else {
+                return Ok(None);
+            };
+            if old == new {
+                return Ok(Some(v));
+            }
+            let writes = if old < new {
+                [(old, None), (new, Some(v))]
+            } else {
+                [(new, Some(v)), (old, None)]
+            };
+            match self.commit(&[located.node], &writes, guard) {
+                Ok(()) => return Ok(Some(v)),
+                Err(ArtError::Oom) => return Err(OOMError::new()),
+                Err(_) => backoff.wait(),
+            }
+        }
+    }
+
+    /// Records the version of `node` that a commit relies on, fails if it was read before with another version.
+    fn expect<'a>(
+        expected: &mut HashMap<usize, ReadGuard<'a>>,
+        node: &ReadGuard<'a>,
+    ) -> Result<(), ArtError> {
+        let addr = node.as_ref() as *const BaseNode as usize;
+        match expected.entry(addr) {
+            Entry::Occupied(e) if e.get().version() != node.version() => {
+                Err(ArtError::VersionNotMatch)
+            }
+            Entry::Occupied(_) => Ok(()),
+            Entry::Vacant(e) => {
+                e.insert(node.clone());
+                Ok(())
+            }
+        }
+    }
+
+    /// Plans the subtree counts of a commit: the nodes that gain or lose values and their ancestors change by that many.
+    ///
+    /// The nodes from the lowest common ancestor of these nodes down to them, or from the highest node above it
+    /// that the commit locks anyway, are added to `to_lock`. Their counts change with the values, as for a single
+    /// write, and only their ancestors are counted without a lock, see [RawTree::count_keys].
+    #[cfg(feature = "order_statistics")]
+    fn commit_counts<'a, 'k>(
+        leaves: &BTreeMap<usize, LeafWrites<'a, 'k, V>>,
+        new_children: &BTreeMap<usize, NewChildren<'a, 'k, V>>,
+        expected: &mut HashMap<usize, ReadGuard<'a>>,
+        to_lock: &mut BTreeSet<usize>,
+    ) -> Result<Option<CommitCounts<'k>>, ArtError> {
+        let addr = |n: &ReadGuard| n.as_ref() as *const BaseNode as usize;
+        let nodes = |path: &'_ [(ReadGuard<'a>, u8)], node: &'_ ReadGuard<'a>| {
+            let path: Vec<_> = path.iter().map(|(n, _)| n.clone()).collect();
+            path.into_iter().chain(std::iter::once(node.clone()))
+        };
+        // the nodes from the root to a node that gains or loses values, the change and a key below it
+        let mut changed = Vec::new();
+        for leaf in leaves.values() {
+            let delta = leaf.count as isize - leaf.node.as_ref().get_count() as isize;
+            if delta != 0 {
+                let nodes: Vec<_> = nodes(&leaf.path, &leaf.node).collect();
+                changed.push((nodes, delta, leaf.key));
+            }
+        }
+        for parent in new_children.values() {
+            let nodes: Vec<_> = nodes(&parent.path, &parent.node).collect();
+            changed.push((nodes, parent.values.len() as isize, parent.values[0].0));
+        }
+
+        let Some((first, _, key)) = changed.first() else {
+            return Ok(None);
+        };
+        let common = changed
+            .iter()
+            .map(|(nodes, _, _)| {
+                let same = nodes
+                    .iter()
+                    .zip(first)
+                    .take_while(|(n, f)| addr(n) == addr(f));
+                same.count()
+            })
+            .min()
+            .unwrap_or(0);
+        let top = first[..common]
+            .iter()
+            .position(|n| to_lock.contains(&addr(n)))
+            .unwrap_or(common - 1);
+
+        let mut deltas = HashMap::new();
+        for (nodes, delta, _) in &changed {
+            for n in &nodes[top..] {
+                Self::expect(expected, n)?;
+                to_lock.insert(addr(n));
+                *deltas.entry(addr(n)).or_insert(0) += delta;
+            }
+        }
+        Ok(Some(CommitCounts {
+            deltas,
+            top: addr(&first[top]),
+            key,
+        }))
+    }
+
+    /// Write-locks the `to_lock` nodes in the order of their addresses, validates the versions of the others.
+    fn lock_expected<'a>(
+        expected: &HashMap<usize, ReadGuard<'a>>,
+        to_lock: &BTreeSet<usize>,
+    ) -> Result<HashMap<usize, WriteGuard<'a>>, ArtError> {
+        let locked = to_lock
+            .iter()
+            .map(|addr| {
+                let write_n = expected[addr].clone().upgrade().map_err(|(_n, v)| v)?;
+                Ok((*addr, write_n))
+            })
+            .collect::<Result<HashMap<_, _>, ArtError>>()?;
+        for (addr, node) in expected {
+            if !to_lock.contains(addr) {
+                node.check_version()?;
+            }
+        }
+        Ok(locked)
+    }
+
+    /// Frees the subtrees that a failed commit has loaded.
+    fn drop_loaded(&self, new_children: BTreeMap<usize, NewChildren<V>>) {
+        for parent in new_children.into_values() {
+            for (_, child) in parent.loaded {
+                unsafe { self.drop_child(child, parent.level) };
+            }
+        }
+    }
+
+    /// Replaces `node`, the child `key` of `parent`, with a copy of type `n_type` that has room for more slots.
+    /// `S` is the slot type of `node`.
+    fn grow_node<S: Copy>(
+        &self,
+        parent: Option<(ReadGuard, u8)>,
+        node: ReadGuard,
+        n_type: NodeType,
+        guard: &Guard,
+    ) -> Result<(), ArtError> {
+        // the root is a `Node256`, only a node that was changed while it was read seems to be short of room
+        let (parent, key) = parent.ok_or(ArtError::VersionNotMatch)?;
+        let mut write_p = parent.upgrade().map_err(|(_n, v)| v)?;
+        let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
+
+        let n_big = write_n
+            .as_ref()
+            .copy_children::<S>(n_type, |_| true, &self.allocator)?;
+        write_n.as_ref().move_keys(unsafe { &*n_big }, 0);
+        write_p.as_mut().change(key, NodePtr::from_node(n_big));
+
+        write_n.mark_obsolete();
+        let delete_n = write_n.as_mut() as *mut BaseNode as usize;
+        std::mem::forget(write_n);
+        let allocator = self.allocator.clone();
+        guard.defer(move || unsafe {
+            BaseNode::drop_node_of::<S, A>(delete_n as *mut BaseNode, allocator);
+        });
+        Ok(())
+    }
+
+    /// Splits the compressed path of `node`, the child `key` of `parent`, after `matched` bytes:
+    /// a new `Node4` with the bytes before as its prefix holds `node` as its only child,
+    /// so that a key leaving the path there can be inserted into it. The path of `node` starts at `level`.
+    fn split_prefix(
+        &self,
+        (parent, key): (ReadGuard, u8),
+        node: ReadGuard,
+        level: usize,
+        matched: usize,
+        guard: &Guard,
+    ) -> Result<(), ArtError> {
+        let mut write_p = parent.upgrade().map_err(|(_n, v)| v)?;
+        let write_n = node.upgrade().map_err(|(_n, v)| v)?;
+
+        let prefix = write_n.as_ref().prefix();
+        let middle = BaseNode::make_node::<Node4>(&prefix[..matched], &self.allocator)?;
+        let child_key = prefix[matched];
+        let n = match self.cut_prefix(write_n, level, matched + 1, guard) {
+            Ok(n) => n,
+            Err(e) => {
+                unsafe { BaseNode::drop_node(middle as *mut BaseNode, self.allocator.clone()) };
+                return Err(e);
+            }
+        };
+        unsafe { &mut *middle }.insert(child_key, n);
+        let middle = middle as *mut BaseNode;
+        unsafe { &*middle }.set_keys(unsafe { &*middle }.child_keys());
+        write_p.as_mut().change(key, NodePtr::from_node(middle));
+        Ok(())
+    }
+
+    /// Drops the first `cut` bytes of the compressed path of `write_n`, whose path starts at `level`,
+    /// for a split of the path that makes it the child of a new node. Returns the node to link to the new node.
+    ///
+    /// With subtree counts, an inner node is replaced by a copy instead: threads that count keys below it
+    /// may have passed its parent before the split, they find the count moved and count the new node too.
+    fn cut_prefix(
+        &self,
+        mut write_n: WriteGuard,
+        level: usize,
+        cut: usize,
+        guard: &Guard,
+    ) -> Result<NodePtr, ArtError> {
+        let mut remaining = Prefix::default();
+        let remaining_len = write_n.as_ref().prefix_len() - cut;
+        remaining[..remaining_len].copy_from_slice(&write_n.as_ref().prefix()[cut..]);
+
+        if !cfg!(feature = "order_statistics") || level + cut + remaining_len == K_LEN - 1 {
+            write_n
+                .as_mut()
+                .set_prefix(&remaining[..remaining_len], remaining_len);
+            return Ok(NodePtr::from_node(write_n.as_mut()));
+        }
+
+        let n_type = write_n.as_ref().get_type();
+        let copy = write_n
+            .as_ref()
+            .copy_children::<NodePtr>(n_type, |_| true, &self.allocator)?;
+        unsafe { &mut *copy }.set_prefix(&remaining[..remaining_len], remaining_len);
+        write_n.as_ref().move_keys(unsafe { &*copy }, 0);
+
+        write_n.mark_obsolete();
+        let delete_n = write_n.as_mut() as *mut BaseNode as usize;
+        std::mem::forget(write_n);
+        let allocator = self.allocator.clone();
+        guard.defer(move || unsafe {
+            BaseNode::drop_node(delete_n as *mut BaseNode, allocator);
+        });
+        Ok(NodePtr::from_node(copy))
+    }
+
     #[inline]
     pub(crate) fn compute_if_present<F>(
         &self,
         k: &T,
         remapping_function: &mut F,
         guard: &Guard,
-    ) -> Option<(usize, Option<usize>)>
+    ) -> Option<(V, Option<V>)>
     where
-        F: FnMut(usize) -> Option<usize>,
+        F: FnMut(V) -> Option<V>,
     {
-        let backoff = Backoff::new();
+        let backoff = self.backoff();
         loop {
             match self.compute_if_present_inner(k, &mut *remapping_function, guard) {
                 Ok(n) => return n,
-                Err(_) => backoff.spin(),
+                Err(_) => backoff.wait(),
             }
         }
     }
 
+    /// Same as [RawTree::compute_if_present], but gives up once the `budget` runs out.
+    #[inline]
+    pub(crate) fn try_compute_if_present<F>(
+        &self,
+        k: &T,
+        remapping_function: &mut F,
+        budget: &RetryBudget,
+        guard: &Guard,
+    ) -> Result<Option<(V, Option<V>)>, ArtError>
+    where
+        F: FnMut(V) -> Option<V>,
+    {
+        self.retry_within(budget, || {
+            self.compute_if_present_inner(k, &mut *remapping_function, guard)
+        })
+    }
+
+    /// Picks a value uniformly at random, `None` if the tree is empty.
+    ///
+    /// With the subtree counts of `order_statistics`, it selects a random position, see [RawTree::select].
+    ///
+    /// Otherwise, a descent that picks one of the children of every node uniformly reaches a value with the inverse
+    /// of the product of the child counts on its path, which favors the values below nodes with few children.
+    /// It is kept with the probability of that product relative to the [FanOut](crate::utils::FanOut) bound,
+    /// which makes every value equally likely, and restarts otherwise.
+    /// The sampling fails after `attempts` rejected descents, see [RawTree::sample_attempts].
+    #[cfg(feature = "db_extension")]
+    pub(crate) fn sample(
+        &self,
+        rng: &mut impl rand::Rng,
+        attempts: usize,
+        _guard: &Guard,
+    ) -> Result<Option<(KeyTracker<K_LEN>, V)>, ArtError> {
+        let backoff = self.backoff();
+        let mut rejected = 0;
+        while rejected < attempts {
+            match self.sample_inner(rng) {
+                Ok(Some(sampled)) => return Ok(sampled),
+                Ok(None) => rejected += 1,
+                Err(_) => backoff.wait(),
+            }
+        }
+        Err(ArtError::VersionNotMatch)
+    }
+
+    /// The number of descents [RawTree::sample] may take to pick a value of a tree with `keys` values,
+    /// `None` if picking `n` values this way is expected to take longer than scanning the keys.
+    #[cfg(feature = "db_extension")]
+    pub(crate) fn sample_attempts(&self, n: usize, keys: usize) -> Option<usize> {
+        let expected = self.sample_descents(keys);
+        if expected * n as f64 > keys.saturating_add(n) as f64 {
+            return None;
+        }
+        Some(((expected * SAMPLE_PATIENCE).ceil() as usize).max(SAMPLE_ATTEMPTS))
+    }
+
+    /// The expected number of descents of [RawTree::sample] per value, none is rejected.
+    #[cfg(all(feature = "db_extension", feature = "order_statistics"))]
+    fn sample_descents(&self, _keys: usize) -> f64 {
+        1.0
+    }
+
+    /// The expected number of descents of [RawTree::sample] per value.
+    ///
+    /// A descent reaches a value with the inverse of the product of the child counts on its path
+    /// and keeps it with that product relative to the bound, so it is kept with `keys` over the bound.
+    /// The bound multiplies the largest fan-out of every level, in a tree whose keys are spread unevenly
+    /// they come from different parts of the tree and the bound is far above any product.
+    #[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+    fn sample_descents(&self, keys: usize) -> f64 {
+        let bound: f64 = (0..K_LEN)
+            .map(|level| self.fan_out.get(level) as f64)
+            .product();
+        bound / keys.max(1) as f64
+    }
+
+    /// One pick of [RawTree::sample], `None` if the position is past the keys removed since the count was read.
+    #[cfg(all(feature = "db_extension", feature = "order_statistics"))]
+    fn sample_inner(
+        &self,
+        rng: &mut impl rand::Rng,
+    ) -> Result<Option<Option<(KeyTracker<K_LEN>, V)>>, ArtError> {
+        let root = unsafe { &*self.root }.read_lock()?;
+        let keys = root.as_ref().keys();
+        root.check_version()?;
+        // the root is never replaced, its count is never moved
+        let keys = keys.unwrap_or(0);
+        if keys == 0 {
+            return Ok(Some(None));
+        }
+        Ok(self.select_inner(rng.gen_range(0..keys))?.map(Some))
+    }
+
+    /// One descent of [RawTree::sample], `None` if it is rejected, fails if it conflicts with a writer.
+    #[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+    fn sample_inner(
+        &self,
+        rng: &mut impl rand::Rng,
+    ) -> Result<Option<Option<(KeyTracker<K_LEN>, V)>>, ArtError> {
+        let mut node = unsafe { &*self.root }.read_lock()?;
+        let mut key = KeyTracker::<K_LEN>::default();
+        // the probability to keep the descent so far, it is rejected once it drops below `threshold`
+        let threshold: f64 = rng.gen();
+        let mut keep = 1.0;
+
+        loop {
+            let prefix = node.as_ref().prefix();
+            if key.len() + prefix.len() >= K_LEN {
+                node.check_version()?;
+                return Err(ArtError::VersionNotMatch);
+            }
+            // the path does not branch on the prefix bytes
+            for k in prefix {
+                keep /= self.fan_out.get(key.len()) as f64;
+                key.push(*k);
+            }
+            let level = key.len();
+            keep *= node.as_ref().get_count() as f64 / self.fan_out.get(level) as f64;
+
+            if level == K_LEN - 1 {
+                let value = node.as_ref().get_random_value::<V>(rng);
+                node.check_version()?;
+                // only the root is ever empty
+                let Some((k, value)) = value else {
+                    return Ok(Some(None));
+                };
+                if keep <= threshold {
+                    return Ok(None);
+                }
+                key.push(k);
+                return Ok(Some(Some((key, value))));
+            }
+
+            let child = node.as_ref().get_random_child(rng);
+            node.check_version()?;
+            let Some((k, child)) = child else {
+                return Ok(Some(None));
+            };
+            if keep <= threshold {
+                return Ok(None);
+            }
+            key.push(k);
+
+            let child = unsafe { &*child.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            node = child;
+        }
+    }
+
     #[inline]
     #[cfg(feature = "db_extension")]
     pub(crate) fn compute_on_random(
         &self,
         rng: &mut impl rand::Rng,
-        f: &mut impl FnMut(usize, usize) -> usize,
+        f: &mut impl FnMut(&[u8], V) -> V,
         guard: &Guard,
-    ) -> Option<(usize, usize, usize)> {
-        let backoff = Backoff::new();
+    ) -> Option<(crate::utils::KeyTracker<K_LEN>, V, V)> {
+        let backoff = self.backoff();
         loop {
             match self.compute_on_random_inner(rng, f, guard) {
                 Ok(n) => return n,
-                Err(_) => backoff.spin(),
+                Err(_) => backoff.wait(),
             }
         }
     }
@@ -506,10 +2599,10 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
     fn compute_on_random_inner(
         &self,
         rng: &mut impl rand::Rng,
-        f: &mut impl FnMut(usize, usize) -> usize,
+        f: &mut impl FnMut(&[u8], V) -> V,
         _guard: &Guard,
-    ) -> Result<Option<(usize, usize, usize)>, ArtError> {
-        let mut node = unsafe { &*self.root }.base().read_lock()?;
+    ) -> Result<Option<(crate::utils::KeyTracker<K_LEN>, V, V)>, ArtError> {
+        let mut node = unsafe { &*self.root }.read_lock()?;
 
         let mut key_tracker = crate::utils::KeyTracker::default();
 
@@ -518,37 +2611,44 @@ impl<T: RawKey, A: Allocator + Clone + Send> RawTree<T, A> {
                 key_tracker.push(*k);
             }
 
-            let child_node = node.as_ref().get_random_child(rng);
-            node.check_version()?;
-
-            let (k, child_node) = match child_node {
-                Some(n) => n,
-                None => return Ok(None),
-            };
+            if key_tracker.len() == K_LEN - 1 {
+                let value = node.as_ref().get_random_value::<V>(rng);
+                node.check_version()?;
 
-            key_tracker.push(k);
+                let (k, value) = match value {
+                    Some(v) => v,
+                    None => return Ok(None),
+                };
+                key_tracker.push(k);
 
-            if key_tracker.len() == MAX_KEY_LEN {
-                let new_v = f(key_tracker.to_usize_key(), child_node.as_tid());
-                if new_v == child_node.as_tid() {
+                let new_v = f(key_tracker.as_bytes(), value);
+                if new_v == value {
                     // Don't acquire the lock if the value is not changed
-                    return Ok(Some((key_tracker.to_usize_key(), new_v, new_v)));
+                    return Ok(Some((key_tracker, new_v, new_v)));
                 }
 
                 let mut write_n = node.upgrade().map_err(|(_n, v)| v)?;
 
-                let old_v = write_n.as_mut().change(k, NodePtr::from_tid(new_v));
+                let old_v = write_n.as_mut().change_value(k, new_v);
 
-                debug_assert_eq!(old_v.as_tid(), child_node.as_tid());
+                debug_assert!(old_v == value);
 
-                return Ok(Some((
-                    key_tracker.to_usize_key(),
-                    child_node.as_tid(),
-                    new_v,
-                )));
+                return Ok(Some((key_tracker, value, new_v)));
             }
 
-            node = unsafe { &*child_node.as_ptr() }.read_lock()?;
+            let child_node = node.as_ref().get_random_child(rng);
+            node.check_version()?;
+
+            let (k, child_node) = match child_node {
+                Some(n) => n,
+                None => return Ok(None),
+            };
+
+            key_tracker.push(k);
+
+            let child = unsafe { &*child_node.as_ptr() }.read_lock()?;
+            node.check_version()?;
+            node = child;
         }
     }
 }
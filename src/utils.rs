@@ -1,20 +1,68 @@
-use crate::base_node::MAX_KEY_LEN;
 use crate::node_ptr::NodePtr;
 use core::cell::Cell;
 use core::fmt;
+use std::{
+    sync::atomic::{AtomicIsize, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
 
 const SPIN_LIMIT: u32 = 6;
 const YIELD_LIMIT: u32 = 10;
+/// The first park of [BackoffPolicy::Park] is 1us, every later park doubles it up to `2^PARK_LIMIT` us.
+const PARK_LIMIT: u32 = 20;
+
+/// How a tree waits before it restarts an operation that conflicted with a concurrent writer,
+/// chosen when the tree is created, e.g., with [Art::with_backoff](crate::Art::with_backoff).
+///
+/// Every policy starts by spinning a few times: most conflicts are short, a writer holds a node lock
+/// only for a few memory writes. They differ in what happens when the conflict persists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackoffPolicy {
+    /// Only spins, with exponentially more spin loop hints up to `2^6` per restart.
+    /// It has the lowest latency, but burns the CPU as long as the conflict lasts.
+    #[default]
+    Spin,
+    /// Spins for the first few restarts, then yields the thread to the OS scheduler on every restart.
+    SpinThenYield,
+    /// Spins, then yields, then parks the thread for an exponentially growing time, from 1us up to `max_park`.
+    /// Heavily contended writers stop using the CPU, at the cost of latency.
+    Park { max_park: Duration },
+}
 
 /// Backoff implementation from the Crossbeam, added shuttle instrumentation
 pub(crate) struct Backoff {
     step: Cell<u32>,
+    policy: BackoffPolicy,
 }
 
 impl Backoff {
     #[inline]
     pub(crate) fn new() -> Self {
-        Backoff { step: Cell::new(0) }
+        Self::with_policy(BackoffPolicy::Spin)
+    }
+
+    #[inline]
+    pub(crate) fn with_policy(policy: BackoffPolicy) -> Self {
+        Backoff {
+            step: Cell::new(0),
+            policy,
+        }
+    }
+
+    /// Waits before a restart according to the policy.
+    #[inline]
+    pub(crate) fn wait(&self) {
+        match self.policy {
+            BackoffPolicy::Spin => self.spin(),
+            BackoffPolicy::SpinThenYield => self.snooze(),
+            BackoffPolicy::Park { max_park } => {
+                if self.is_completed() {
+                    self.park(max_park)
+                } else {
+                    self.snooze()
+                }
+            }
+        }
     }
 
     #[inline]
@@ -36,7 +84,6 @@ impl Backoff {
         shuttle::thread::yield_now();
     }
 
-    #[allow(dead_code)]
     #[inline]
     pub(crate) fn snooze(&self) {
         if self.step.get() <= SPIN_LIMIT {
@@ -60,6 +107,25 @@ impl Backoff {
     pub(crate) fn is_completed(&self) -> bool {
         self.step.get() > YIELD_LIMIT
     }
+
+    /// Parks the thread once the backoff is completed, twice as long as the last time.
+    fn park(&self, max_park: Duration) {
+        let exp = (self.step.get() - YIELD_LIMIT - 1).min(PARK_LIMIT);
+        let timeout = Duration::from_micros(1 << exp).min(max_park);
+
+        #[cfg(all(feature = "shuttle", test))]
+        {
+            let _ = timeout;
+            shuttle::thread::yield_now();
+        }
+
+        #[cfg(not(all(feature = "shuttle", test)))]
+        std::thread::park_timeout(timeout);
+
+        if exp < PARK_LIMIT {
+            self.step.set(self.step.get() + 1);
+        }
+    }
 }
 
 impl fmt::Debug for Backoff {
@@ -77,16 +143,190 @@ impl Default for Backoff {
     }
 }
 
-#[derive(Default, Clone)]
-pub(crate) struct KeyTracker {
+/// How long the `try_` operations retry before giving up with a [Contended](crate::Contended) error.
+///
+/// An operation restarts when it conflicts with a concurrent writer, e.g., a node it read was changed
+/// or is locked. The budget bounds the number of restarts, the wall-clock time, or both;
+/// the first attempt is always made.
+///
+/// # Examples
+///
+/// ```
+/// use congee::RetryBudget;
+/// use std::time::Duration;
+///
+/// // give up after 16 restarts or 50 microseconds, whichever comes first
+/// let budget = RetryBudget::restarts(16).with_timeout(Duration::from_micros(50));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct RetryBudget {
+    max_restarts: u32,
+    deadline: Option<Instant>,
+}
+
+impl RetryBudget {
+    /// Allows at most `n` restarts, `0` means a single attempt.
+    pub fn restarts(n: u32) -> Self {
+        Self {
+            max_restarts: n,
+            deadline: None,
+        }
+    }
+
+    /// Allows restarts until `deadline`.
+    pub fn deadline(deadline: Instant) -> Self {
+        Self::restarts(u32::MAX).with_deadline(deadline)
+    }
+
+    /// Allows restarts for `timeout` from now.
+    pub fn timeout(timeout: Duration) -> Self {
+        Self::deadline(Instant::now() + timeout)
+    }
+
+    /// Also stops restarting at `deadline`.
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// Also stops restarting `timeout` from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Whether the `restarts`-th restart is allowed.
+    #[inline]
+    pub(crate) fn allows(&self, restarts: u32) -> bool {
+        restarts <= self.max_restarts && self.deadline.is_none_or(|d| Instant::now() < d)
+    }
+}
+
+/// Number of pending updates a stripe of the [Counter] holds before it is flushed to the total.
+const COUNTER_FLUSH: isize = 64;
+
+#[repr(align(64))]
+#[derive(Default)]
+struct Stripe(AtomicIsize);
+
+/// A striped counter, threads update their own stripe so that the hot path does not contend on a single atomic.
+///
+/// Each stripe buffers a few updates before flushing them into `total`,
+/// so `total` alone is a cheap approximation and `total` plus all stripes is the exact value.
+pub(crate) struct Counter {
+    total: AtomicIsize,
+    stripes: Box<[Stripe]>,
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        let cnt = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .next_power_of_two()
+            .min(64);
+        Self {
+            total: AtomicIsize::new(0),
+            stripes: (0..cnt).map(|_| Stripe::default()).collect(),
+        }
+    }
+}
+
+impl Counter {
+    fn stripe(&self) -> &AtomicIsize {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        thread_local! {
+            static THREAD_ID: usize = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let id = THREAD_ID.try_with(|id| *id).unwrap_or(0);
+        &self.stripes[id & (self.stripes.len() - 1)].0
+    }
+
+    #[inline]
+    pub(crate) fn add(&self, delta: isize) {
+        let stripe = self.stripe();
+        let pending = stripe.fetch_add(delta, Ordering::Relaxed) + delta;
+        if pending.abs() >= COUNTER_FLUSH
+            && stripe
+                .compare_exchange(pending, 0, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.total.fetch_add(pending, Ordering::Relaxed);
+        }
+    }
+
+    /// The exact value when there are no concurrent updates.
+    pub(crate) fn sum(&self) -> usize {
+        let pending: isize = self
+            .stripes
+            .iter()
+            .map(|s| s.0.load(Ordering::Relaxed))
+            .sum();
+        (self.total.load(Ordering::Relaxed) + pending).max(0) as usize
+    }
+
+    /// Off by at most `COUNTER_FLUSH` per stripe.
+    pub(crate) fn approximate(&self) -> usize {
+        self.total.load(Ordering::Relaxed).max(0) as usize
+    }
+}
+
+/// The largest number of slots that a node branching on each key position has held, it never decreases.
+///
+/// Its product over all positions bounds the product of the child counts on any path of the tree,
+/// which is what [RawTree::sample](crate::tree::RawTree::sample) accepts a random descent against,
+/// unless the subtree counts of `order_statistics` pick the value.
+#[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+pub(crate) struct FanOut<const K_LEN: usize> {
+    max: [AtomicUsize; K_LEN],
+}
+
+#[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+impl<const K_LEN: usize> Default for FanOut<K_LEN> {
+    fn default() -> Self {
+        Self {
+            max: std::array::from_fn(|_| AtomicUsize::new(1)),
+        }
+    }
+}
+
+#[cfg(all(feature = "db_extension", not(feature = "order_statistics")))]
+impl<const K_LEN: usize> FanOut<K_LEN> {
+    /// Raises the bound of `level` to `count`, the write lock that publishes the slots orders it before them.
+    #[inline]
+    pub(crate) fn raise(&self, level: usize, count: usize) {
+        let max = &self.max[level];
+        if max.load(Ordering::Relaxed) < count {
+            max.fetch_max(count, Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, level: usize) -> usize {
+        self.max[level].load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct KeyTracker<const K_LEN: usize> {
     len: usize,
-    data: [u8; 8],
+    data: [u8; K_LEN],
+}
+
+impl<const K_LEN: usize> Default for KeyTracker<K_LEN> {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            data: [0; K_LEN],
+        }
+    }
 }
 
-impl KeyTracker {
+impl<const K_LEN: usize> KeyTracker<K_LEN> {
     #[inline]
     pub(crate) fn push(&mut self, key: u8) {
-        debug_assert!(self.len <= 8);
+        debug_assert!(self.len < K_LEN);
 
         self.data[self.len] = key;
         self.len += 1;
@@ -102,21 +342,18 @@ impl KeyTracker {
     }
 
     #[inline]
-    pub(crate) fn to_usize_key(&self) -> usize {
-        assert!(self.len == 8);
-        let val = unsafe { *((&self.data) as *const [u8; 8] as *const usize) };
-        val.swap_bytes()
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len]
     }
 
     #[inline]
-    pub(crate) fn append_prefix(node: NodePtr, key_tracker: &KeyTracker) -> KeyTracker {
+    pub(crate) fn append_prefix(node: NodePtr, key_tracker: &Self) -> Self {
         let mut cur_key = key_tracker.clone();
-        if key_tracker.len() == MAX_KEY_LEN {
+        if key_tracker.len() == K_LEN {
             cur_key
         } else {
             let node_ref = unsafe { &*node.as_ptr() };
-            let n_prefix = node_ref.prefix().iter().skip(key_tracker.len());
-            for i in n_prefix {
+            for i in node_ref.prefix() {
                 cur_key.push(*i);
             }
             cur_key
@@ -129,6 +366,17 @@ impl KeyTracker {
     }
 }
 
+/// Hints the CPU to load the cache line at `ptr`, so that reading it later does not stall.
+#[inline]
+pub(crate) fn prefetch<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        std::arch::x86_64::_mm_prefetch(ptr as *const i8, std::arch::x86_64::_MM_HINT_T0);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
 /// Inject error at 5% of the time
 #[cfg(test)]
 pub(crate) fn fail_point(err: crate::error::ArtError) -> Result<(), crate::error::ArtError> {
@@ -145,7 +393,7 @@ fn random(n: u32) -> u32 {
     use std::num::Wrapping;
 
     thread_local! {
-        static RNG: Cell<Wrapping<u32>> = Cell::new(Wrapping(1_406_868_647));
+        static RNG: Cell<Wrapping<u32>> = const { Cell::new(Wrapping(1_406_868_647)) };
     }
 
     #[allow(clippy::cast_possible_truncation)]
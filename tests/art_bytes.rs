@@ -0,0 +1,252 @@
+use std::{
+    collections::BTreeMap,
+    ops::Bound::{self, Excluded, Included, Unbounded},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use congee::{ArtBytes, BackoffPolicy, DefaultAllocator};
+use rand::{prelude::StdRng, Rng, SeedableRng};
+
+enum TreeOp {
+    Get {
+        key: Vec<u8>,
+    },
+    Insert {
+        key: Vec<u8>,
+        val: usize,
+    },
+    Range {
+        low: Bound<Vec<u8>>,
+        high: Bound<Vec<u8>>,
+    },
+    Delete {
+        key: Vec<u8>,
+    },
+}
+
+fn test_runner(ops: &[TreeOp]) {
+    let art = ArtBytes::default();
+    let mut bt_map = BTreeMap::new();
+
+    let mut scan_buffer = vec![(Vec::new(), 0); 64];
+
+    for m_c in ops.chunks(1024) {
+        let guard = art.pin();
+        for m in m_c {
+            match m {
+                TreeOp::Get { key } => {
+                    assert_eq!(art.get(key, &guard), bt_map.get(key).cloned());
+                }
+                TreeOp::Insert { key, val } => {
+                    let art_insert = art.insert(key, *val, &guard).unwrap();
+                    assert_eq!(art_insert, bt_map.insert(key.clone(), *val));
+                }
+                TreeOp::Delete { key } => {
+                    assert_eq!(art.remove(key, &guard), bt_map.remove(key));
+                }
+                TreeOp::Range { low, high } => {
+                    let range = (low.as_ref().map(|k| &k[..]), high.as_ref().map(|k| &k[..]));
+                    let art_range = art.range(range, &mut scan_buffer, &guard);
+                    // BTreeMap panics on the ranges that are empty because the start is not below the end
+                    let empty = match (low, high) {
+                        (Included(l), Included(h)) => l > h,
+                        (Included(l) | Excluded(l), Included(h) | Excluded(h)) => l >= h,
+                        _ => false,
+                    };
+                    let bt_range: Vec<(&Vec<u8>, &usize)> = if empty {
+                        vec![]
+                    } else {
+                        bt_map
+                            .range((low.clone(), high.clone()))
+                            .take(scan_buffer.len())
+                            .collect()
+                    };
+
+                    assert_eq!(bt_range.len(), art_range);
+                    for (i, v) in scan_buffer.iter().take(art_range).enumerate() {
+                        assert_eq!(&v.0, bt_range[i].0);
+                        assert_eq!(v.1, *bt_range[i].1);
+                    }
+                }
+            }
+        }
+    }
+
+    let guard = art.pin();
+    for (k, v) in bt_map.iter() {
+        assert_eq!(art.get(k, &guard).unwrap(), *v);
+    }
+}
+
+/// Keys from a tiny alphabet (including 0) with a long shared prefix, so that
+/// keys are often prefixes of each other and compressed paths exceed the stored prefix.
+fn random_key(r: &mut StdRng) -> Vec<u8> {
+    let mut key = if r.gen_bool(0.5) {
+        b"a-very-long-shared-prefix/".to_vec()
+    } else {
+        vec![]
+    };
+    let len = r.gen_range(0..12);
+    for _ in 0..len {
+        key.push([0u8, 1, b'a', b'b', 255][r.gen_range(0..5)]);
+    }
+    key
+}
+
+fn random_bound(r: &mut StdRng, key: Vec<u8>) -> Bound<Vec<u8>> {
+    match r.gen_range(0..5) {
+        0..=1 => Included(key),
+        2..=3 => Excluded(key),
+        _ => Unbounded,
+    }
+}
+
+#[test]
+fn prefix_keys() {
+    let keys: Vec<&[u8]> = vec![
+        b"", b"a", b"ab", b"abc", b"abc\0", b"abc\0\0", b"abcd", b"b", b"\0", b"\0\0",
+    ];
+    let mut ops = vec![];
+    for (i, k) in keys.iter().enumerate() {
+        ops.push(TreeOp::Insert {
+            key: k.to_vec(),
+            val: i,
+        });
+    }
+    for k in keys.iter() {
+        ops.push(TreeOp::Get { key: k.to_vec() });
+    }
+    ops.push(TreeOp::Get {
+        key: b"abc\0\0\0".to_vec(),
+    });
+    ops.push(TreeOp::Range {
+        low: Unbounded,
+        high: Unbounded,
+    });
+    ops.push(TreeOp::Range {
+        low: Included(b"a".to_vec()),
+        high: Excluded(b"abc\0".to_vec()),
+    });
+    ops.push(TreeOp::Range {
+        low: Excluded(b"a".to_vec()),
+        high: Included(b"abc\0".to_vec()),
+    });
+    ops.push(TreeOp::Range {
+        low: Excluded(b"abc".to_vec()),
+        high: Unbounded,
+    });
+    ops.push(TreeOp::Range {
+        low: Unbounded,
+        high: Included(b"\0".to_vec()),
+    });
+    ops.push(TreeOp::Range {
+        low: Included(b"abc".to_vec()),
+        high: Included(b"abc".to_vec()),
+    });
+    for k in keys.iter().step_by(2) {
+        ops.push(TreeOp::Delete { key: k.to_vec() });
+    }
+    ops.push(TreeOp::Range {
+        low: Unbounded,
+        high: Unbounded,
+    });
+    test_runner(&ops);
+}
+
+#[test]
+fn long_prefix() {
+    let mut ops = vec![];
+    let base = b"0123456789abcdefghijklmnopqrstuvwxyz".to_vec();
+    for i in 0..base.len() {
+        // diverge at every position of the long shared prefix
+        let mut key = base.clone();
+        key[i] = b'#';
+        ops.push(TreeOp::Insert { key, val: i });
+        ops.push(TreeOp::Insert {
+            key: base[..i].to_vec(),
+            val: i,
+        });
+    }
+    ops.push(TreeOp::Insert {
+        key: base.clone(),
+        val: 0,
+    });
+    for i in 0..base.len() {
+        let mut key = base.clone();
+        key[i] = b'$';
+        ops.push(TreeOp::Get { key });
+    }
+    ops.push(TreeOp::Range {
+        low: Unbounded,
+        high: Unbounded,
+    });
+    test_runner(&ops);
+}
+
+#[test]
+fn rng_ops() {
+    let mut r = StdRng::seed_from_u64(42);
+    let mut ops = vec![];
+    for _ in 0..50_000 {
+        let key = random_key(&mut r);
+        let op = match r.gen_range(0..10) {
+            0..=4 => TreeOp::Insert { key, val: r.gen() },
+            5..=6 => TreeOp::Get { key },
+            7..=8 => TreeOp::Delete { key },
+            _ => {
+                let high = random_key(&mut r);
+                TreeOp::Range {
+                    low: random_bound(&mut r, key),
+                    high: random_bound(&mut r, high),
+                }
+            }
+        };
+        ops.push(op);
+    }
+    test_runner(&ops);
+}
+
+#[test]
+fn concurrent_insert_remove() {
+    let n_thread = 4;
+    let key_cnt_per_thread = 5_000;
+    let key = |t: usize, i: usize| format!("user/{}/{:08}", t, i * 7).into_bytes();
+
+    let policies = [
+        BackoffPolicy::Spin,
+        BackoffPolicy::SpinThenYield,
+        BackoffPolicy::Park {
+            max_park: Duration::from_micros(50),
+        },
+    ];
+    for policy in policies {
+        let tree = Arc::new(ArtBytes::with_backoff(DefaultAllocator {}, policy));
+
+        let mut handlers = Vec::new();
+        for t in 0..n_thread {
+            let tree = tree.clone();
+            handlers.push(thread::spawn(move || {
+                let guard = tree.pin();
+                for i in 0..key_cnt_per_thread {
+                    tree.insert(&key(t, i), i, &guard).unwrap();
+                }
+                for i in (0..key_cnt_per_thread).step_by(2) {
+                    assert_eq!(tree.remove(&key(t, i), &guard), Some(i));
+                }
+            }));
+        }
+        for h in handlers.into_iter() {
+            h.join().unwrap();
+        }
+
+        let guard = tree.pin();
+        for t in 0..n_thread {
+            for i in 0..key_cnt_per_thread {
+                let expected = if i % 2 == 0 { None } else { Some(i) };
+                assert_eq!(tree.get(&key(t, i), &guard), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use congee::{Art, ArtKey, DefaultAllocator};
+use rand::{
+    distributions::{Distribution, Standard},
+    prelude::StdRng,
+    Rng, SeedableRng,
+};
+
+fn check_order<K>(keys: &[K])
+where
+    K: ArtKey + Ord + Copy + Debug + Default,
+{
+    check_order_with_len::<K, 8>(keys);
+}
+
+/// Checks `get`, `range` and `remove` against a `BTreeMap`, so that the keys are in their natural order.
+fn check_order_with_len<K, const K_LEN: usize>(keys: &[K])
+where
+    K: ArtKey + Ord + Copy + Debug + Default,
+{
+    let art = Art::<K, usize, _, K_LEN>::new(DefaultAllocator {});
+    let mut bt_map = BTreeMap::new();
+
+    let guard = art.pin();
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(art.insert(*k, i, &guard).unwrap(), bt_map.insert(*k, i));
+    }
+    for k in keys.iter() {
+        assert_eq!(art.get(k, &guard), bt_map.get(k).cloned());
+    }
+
+    let mut result = vec![(K::default(), 0); 16];
+    for w in keys.windows(2) {
+        let (low, high) = (w[0], w[1]);
+        let scanned = art.range(low..high, &mut result, &guard);
+        let expected: Vec<_> = if low < high {
+            bt_map.range(low..high).take(result.len()).collect()
+        } else {
+            vec![]
+        };
+        assert_eq!(scanned, expected.len());
+        for (r, e) in result.iter().zip(expected) {
+            assert_eq!((&r.0, &r.1), e);
+        }
+    }
+
+    for k in keys.iter().step_by(2) {
+        assert_eq!(art.remove(k, &guard), bt_map.remove(k));
+    }
+    for k in keys.iter() {
+        assert_eq!(art.get(k, &guard), bt_map.get(k).cloned());
+    }
+}
+
+/// Checks `prefix_iter` and `count_prefix` against filtering a `BTreeMap` by the encoded key bytes,
+/// every key of `keys` is used as a prefix with every prefix length.
+fn check_prefix_with_len<K, const K_LEN: usize>(keys: &[K])
+where
+    K: ArtKey + Ord + Copy + Debug,
+{
+    let art = Art::<K, usize, _, K_LEN>::new(DefaultAllocator {});
+    let mut bt_map = BTreeMap::new();
+
+    let guard = art.pin();
+    for (i, k) in keys.iter().enumerate() {
+        art.insert(*k, i, &guard).unwrap();
+        bt_map.insert(*k, i);
+    }
+
+    let encode = |k: &K| {
+        let mut buf = vec![0; K::LEN];
+        k.encode(&mut buf);
+        buf
+    };
+    for prefix in keys.iter().step_by(keys.len() / 50 + 1) {
+        let prefix_bytes = encode(prefix);
+        for prefix_len in 0..=K::LEN {
+            let expected: Vec<_> = bt_map
+                .iter()
+                .filter(|(k, _)| encode(k)[..prefix_len] == prefix_bytes[..prefix_len])
+                .map(|(k, v)| (*k, *v))
+                .collect();
+            assert!(art
+                .prefix_iter(prefix, prefix_len, &guard)
+                .eq(expected.iter().copied()));
+            assert_eq!(art.count_prefix(prefix, prefix_len, &guard), expected.len());
+        }
+    }
+}
+
+/// Checks `from_sorted_iter` and `par_from_sorted_slice` against a `BTreeMap` of the same keys,
+/// the loaded trees must then take updates like any other tree.
+fn check_bulk_load_with_len<K, const K_LEN: usize>(keys: &[K])
+where
+    K: ArtKey + Ord + Copy + Debug + Sync,
+{
+    let bt_map: BTreeMap<_, _> = keys.iter().copied().zip(0..).collect();
+    // the sort is stable, the last value of a repeated key wins like in the map
+    let mut sorted: Vec<_> = keys.iter().copied().zip(0..).collect();
+    sorted.sort_by_key(|(k, _)| *k);
+
+    let loaded =
+        Art::<K, usize, _, K_LEN>::from_sorted_iter(sorted.iter().copied(), DefaultAllocator {});
+    let loaded_par = Art::<K, usize, _, K_LEN>::par_from_sorted_slice(&sorted, DefaultAllocator {});
+    for art in [loaded.unwrap(), loaded_par.unwrap()] {
+        let mut bt_map = bt_map.clone();
+        let guard = art.pin();
+        assert_eq!(art.len(), bt_map.len());
+        assert!(art
+            .range_iter(.., &guard)
+            .eq(bt_map.iter().map(|(k, v)| (*k, *v))));
+
+        for (i, k) in keys.iter().enumerate() {
+            if i % 2 == 0 {
+                assert_eq!(art.remove(k, &guard), bt_map.remove(k));
+            } else {
+                assert_eq!(art.insert(*k, !i, &guard).unwrap(), bt_map.insert(*k, !i));
+            }
+        }
+        assert_eq!(art.len(), bt_map.len());
+        assert!(art
+            .range_iter(.., &guard)
+            .eq(bt_map.iter().map(|(k, v)| (*k, *v))));
+    }
+}
+
+fn random_keys<K>(cnt: usize) -> Vec<K>
+where
+    Standard: Distribution<K>,
+{
+    let mut r = StdRng::seed_from_u64(42);
+    (0..cnt).map(|_| r.gen()).collect()
+}
+
+fn round_trip<K: ArtKey + PartialEq + Debug>(k: K) {
+    let mut buf = vec![0; K::LEN];
+    k.encode(&mut buf);
+    assert_eq!(K::decode(&buf), k);
+}
+
+#[test]
+fn signed_keys() {
+    check_order(&(i8::MIN..=i8::MAX).rev().collect::<Vec<_>>());
+    check_order(&random_keys::<i16>(5_000));
+    check_order(&random_keys::<i32>(10_000));
+    check_order(&random_keys::<i64>(10_000));
+    check_order(&random_keys::<isize>(10_000));
+    check_order(&[i64::MIN, -1, 0, 1, i64::MAX, -42, 42]);
+}
+
+#[test]
+fn unsigned_keys() {
+    check_order(&(0..=u8::MAX).rev().collect::<Vec<_>>());
+    check_order(&random_keys::<u16>(5_000));
+    check_order(&random_keys::<u32>(10_000));
+    check_order(&random_keys::<u64>(10_000));
+    check_order(&[0u64, u64::MAX, 1, u64::MAX - 1, 1 << 32]);
+}
+
+#[test]
+fn tuple_keys() {
+    check_order(&random_keys::<(u32, i32)>(10_000));
+    check_order(&random_keys::<(i16, u8, bool)>(5_000));
+    let r: Vec<(u16, i16)> = random_keys::<(u8, i8)>(5_000)
+        .into_iter()
+        .map(|(a, b)| (a as u16 % 4, b as i16))
+        .collect();
+    check_order(&r);
+}
+
+#[test]
+fn char_and_bool_keys() {
+    check_order(&random_keys::<char>(5_000));
+    check_order(&[true, false]);
+}
+
+#[test]
+fn short_keys() {
+    check_order_with_len::<u32, 4>(&random_keys::<u32>(10_000));
+    check_order_with_len::<i16, 2>(&random_keys::<i16>(5_000));
+    check_order_with_len::<u8, 1>(&(0..=u8::MAX).rev().collect::<Vec<_>>());
+    check_order_with_len::<(u32, u8), 5>(&random_keys::<(u32, u8)>(10_000));
+    // padded keys
+    check_order_with_len::<u16, 4>(&random_keys::<u16>(5_000));
+}
+
+#[test]
+fn wide_keys() {
+    check_order_with_len::<u128, 16>(&random_keys::<u128>(10_000));
+    check_order_with_len::<i128, 16>(&random_keys::<i128>(10_000));
+    check_order_with_len::<(u64, u64), 16>(&random_keys::<(u64, u64)>(10_000));
+    check_order_with_len::<(u64, u32), 12>(&random_keys::<(u64, u32)>(10_000));
+
+    // long compressed paths, shared by many keys or only by a few
+    let mut keys: Vec<u128> = (0..5_000).collect();
+    keys.extend((0..128).map(|i| 1u128 << i));
+    keys.extend((0..128).map(|i| u128::MAX >> i));
+    check_order_with_len::<u128, 16>(&keys);
+    let keys: Vec<i128> = keys.iter().map(|k| *k as i128).collect();
+    check_order_with_len::<i128, 16>(&keys);
+}
+
+#[test]
+fn prefix_scan() {
+    // a few tenants with many rows, including the all 0xFF tenant
+    let mut r = StdRng::seed_from_u64(42);
+    let tenants = [0, 1, 7, 1 << 16, u32::MAX - 1, u32::MAX];
+    let keys: Vec<(u32, u32)> = (0..5_000)
+        .map(|_| (tenants[r.gen_range(0..tenants.len())], r.gen()))
+        .chain([(u32::MAX, u32::MAX), (0, 0)])
+        .collect();
+    check_prefix_with_len::<(u32, u32), 8>(&keys);
+
+    // long compressed paths
+    let keys: Vec<(u64, u64)> = (0..2_000)
+        .map(|i| (i % 3, r.gen_range(0..1_000)))
+        .chain((0..64).map(|i| (1 << i, u64::MAX >> i)))
+        .collect();
+    check_prefix_with_len::<(u64, u64), 16>(&keys);
+
+    check_prefix_with_len::<u16, 4>(&random_keys::<u16>(1_000));
+    check_prefix_with_len::<u8, 1>(&random_keys::<u8>(100));
+
+    let art = Art::<u64, usize>::default();
+    let guard = art.pin();
+    assert_eq!(art.count_prefix(&0, 8, &guard), 0);
+    assert_eq!(art.prefix_iter(&0, 0, &guard).next(), None);
+}
+
+#[test]
+fn encoding_round_trip() {
+    round_trip(u128::MAX - 7);
+    round_trip(i128::MIN + 3);
+    round_trip(-7i8);
+    round_trip('🦀');
+    round_trip((1u8, -2i64, 'c', (true, 3u16)));
+    round_trip((u64::MAX, i64::MIN, u32::MAX, i32::MIN, 1u8, false));
+
+    let mut a = [0; 16];
+    let mut b = [0; 16];
+    (-1i128).encode(&mut a);
+    0i128.encode(&mut b);
+    assert!(a < b);
+}
+
+#[test]
+fn bulk_load() {
+    check_bulk_load_with_len::<u8, 1>(&random_keys::<u8>(1_000));
+    check_bulk_load_with_len::<u32, 4>(&random_keys::<u32>(10_000));
+    check_bulk_load_with_len::<i64, 8>(&random_keys::<i64>(10_000));
+    check_bulk_load_with_len::<(u32, u8), 5>(&random_keys::<(u32, u8)>(10_000));
+    check_bulk_load_with_len::<u128, 16>(&random_keys::<u128>(10_000));
+
+    // repeated keys, the dense keys fill whole nodes
+    let keys: Vec<u16> = random_keys::<u16>(10_000)
+        .into_iter()
+        .map(|k| k % 2_000)
+        .collect();
+    check_bulk_load_with_len::<u16, 2>(&keys);
+
+    // long compressed paths, which are split into chains of nodes
+    let mut keys: Vec<u128> = (0..5_000).collect();
+    keys.extend((0..128).map(|i| 1u128 << i));
+    keys.extend((0..128).map(|i| u128::MAX >> i));
+    check_bulk_load_with_len::<u128, 16>(&keys);
+
+    // keys with a long common prefix are split for the threads at the first byte in which they differ
+    let keys: Vec<u128> = random_keys::<u64>(10_000)
+        .into_iter()
+        .map(|k| (42 << 100) | (k as u128))
+        .collect();
+    check_bulk_load_with_len::<u128, 16>(&keys);
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use congee::ArtMap;
+use rand::{prelude::StdRng, Rng, SeedableRng};
+
+/// A value that tracks how many instances are alive.
+struct Tracked {
+    val: String,
+    alive: Arc<AtomicUsize>,
+}
+
+impl Tracked {
+    fn new(val: usize, alive: &Arc<AtomicUsize>) -> Self {
+        alive.fetch_add(1, Ordering::Relaxed);
+        Self {
+            val: val.to_string(),
+            alive: alive.clone(),
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.alive.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Deferred drops only run once the epoch advances, keep pinning until they are done.
+fn wait_reclaimed(alive: &AtomicUsize) {
+    for _ in 0..10_000 {
+        if alive.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        crossbeam_epoch::pin().flush();
+        thread::yield_now();
+    }
+    panic!("{} values are leaked", alive.load(Ordering::Relaxed));
+}
+
+#[test]
+fn rng_ops() {
+    let alive = Arc::new(AtomicUsize::new(0));
+    let tree = ArtMap::<u16, Tracked>::default();
+    let mut bt_map = BTreeMap::new();
+    let mut r = StdRng::seed_from_u64(42);
+
+    for chunk in 0..100 {
+        let guard = tree.pin();
+        let mut result = vec![None; 16];
+        for _ in 0..1000 {
+            let key = r.gen_range(0..2000);
+            match r.gen_range(0..4) {
+                0 | 1 => {
+                    let v = chunk * 1000 + key as usize;
+                    let old = tree.insert(key, Tracked::new(v, &alive), &guard).unwrap();
+                    assert_eq!(
+                        old.map(|o| &o.val),
+                        bt_map.insert(key, v.to_string()).as_ref()
+                    );
+                }
+                2 => {
+                    let old = tree.remove(&key, &guard);
+                    assert_eq!(old.map(|o| &o.val), bt_map.remove(&key).as_ref());
+                }
+                _ => {
+                    let end = key.saturating_add(r.gen_range(0..100));
+                    let scanned = tree.range(key..end, &mut result, &guard);
+                    let expected: Vec<_> = bt_map.range(key..end).take(result.len()).collect();
+                    assert_eq!(scanned, expected.len());
+                    for (r, (k, v)) in result.iter().zip(expected) {
+                        let (r_k, r_v) = r.as_ref().unwrap();
+                        assert_eq!(r_k, k);
+                        assert_eq!(&r_v.val, v);
+                    }
+                }
+            }
+        }
+        for (k, v) in bt_map.iter() {
+            assert_eq!(&tree.get(k, &guard).unwrap().val, v);
+        }
+    }
+
+    drop(tree);
+    wait_reclaimed(&alive);
+}
+
+#[test]
+fn concurrent_insert_remove() {
+    let alive = Arc::new(AtomicUsize::new(0));
+    let n_thread = 4;
+    let key_cnt_per_thread = 10_000;
+    let tree = Arc::new(ArtMap::<usize, Tracked>::default());
+
+    let mut handlers = Vec::new();
+    for t in 0..n_thread {
+        let tree = tree.clone();
+        let alive = alive.clone();
+        handlers.push(thread::spawn(move || {
+            for i in 0..key_cnt_per_thread {
+                let guard = tree.pin();
+                // all threads write to the same keys
+                let key = i % 1000;
+                tree.insert(key, Tracked::new(t, &alive), &guard).unwrap();
+                if let Some(v) = tree.get(&key, &guard) {
+                    assert!(v.val.parse::<usize>().unwrap() < n_thread);
+                }
+                if i % 3 == 0 {
+                    tree.remove(&key, &guard);
+                }
+            }
+        }));
+    }
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+
+    drop(tree);
+    wait_reclaimed(&alive);
+}
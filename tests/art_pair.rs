@@ -0,0 +1,138 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    thread,
+};
+
+use congee::{ArtPair, DefaultAllocator};
+use rand::{prelude::StdRng, Rng, SeedableRng};
+
+#[test]
+fn rng_ops() {
+    let tree = ArtPair::<u32>::default();
+    let mut bt_map = BTreeMap::new();
+    let mut r = StdRng::seed_from_u64(42);
+    let mut result = vec![(0, (0, 0)); 16];
+
+    for _ in 0..100 {
+        let guard = tree.pin();
+        for _ in 0..1000 {
+            let key = r.gen_range(0..5000);
+            // the high halves must survive, not only the low 64 bits
+            let val = (r.gen::<u64>(), u64::MAX - r.gen_range(0..4));
+            match r.gen_range(0..6) {
+                0 | 1 => {
+                    let old = tree.insert(key, val, &guard).unwrap();
+                    assert_eq!(old, bt_map.insert(key, val));
+                }
+                2 => {
+                    assert_eq!(tree.remove(&key, &guard), bt_map.remove(&key));
+                }
+                3 => {
+                    let old = tree.compute_if_present(&key, |(a, b)| Some((b, a)), &guard);
+                    let expected = bt_map.get_mut(&key).map(|v| {
+                        let old = *v;
+                        *v = (v.1, v.0);
+                        (old, Some(*v))
+                    });
+                    assert_eq!(old, expected);
+                }
+                4 => {
+                    let expected = bt_map.get(&key).copied();
+                    let cur = expected.unwrap_or(val);
+                    // only the second half differs, the exchange must fail
+                    let wrong = (cur.0, cur.1 ^ 1);
+                    let rv = tree.compare_exchange(&key, &wrong, Some(val), &guard);
+                    assert_eq!(rv, Err(expected));
+
+                    let rv = tree.compare_exchange(&key, &cur, None, &guard);
+                    if expected.is_some() {
+                        assert_eq!(rv, Ok(None));
+                        bt_map.remove(&key);
+                    } else {
+                        assert_eq!(rv, Err(None));
+                    }
+                }
+                _ => {
+                    let end = key + r.gen_range(0..200);
+                    let scanned = tree.range(key..end, &mut result, &guard);
+                    let expected: Vec<_> = bt_map.range(key..end).take(result.len()).collect();
+                    assert_eq!(scanned, expected.len());
+                    for (r, (k, v)) in result.iter().zip(expected) {
+                        assert_eq!((&r.0, &r.1), (k, v));
+                    }
+                }
+            }
+        }
+        for (k, v) in bt_map.iter() {
+            assert_eq!(tree.get(k, &guard), Some(*v));
+        }
+    }
+}
+
+#[test]
+fn wide_keys() {
+    let tree = ArtPair::<u128, _, 16>::new(DefaultAllocator {});
+    let guard = tree.pin();
+    let keys: Vec<u128> = (0..128).map(|i| 1u128 << i).chain(0..1000).collect();
+    for k in keys.iter() {
+        tree.insert(*k, (*k as u64, (*k >> 64) as u64), &guard)
+            .unwrap();
+    }
+    for k in keys.iter() {
+        assert_eq!(tree.get(k, &guard), Some((*k as u64, (*k >> 64) as u64)));
+    }
+
+    let mut result = vec![(0, (0, 0)); 2048];
+    let scanned = tree.range(.., &mut result, &guard);
+    assert_eq!(scanned, keys.iter().collect::<BTreeSet<_>>().len());
+    assert!(result[..scanned].windows(2).all(|w| w[0].0 < w[1].0));
+
+    for k in keys.iter() {
+        tree.remove(k, &guard);
+    }
+    assert_eq!(tree.range(.., &mut result, &guard), 0);
+}
+
+#[test]
+fn concurrent_compare_exchange() {
+    let n_thread = 4;
+    let rounds = 2_000;
+    let key_cnt = 64u64;
+    let tree = Arc::new(ArtPair::<u64>::default());
+    {
+        let guard = tree.pin();
+        for k in 0..key_cnt {
+            tree.insert(k, (k, 0), &guard).unwrap();
+        }
+    }
+
+    let mut handlers = Vec::new();
+    for _ in 0..n_thread {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            for i in 0..rounds {
+                let guard = tree.pin();
+                let key = i % key_cnt;
+                // bump the version of (row, version), retry until the exchange succeeds
+                loop {
+                    let cur = tree.get(&key, &guard).unwrap();
+                    assert_eq!(cur.0, key);
+                    if tree
+                        .compare_exchange(&key, &cur, Some((cur.0, cur.1 + 1)), &guard)
+                        .is_ok()
+                    {
+                        break;
+                    }
+                }
+            }
+        }));
+    }
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+
+    let guard = tree.pin();
+    let total: u64 = (0..key_cnt).map(|k| tree.get(&k, &guard).unwrap().1).sum();
+    assert_eq!(total, n_thread * rounds);
+}
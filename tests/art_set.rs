@@ -0,0 +1,92 @@
+use std::{collections::BTreeSet, sync::Arc, thread};
+
+use congee::{ArtSet, DefaultAllocator};
+use rand::{prelude::StdRng, Rng, SeedableRng};
+
+#[test]
+fn rng_ops() {
+    let set = ArtSet::<u32>::default();
+    let mut bt_set = BTreeSet::new();
+    let mut r = StdRng::seed_from_u64(42);
+    let mut result = vec![0; 16];
+
+    for _ in 0..100 {
+        let guard = set.pin();
+        for _ in 0..1000 {
+            let key = r.gen_range(0..5000);
+            match r.gen_range(0..4) {
+                0 | 1 => assert_eq!(set.insert(key, &guard).unwrap(), bt_set.insert(key)),
+                2 => assert_eq!(set.remove(&key, &guard), bt_set.remove(&key)),
+                _ => {
+                    let end = key + r.gen_range(0..200);
+                    let scanned = set.range(key..end, &mut result, &guard);
+                    let expected: Vec<_> = bt_set.range(key..end).take(result.len()).collect();
+                    assert_eq!(scanned, expected.len());
+                    for (r, e) in result.iter().zip(expected) {
+                        assert_eq!(r, e);
+                    }
+                }
+            }
+        }
+        for k in 0..5000 {
+            assert_eq!(set.contains(&k, &guard), bt_set.contains(&k));
+        }
+        assert!(set.iter(&guard).eq(bt_set.iter().copied()));
+        assert_eq!(set.len(), bt_set.len());
+    }
+}
+
+#[test]
+fn iter_extreme_keys() {
+    let set = ArtSet::<i64>::default();
+    let guard = set.pin();
+    assert_eq!(set.iter(&guard).next(), None);
+
+    let keys = [i64::MIN, -1, 0, 1, i64::MAX];
+    for k in keys.iter() {
+        set.insert(*k, &guard).unwrap();
+    }
+    assert!(set.iter(&guard).eq(keys.iter().copied()));
+    assert!(set.iter_rev(&guard).eq(keys.iter().rev().copied()));
+
+    // more keys than a batch, ending with the largest key
+    let set = ArtSet::<u16, _, 2>::new(DefaultAllocator {});
+    for k in (0..=u16::MAX).step_by(7).chain([u16::MAX]) {
+        set.insert(k, &guard).unwrap();
+    }
+    let expected: Vec<_> = (0..=u16::MAX).step_by(7).chain([u16::MAX]).collect();
+    assert_eq!(set.iter(&guard).collect::<Vec<_>>(), expected);
+    assert!(set.iter_rev(&guard).eq(expected.iter().rev().copied()));
+}
+
+#[test]
+fn concurrent_insert_remove() {
+    let n_thread = 4;
+    let key_cnt_per_thread = 10_000;
+    let set = Arc::new(ArtSet::<usize>::default());
+
+    let mut handlers = Vec::new();
+    for t in 0..n_thread {
+        let set = set.clone();
+        handlers.push(thread::spawn(move || {
+            let guard = set.pin();
+            for i in 0..key_cnt_per_thread {
+                let key = i * n_thread + t;
+                assert!(set.insert(key, &guard).unwrap());
+                if i % 2 == 0 {
+                    assert!(set.remove(&key, &guard));
+                }
+            }
+        }));
+    }
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+
+    let guard = set.pin();
+    let expected = (0..key_cnt_per_thread * n_thread).filter(|k| (k / n_thread) % 2 == 1);
+    assert_eq!(set.len(), key_cnt_per_thread * n_thread / 2);
+    let approx = set.approximate_len();
+    assert!(approx.abs_diff(set.len()) <= 64 * n_thread, "{approx}");
+    assert!(set.iter(&guard).eq(expected));
+}
@@ -1,9 +1,16 @@
-use std::{collections::BTreeMap, vec};
+use std::{
+    collections::BTreeMap,
+    ops::Bound,
+    sync::{Arc, Barrier},
+    thread,
+    time::Duration,
+    vec,
+};
 
-use congee::Art;
+use congee::{Art, BackoffPolicy, DefaultAllocator, Entry, RetryBudget, TryInsertError};
 use rand::{
     prelude::{SliceRandom, StdRng},
-    SeedableRng,
+    Rng, SeedableRng,
 };
 
 enum TreeOp {
@@ -50,7 +57,7 @@ fn test_runner(ops: &[TreeOp]) {
                         low_v + cnt
                     };
 
-                    let au_range = art_usize.range(low_v, &high_key, &mut au_scan_buffer, &guard);
+                    let au_range = art_usize.range(*low_v..high_key, &mut au_scan_buffer, &guard);
                     let bt_range: Vec<(&usize, &usize)> = bt_map.range(*low_v..high_key).collect();
 
                     assert_eq!(bt_range.len(), au_range);
@@ -68,6 +75,8 @@ fn test_runner(ops: &[TreeOp]) {
     for (k, v) in bt_map.iter() {
         assert_eq!(art_usize.get(k, &guard).unwrap(), *v);
     }
+    assert_eq!(art_usize.len(), bt_map.len());
+    assert!(art_usize.approximate_len().abs_diff(bt_map.len()) < 64);
 }
 
 #[test]
@@ -108,15 +117,12 @@ fn rng_insert() {
     key_space.shuffle(&mut r);
 
     let mut ops = vec![];
-    for i in 0..key_cnt {
-        ops.push(TreeOp::Insert {
-            key: key_space[i],
-            val: key_space[i],
-        });
+    for k in key_space.iter() {
+        ops.push(TreeOp::Insert { key: *k, val: *k });
     }
 
-    for i in 0..key_cnt {
-        ops.push(TreeOp::Get { key: key_space[i] });
+    for k in key_space.iter() {
+        ops.push(TreeOp::Get { key: *k });
     }
 
     for i in key_cnt..2 * key_cnt {
@@ -177,9 +183,9 @@ fn small_scan() {
 fn fuzz_0() {
     let key: usize = 4294967295;
     let ops = vec![
-        TreeOp::Insert { key: key, val: key },
-        TreeOp::Insert { key: key, val: key },
-        TreeOp::Get { key: key },
+        TreeOp::Insert { key, val: key },
+        TreeOp::Insert { key, val: key },
+        TreeOp::Get { key },
     ];
 
     test_runner(&ops);
@@ -188,9 +194,9 @@ fn fuzz_0() {
 #[cfg(feature = "db_extension")]
 #[test]
 fn compute_if_present() {
-    let tree = Art::new();
+    let tree = Art::default();
     let guard = tree.pin();
-    tree.insert(1, 42, &guard);
+    tree.insert(1, 42, &guard).unwrap();
     let (old_v, new_v) = tree
         .compute_if_present(
             &1,
@@ -224,9 +230,9 @@ fn compute_if_present() {
 #[cfg(feature = "db_extension")]
 #[test]
 fn random_value() {
-    let tree = Art::new();
+    let tree = Art::default();
     let guard = tree.pin();
-    tree.insert(1, 42, &guard);
+    tree.insert(1, 42, &guard).unwrap();
     let mut rng = rand::thread_rng();
     let (key, old_v, new_v) = tree
         .compute_on_random(
@@ -265,9 +271,9 @@ fn random_value() {
 #[cfg(feature = "db_extension")]
 #[test]
 fn compare_exchange() {
-    let tree = Art::new();
+    let tree = Art::default();
     let guard = tree.pin();
-    tree.insert(1, 42, &guard);
+    tree.insert(1, 42, &guard).unwrap();
 
     let v = tree.compare_exchange(&1, &42, Some(43), &guard).unwrap();
     assert_eq!(v, Some(43));
@@ -293,3 +299,1361 @@ fn compare_exchange() {
         panic!("should have failed");
     }
 }
+
+#[cfg(feature = "db_extension")]
+#[test]
+fn sample_uniform() {
+    let tree = Art::default();
+    let guard = tree.pin();
+    let mut rng = StdRng::seed_from_u64(42);
+    assert!(tree.sample(&mut rng, 10, &guard).is_empty());
+
+    // a full leaf next to a few keys with leaves of their own
+    let mut keys: Vec<usize> = (0..256).collect();
+    keys.extend((1..5).map(|i| i << 40));
+    for k in keys.iter() {
+        tree.insert(*k, k + 1, &guard).unwrap();
+    }
+
+    let rounds = 400;
+    let mut counts = BTreeMap::new();
+    for (k, v) in tree.sample(&mut rng, keys.len() * rounds, &guard) {
+        assert_eq!(v, k + 1);
+        *counts.entry(k).or_insert(0) += 1;
+    }
+    for k in keys.iter() {
+        let count = counts.get(k).copied().unwrap_or(0);
+        assert!(
+            (rounds / 2..rounds * 3 / 2).contains(&count),
+            "key {k} sampled {count} times"
+        );
+    }
+}
+
+#[cfg(feature = "db_extension")]
+#[test]
+fn sample_weighted() {
+    let tree = Art::default();
+    let guard = tree.pin();
+    for k in 0..1000 {
+        tree.insert(k, k % 4, &guard).unwrap();
+    }
+    let mut rng = StdRng::seed_from_u64(42);
+    let samples = tree.sample_weighted(&mut rng, 8000, 3.0, |_, v| *v as f64, &guard);
+    assert_eq!(samples.len(), 8000);
+
+    let mut counts = [0; 4];
+    for (k, v) in samples {
+        assert_eq!(v, k % 4);
+        counts[v] += 1;
+    }
+    assert_eq!(counts[0], 0);
+    // expected 8000 / 6 * v
+    for (v, count) in counts.iter().enumerate().skip(1) {
+        let expected = 8000 * v / 6;
+        assert!(
+            (expected * 8 / 10..expected * 12 / 10).contains(count),
+            "value {v} sampled {count} times"
+        );
+    }
+
+    // no key has a positive weight, the draws run out
+    let samples = tree.sample_weighted(&mut rng, 10, 1.0, |_, _| 0.0, &guard);
+    assert!(samples.is_empty());
+}
+
+#[cfg(feature = "db_extension")]
+#[test]
+fn sample_skewed() {
+    // the keys are spread over all levels, a uniform descent reaches the deep ones very rarely
+    let tree = Art::default();
+    let guard = tree.pin();
+    let keys: Vec<usize> = (1..256)
+        .flat_map(|i| (0..8).map(move |j| i << (8 * j)))
+        .collect();
+    for k in keys.iter() {
+        tree.insert(*k, *k, &guard).unwrap();
+    }
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let samples = tree.sample(&mut rng, 2000, &guard);
+    assert_eq!(samples.len(), 2000);
+    assert!(samples.iter().all(|(k, v)| k == v && keys.contains(k)));
+    // about 1270 distinct keys are expected
+    let distinct: BTreeMap<_, _> = samples.into_iter().collect();
+    assert!(distinct.len() > 1000, "{} distinct keys", distinct.len());
+}
+
+#[cfg(feature = "db_extension")]
+#[test]
+fn sample_concurrent_updates() {
+    // the odd keys come and go, as do the even keys right below them
+    let key_cnt = 10_000;
+    let threads = 2;
+    let tree = Arc::new(Art::default());
+    {
+        let guard = tree.pin();
+        for k in (0..key_cnt).step_by(2) {
+            tree.insert(k, k, &guard).unwrap();
+        }
+    }
+
+    let handlers: Vec<_> = (0..threads)
+        .map(|t| {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                let guard = tree.pin();
+                let keys = (1..key_cnt).step_by(2).skip(t).step_by(threads);
+                for k in keys.clone() {
+                    tree.insert(k, k, &guard).unwrap();
+                    tree.remove(&(k - 1), &guard);
+                    tree.insert(k - 1, k - 1, &guard).unwrap();
+                }
+                for k in keys {
+                    tree.remove(&k, &guard);
+                }
+            })
+        })
+        .collect();
+
+    let guard = tree.pin();
+    let mut rng = StdRng::seed_from_u64(42);
+    for _ in 0..100 {
+        for (k, v) in tree.sample(&mut rng, 100, &guard) {
+            assert!(k < key_cnt);
+            assert_eq!(k, v);
+        }
+    }
+
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn range_iter() {
+    let tree = Art::default();
+    let mut bt_map = BTreeMap::new();
+    let mut r = StdRng::seed_from_u64(42);
+
+    let guard = tree.pin();
+    for _ in 0..10_000 {
+        let k = r.gen_range(0..100_000);
+        tree.insert(k, k + 1, &guard).unwrap();
+        bt_map.insert(k, k + 1);
+    }
+
+    for _ in 0..100 {
+        let start = r.gen_range(0..100_000);
+        let end = start + r.gen_range(0..10_000);
+        let expected = bt_map.range(start..end).map(|(k, v)| (*k, *v));
+        assert!(tree.range_iter(start..end, &guard).eq(expected));
+    }
+    assert_eq!(tree.range_iter(0..usize::MAX, &guard).count(), bt_map.len());
+    assert_eq!(tree.range_iter(5..5, &guard).next(), None);
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 6..5;
+    assert_eq!(tree.range_iter(reversed, &guard).next(), None);
+}
+
+#[test]
+fn range_rev() {
+    let tree = Art::default();
+    let mut bt_map = BTreeMap::new();
+    let mut r = StdRng::seed_from_u64(42);
+
+    let guard = tree.pin();
+    // sparse keys for the small nodes, a dense run for Node48 and Node256
+    for k in (0..10_000)
+        .map(|_| r.gen_range(0..100_000))
+        .chain(200_000..201_000)
+    {
+        tree.insert(k, k + 1, &guard).unwrap();
+        bt_map.insert(k, k + 1);
+    }
+
+    let mut result = vec![(0, 0); 64];
+    for _ in 0..100 {
+        let start = r.gen_range(0..202_000);
+        let end = start + r.gen_range(0..10_000);
+        let expected: Vec<_> = bt_map
+            .range(start..end)
+            .rev()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        assert!(tree
+            .range_iter_rev(start..end, &guard)
+            .eq(expected.iter().copied()));
+
+        let scanned = tree.range_rev(start..end, &mut result, &guard);
+        assert_eq!(&result[..scanned], &expected[..scanned.min(expected.len())]);
+        assert_eq!(scanned, expected.len().min(result.len()));
+    }
+    assert!(tree
+        .range_iter_rev(0..usize::MAX, &guard)
+        .eq(bt_map.iter().rev().map(|(k, v)| (*k, *v))));
+    assert_eq!(tree.range_iter_rev(5..5, &guard).next(), None);
+    assert_eq!(tree.range_rev(5..5, &mut result, &guard), 0);
+}
+
+#[test]
+fn range_bounds() {
+    let tree = Art::default();
+    let mut bt_map = BTreeMap::new();
+    let mut r = StdRng::seed_from_u64(42);
+
+    let guard = tree.pin();
+    for k in (0..1_000)
+        .map(|_| r.gen_range(0..10_000))
+        .chain([0, 1, usize::MAX - 1, usize::MAX])
+    {
+        tree.insert(k, !k, &guard).unwrap();
+        bt_map.insert(k, !k);
+    }
+
+    let mut result = vec![(0, 0); 64];
+    let mut check = |range: (Bound<usize>, Bound<usize>)| {
+        let expected: Vec<_> = bt_map.range(range).map(|(k, v)| (*k, *v)).collect();
+        assert!(tree.range_iter(range, &guard).eq(expected.iter().copied()));
+        assert!(tree
+            .range_iter_rev(range, &guard)
+            .eq(expected.iter().rev().copied()));
+
+        let scanned = tree.range(range, &mut result, &guard);
+        assert_eq!(scanned, expected.len().min(result.len()));
+        assert_eq!(&result[..scanned], &expected[..scanned]);
+
+        let scanned = tree.range_rev(range, &mut result, &guard);
+        assert_eq!(scanned, expected.len().min(result.len()));
+        assert!(result[..scanned]
+            .iter()
+            .eq(expected.iter().rev().take(scanned)));
+    };
+
+    for _ in 0..100 {
+        let low = r.gen_range(0..10_000);
+        let high = low + r.gen_range(0..100);
+        check((Bound::Included(low), Bound::Excluded(high)));
+        check((Bound::Included(low), Bound::Included(high)));
+        check((Bound::Excluded(low), Bound::Included(high)));
+        check((Bound::Included(low), Bound::Unbounded));
+        check((Bound::Excluded(low), Bound::Unbounded));
+        check((Bound::Unbounded, Bound::Excluded(high)));
+        check((Bound::Unbounded, Bound::Included(high)));
+    }
+    for k in [0, 1, usize::MAX - 1, usize::MAX] {
+        check((Bound::Included(k), Bound::Included(k)));
+        check((Bound::Included(k), Bound::Excluded(k)));
+        check((Bound::Included(k), Bound::Unbounded));
+        check((Bound::Excluded(k), Bound::Unbounded));
+        check((Bound::Unbounded, Bound::Included(k)));
+        check((Bound::Unbounded, Bound::Excluded(k)));
+    }
+    check((Bound::Unbounded, Bound::Unbounded));
+
+    // empty and inverted ranges scan nothing instead of panicking
+    assert_eq!(tree.range(1..1, &mut result, &guard), 0);
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 2..=1;
+    assert_eq!(tree.range(reversed.clone(), &mut result, &guard), 0);
+    assert_eq!(tree.range_iter_rev(reversed, &guard).next(), None);
+    let excluded = (Bound::Excluded(1), Bound::Excluded(1));
+    assert_eq!(tree.range(excluded, &mut result, &guard), 0);
+}
+
+#[test]
+fn ordered_navigation() {
+    let tree = Art::default();
+    let mut bt_map = BTreeMap::new();
+    let mut r = StdRng::seed_from_u64(42);
+
+    let guard = tree.pin();
+    assert_eq!(tree.first_key_value(&guard), None);
+    assert_eq!(tree.last_key_value(&guard), None);
+    assert_eq!(tree.lower_bound(&0, &guard), None);
+    assert_eq!(tree.predecessor(&usize::MAX, &guard), None);
+
+    // sparse keys for the small nodes, a dense run for Node48 and Node256
+    for k in (0..2_000)
+        .map(|_| r.gen_range(0..100_000))
+        .chain(200_000..200_300)
+        .chain([0, usize::MAX])
+    {
+        tree.insert(k, !k, &guard).unwrap();
+        bt_map.insert(k, !k);
+    }
+    // remove some keys so that the nodes have holes
+    for _ in 0..500 {
+        let k = r.gen_range(0..201_000);
+        assert_eq!(tree.remove(&k, &guard), bt_map.remove(&k));
+    }
+
+    let kv = |(k, v): (&usize, &usize)| (*k, *v);
+    assert_eq!(
+        tree.first_key_value(&guard),
+        bt_map.first_key_value().map(kv)
+    );
+    assert_eq!(tree.last_key_value(&guard), bt_map.last_key_value().map(kv));
+
+    let probes =
+        (0..2_000)
+            .map(|_| r.gen_range(0..201_000))
+            .chain([0, 1, usize::MAX - 1, usize::MAX]);
+    for k in probes {
+        assert_eq!(
+            tree.lower_bound(&k, &guard),
+            bt_map.range(k..).next().map(kv)
+        );
+        assert_eq!(
+            tree.upper_bound(&k, &guard),
+            bt_map.range(..=k).next_back().map(kv)
+        );
+        let after = (Bound::Excluded(k), Bound::Unbounded);
+        assert_eq!(
+            tree.successor(&k, &guard),
+            bt_map.range(after).next().map(kv)
+        );
+        assert_eq!(
+            tree.predecessor(&k, &guard),
+            bt_map.range(..k).next_back().map(kv)
+        );
+    }
+}
+
+#[test]
+fn ordered_navigation_concurrent_updates() {
+    // writers only touch odd keys, the neighbours of an even key are found among the even keys
+    let key_cnt = 20_000;
+    let tree = Arc::new(Art::default());
+    {
+        let guard = tree.pin();
+        for k in (0..key_cnt).step_by(2) {
+            tree.insert(k, k, &guard).unwrap();
+        }
+    }
+
+    let mut handlers = Vec::new();
+    for t in 0..2 {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            let mut r = StdRng::seed_from_u64(t);
+            for _ in 0..50_000 {
+                let guard = tree.pin();
+                let k = r.gen_range(0..key_cnt / 2) * 2 + 1;
+                if r.gen_bool(0.5) {
+                    tree.insert(k, k, &guard).unwrap();
+                } else {
+                    tree.remove(&k, &guard);
+                }
+            }
+        }));
+    }
+
+    let mut r = StdRng::seed_from_u64(42);
+    for _ in 0..50_000 {
+        let guard = tree.pin();
+        let k = r.gen_range(1..key_cnt / 2 - 1) * 2;
+        assert_eq!(tree.lower_bound(&k, &guard), Some((k, k)));
+        assert_eq!(tree.upper_bound(&k, &guard), Some((k, k)));
+
+        let (next, v) = tree.successor(&k, &guard).unwrap();
+        assert!(next == k + 1 || next == k + 2);
+        assert_eq!(next, v);
+        let (prev, v) = tree.predecessor(&k, &guard).unwrap();
+        assert!(prev == k - 1 || prev == k - 2);
+        assert_eq!(prev, v);
+    }
+
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn entry() {
+    let tree = Art::default();
+    let mut bt_map = BTreeMap::new();
+    let mut r = StdRng::seed_from_u64(42);
+
+    let guard = tree.pin();
+    for _ in 0..10_000 {
+        let k = r.gen_range(0..1_000);
+        let v = r.gen_range(0..1_000);
+        match r.gen_range(0..4) {
+            0 => {
+                let mut called = false;
+                let art_v = tree
+                    .entry(k, &guard)
+                    .or_insert_with(|| {
+                        called = true;
+                        v
+                    })
+                    .unwrap();
+                assert_eq!(called, !bt_map.contains_key(&k));
+                assert_eq!(art_v, *bt_map.entry(k).or_insert(v));
+            }
+            1 => {
+                let art_v = tree
+                    .entry(k, &guard)
+                    .and_modify(|old| old + v)
+                    .or_insert(v)
+                    .unwrap();
+                let bt_v = bt_map.entry(k).and_modify(|old| *old += v).or_insert(v);
+                assert_eq!(art_v, *bt_v);
+            }
+            2 => {
+                let entry = tree.entry(k, &guard);
+                assert_eq!(*entry.key(), k);
+                assert_eq!(entry.insert(v).unwrap(), bt_map.insert(k, v));
+            }
+            _ => assert_eq!(tree.entry(k, &guard).remove(), bt_map.remove(&k)),
+        }
+    }
+    for (k, v) in bt_map.iter() {
+        assert_eq!(tree.get(k, &guard), Some(*v));
+    }
+    assert_eq!(tree.len(), bt_map.len());
+}
+
+#[test]
+fn entry_concurrent_or_insert() {
+    // every thread races to insert its own id, they must all see the same winner
+    let n_thread = 4;
+    let key_cnt = 10_000;
+    let tree = Arc::new(Art::default());
+
+    let mut handlers = Vec::new();
+    for t in 0..n_thread {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            let guard = tree.pin();
+            let mut seen = Vec::with_capacity(key_cnt);
+            let mut called = 0;
+            for k in 0..key_cnt {
+                let v = tree
+                    .entry(k, &guard)
+                    .or_insert_with(|| {
+                        called += 1;
+                        t
+                    })
+                    .unwrap();
+                seen.push(v);
+            }
+            (seen, called)
+        }));
+    }
+
+    let results: Vec<_> = handlers.into_iter().map(|h| h.join().unwrap()).collect();
+    let guard = tree.pin();
+    let winners: Vec<_> = (0..key_cnt)
+        .map(|k| tree.get(&k, &guard).unwrap())
+        .collect();
+    for (seen, called) in results.iter() {
+        assert_eq!(seen, &winners);
+        assert!(*called <= key_cnt);
+    }
+    for (t, (_, called)) in results.iter().enumerate() {
+        // a thread that won a key must have called its `default` for it
+        let won = winners.iter().filter(|w| **w == t).count();
+        assert!(*called >= won);
+    }
+    assert_eq!(tree.len(), key_cnt);
+}
+
+#[test]
+fn entry_or_insert_after_concurrent_remove() {
+    // the entry is created while the key is present, then another thread removes the key before `or_insert`
+    let key_cnt = 1_000;
+    let tree = Arc::new(Art::default());
+    let barrier = Arc::new(Barrier::new(2));
+
+    let remover = {
+        let tree = tree.clone();
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            for k in 0..key_cnt {
+                barrier.wait();
+                let guard = tree.pin();
+                assert_eq!(tree.remove(&k, &guard), Some(k));
+                barrier.wait();
+            }
+        })
+    };
+
+    for k in 0..key_cnt {
+        let guard = tree.pin();
+        tree.insert(k, k, &guard).unwrap();
+        let occupied = tree.entry(k, &guard);
+        assert!(matches!(occupied, Entry::Occupied(_)));
+        let occupied_with = tree.entry(k, &guard);
+
+        barrier.wait();
+        barrier.wait();
+        assert_eq!(occupied.or_insert(k + 1).unwrap(), k + 1);
+        assert_eq!(tree.get(&k, &guard), Some(k + 1));
+
+        tree.remove(&k, &guard).unwrap();
+        let v = occupied_with.or_insert_with(|| k + 2).unwrap();
+        assert_eq!(v, k + 2);
+        assert_eq!(tree.get(&k, &guard), Some(k + 2));
+    }
+    remover.join().unwrap();
+}
+
+#[test]
+fn try_ops_concurrent_updates() {
+    // writers keep the values of the keys equal to the keys, a try op either sees that or gives up
+    let key_cnt = 256;
+    let tree = Arc::new(Art::default());
+
+    let mut handlers = Vec::new();
+    for t in 0..2 {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            let mut r = StdRng::seed_from_u64(t);
+            for _ in 0..100_000 {
+                let guard = tree.pin();
+                let k = r.gen_range(0..key_cnt);
+                if r.gen_bool(0.5) {
+                    tree.insert(k, k, &guard).unwrap();
+                } else {
+                    tree.remove(&k, &guard);
+                }
+            }
+        }));
+    }
+
+    let mut r = StdRng::seed_from_u64(42);
+    let mut contended = 0;
+    for _ in 0..100_000 {
+        let guard = tree.pin();
+        let k = r.gen_range(0..key_cnt);
+        let budget = RetryBudget::restarts(r.gen_range(0..4));
+        let consistent = match r.gen_range(0..4) {
+            0 => tree
+                .try_get(&k, budget, &guard)
+                .map(|v| v.is_none_or(|v| v == k)),
+            1 => match tree.try_insert(k, k, budget, &guard) {
+                Ok(old) => Ok(old.is_none_or(|v| v == k)),
+                Err(TryInsertError::Contended(e)) => Err(e),
+                Err(TryInsertError::Oom(_)) => unreachable!(),
+            },
+            2 => tree
+                .try_remove(&k, budget, &guard)
+                .map(|v| v.is_none_or(|v| v == k)),
+            _ => tree
+                .try_compute_if_present(&k, Some, budget, &guard)
+                .map(|v| v.is_none_or(|v| v == (k, Some(k)))),
+        };
+        match consistent {
+            Ok(consistent) => assert!(consistent),
+            Err(_) => contended += 1,
+        }
+    }
+    println!("{contended} try ops gave up");
+
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn backoff_policies_concurrent_updates() {
+    // every thread increments the values of a few hot keys, no increment may be lost under any policy
+    let policies = [
+        BackoffPolicy::Spin,
+        BackoffPolicy::SpinThenYield,
+        BackoffPolicy::Park {
+            max_park: Duration::from_micros(50),
+        },
+    ];
+    let n_thread = 4;
+    let op_cnt = 20_000;
+    let hot_cnt = 16;
+
+    for policy in policies {
+        let tree = Arc::new(Art::<usize, usize>::with_backoff(
+            DefaultAllocator {},
+            policy,
+        ));
+        let guard = tree.pin();
+        for k in 0..hot_cnt {
+            tree.insert(k, 0, &guard).unwrap();
+        }
+        drop(guard);
+
+        let mut handlers = Vec::new();
+        for t in 0..n_thread {
+            let tree = tree.clone();
+            handlers.push(thread::spawn(move || {
+                let mut r = StdRng::seed_from_u64(t as u64);
+                let mut scan_buffer = vec![(0, 0); hot_cnt];
+                let mut increments = 0;
+                for i in 0..op_cnt {
+                    let guard = tree.pin();
+                    match r.gen_range(0..4) {
+                        0 => {
+                            let k = r.gen_range(0..hot_cnt);
+                            tree.compute_if_present(&k, |v| Some(v + 1), &guard)
+                                .unwrap();
+                            increments += 1;
+                        }
+                        #[cfg(feature = "db_extension")]
+                        1 => {
+                            tree.compute_on_random(&mut r, |_, v| v + 1, &guard)
+                                .unwrap();
+                            increments += 1;
+                        }
+                        2 => {
+                            let k = hot_cnt + i * n_thread + t;
+                            assert_eq!(tree.insert(k, 0, &guard).unwrap(), None);
+                        }
+                        _ => {
+                            let scanned = tree.range(0..hot_cnt, &mut scan_buffer, &guard);
+                            assert_eq!(scanned, hot_cnt);
+                            for (i, (k, _)) in scan_buffer.iter().enumerate() {
+                                assert_eq!(*k, i);
+                            }
+                            let keys: Vec<_> = (0..hot_cnt).collect();
+                            let mut values = vec![None; hot_cnt];
+                            tree.get_many(&keys, &mut values, &guard);
+                            assert!(values.iter().all(|v| v.is_some()));
+                            assert!(tree.get(&r.gen_range(0..hot_cnt), &guard).is_some());
+                        }
+                    }
+                }
+                increments
+            }));
+        }
+        let increments: usize = handlers.into_iter().map(|h| h.join().unwrap()).sum();
+
+        let guard = tree.pin();
+        let total: usize = tree.range_iter(.., &guard).map(|(_, v)| v).sum();
+        assert_eq!(total, increments, "{policy:?}");
+    }
+}
+
+#[test]
+fn range_iter_concurrent_updates() {
+    // writers only touch odd keys, the even keys must be returned exactly once and in order
+    let key_cnt = 20_000;
+    let tree = Arc::new(Art::default());
+    {
+        let guard = tree.pin();
+        for k in (0..key_cnt).step_by(2) {
+            tree.insert(k, k, &guard).unwrap();
+        }
+    }
+
+    let mut handlers = Vec::new();
+    for t in 0..2 {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            let mut r = StdRng::seed_from_u64(t);
+            for _ in 0..50_000 {
+                let guard = tree.pin();
+                let k = r.gen_range(0..key_cnt / 2) * 2 + 1;
+                if r.gen_bool(0.5) {
+                    tree.insert(k, k, &guard).unwrap();
+                } else {
+                    tree.remove(&k, &guard);
+                }
+            }
+        }));
+    }
+
+    for _ in 0..20 {
+        let guard = tree.pin();
+        let mut last = None;
+        let mut even = 0;
+        for (k, v) in tree.range_iter(0..key_cnt, &guard) {
+            assert_eq!(k, v);
+            assert!(last < Some(k));
+            last = Some(k);
+            if k % 2 == 0 {
+                assert_eq!(k, even);
+                even += 2;
+            }
+        }
+        assert_eq!(even, key_cnt);
+
+        let mut last = None;
+        for (k, v) in tree.range_iter_rev(0..key_cnt, &guard) {
+            assert_eq!(k, v);
+            assert!(last.is_none_or(|last| k < last));
+            last = Some(k);
+            if k % 2 == 0 {
+                even -= 2;
+                assert_eq!(k, even);
+            }
+        }
+        assert_eq!(even, 0);
+    }
+
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn remove_range() {
+    let mut r = StdRng::seed_from_u64(42);
+    let mut bounds = |low: usize, high: usize| {
+        let start = match r.gen_range(0..3) {
+            0 => Bound::Included(low),
+            1 => Bound::Excluded(low),
+            _ => Bound::Unbounded,
+        };
+        let end = match r.gen_range(0..3) {
+            0 => Bound::Included(high),
+            1 => Bound::Excluded(high),
+            _ => Bound::Unbounded,
+        };
+        (start, end)
+    };
+
+    for round in 0..20 {
+        let tree = Art::default();
+        let mut bt_map = BTreeMap::new();
+        let guard = tree.pin();
+        // dense keys fill whole last level nodes, sparse keys leave them straddling the bounds
+        for k in (0..1 << 16).chain((0..1_000).map(|k| k * 7919 + (1 << 20))) {
+            tree.insert(k, !k, &guard).unwrap();
+            bt_map.insert(k, !k);
+        }
+
+        for _ in 0..5 {
+            let low = [0, 0x100, 0x1234, 1 << 20][round % 4] + round * 0x80;
+            let high = low + [0x80, 0x1000, 0x10000, usize::MAX >> 1][round % 4];
+            let range = bounds(low, high);
+            let expected: Vec<_> = bt_map.range(range).map(|(k, _)| *k).collect();
+            assert_eq!(tree.remove_range(range, &guard), expected.len());
+            for k in expected {
+                bt_map.remove(&k);
+            }
+            assert!(tree
+                .range_iter(.., &guard)
+                .eq(bt_map.iter().map(|(k, v)| (*k, *v))));
+            assert_eq!(tree.len(), bt_map.len());
+        }
+
+        let removed = tree.retain(|k, v| k % 3 != 0 && v == !k, &guard);
+        let before = bt_map.len();
+        bt_map.retain(|k, _| k % 3 != 0);
+        assert_eq!(removed, before - bt_map.len());
+        assert!(tree
+            .range_iter(.., &guard)
+            .eq(bt_map.iter().map(|(k, v)| (*k, *v))));
+
+        assert_eq!(tree.remove_range(.., &guard), bt_map.len());
+        assert!(tree.is_empty());
+        assert_eq!(tree.first_key_value(&guard), None);
+    }
+}
+
+#[test]
+fn remove_range_concurrent_updates() {
+    // writers only touch keys at or above `split`, the main thread repeatedly fills and truncates the keys below
+    let split = 1 << 16;
+    let tree = Arc::new(Art::default());
+
+    let mut handlers = Vec::new();
+    for t in 0..2 {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            let mut r = StdRng::seed_from_u64(t);
+            let mut kept = Vec::new();
+            for i in 0..50_000 {
+                let guard = tree.pin();
+                let k = split + r.gen_range(0..split);
+                if r.gen_bool(0.5) {
+                    tree.insert(k, k, &guard).unwrap();
+                } else {
+                    tree.remove(&k, &guard);
+                }
+                // the keys owned by this thread are never touched by anyone else
+                let own = (split << 2) + (i << 1) + t as usize;
+                tree.insert(own, own, &guard).unwrap();
+                kept.push(own);
+            }
+            kept
+        }));
+    }
+
+    for round in 0..20 {
+        let guard = tree.pin();
+        for k in 0..split {
+            tree.insert(k, k, &guard).unwrap();
+        }
+        let (low, high) = (round * 100, split - round * 100);
+        assert_eq!(tree.remove_range(low..high, &guard), high - low);
+        assert_eq!(tree.remove_range(..split, &guard), split - (high - low));
+        assert_eq!(tree.range_iter(..split, &guard).next(), None);
+    }
+
+    let guard = tree.pin();
+    for h in handlers.into_iter() {
+        for k in h.join().unwrap() {
+            assert_eq!(tree.get(&k, &guard), Some(k));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "the keys are not sorted")]
+fn from_sorted_iter_unsorted() {
+    let _ = Art::<usize, usize>::from_sorted_iter([(1, 1), (3, 3), (2, 2)], DefaultAllocator {});
+}
+
+#[test]
+#[should_panic(expected = "the keys are not sorted")]
+fn par_from_sorted_slice_unsorted() {
+    let mut items: Vec<(usize, usize)> = (0..100_000).map(|k| (k << 40, k)).collect();
+    items.swap(1_000, 90_000);
+    let _ = Art::<usize, usize>::par_from_sorted_slice(&items, DefaultAllocator {});
+}
+
+#[test]
+fn get_many_concurrent_updates() {
+    // writers only touch odd keys, the even keys must always be found
+    let key_cnt = 20_000;
+    let tree = Arc::new(Art::default());
+    {
+        let guard = tree.pin();
+        for k in (0..key_cnt).step_by(2) {
+            tree.insert(k, k, &guard).unwrap();
+        }
+    }
+
+    let mut handlers = Vec::new();
+    for t in 0..2 {
+        let tree = tree.clone();
+        handlers.push(thread::spawn(move || {
+            let mut r = StdRng::seed_from_u64(t);
+            for _ in 0..50_000 {
+                let guard = tree.pin();
+                let k = r.gen_range(0..key_cnt / 2) * 2 + 1;
+                if r.gen_bool(0.5) {
+                    tree.insert(k, k, &guard).unwrap();
+                } else {
+                    tree.remove(&k, &guard);
+                }
+            }
+        }));
+    }
+
+    let mut r = StdRng::seed_from_u64(42);
+    let mut values = vec![None; 1_000];
+    for round in 0..200 {
+        let guard = tree.pin();
+        let mut keys: Vec<usize> = (0..1_000).map(|_| r.gen_range(0..key_cnt)).collect();
+        if round % 2 == 0 {
+            keys.sort();
+        }
+        tree.get_many(&keys, &mut values, &guard);
+        for (k, v) in keys.iter().zip(values.iter()) {
+            if k % 2 == 0 {
+                assert_eq!(*v, Some(*k));
+            } else {
+                assert!(v.is_none() || *v == Some(*k));
+            }
+        }
+    }
+
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn read_modify_write() {
+    let tree = Art::default();
+    let guard = tree.pin();
+
+    assert_eq!(tree.fetch_add(1, 5, &guard).unwrap(), None);
+    assert_eq!(tree.fetch_sub(1, 7, &guard).unwrap(), Some(5));
+    assert_eq!(tree.fetch_add(1, 2, &guard).unwrap(), Some(usize::MAX - 1));
+    assert_eq!(tree.fetch_sub(2, 1, &guard).unwrap(), None);
+    assert_eq!(tree.get(&1, &guard), Some(0));
+    assert_eq!(tree.get(&2, &guard), Some(usize::MAX));
+
+    assert_eq!(tree.swap(&3, 42, &guard), None);
+    assert_eq!(tree.get(&3, &guard), None);
+    assert_eq!(tree.swap(&2, 42, &guard), Some(usize::MAX));
+    assert_eq!(tree.get(&2, &guard), Some(42));
+
+    assert_eq!(tree.insert_if_absent(3, 43, &guard).unwrap(), None);
+    assert_eq!(tree.insert_if_absent(3, 44, &guard).unwrap(), Some(43));
+    assert_eq!(tree.get(&3, &guard), Some(43));
+
+    assert_eq!(tree.remove_if(&3, |v| v != 43, &guard), Err(Some(43)));
+    assert_eq!(tree.remove_if(&3, |v| v == 43, &guard), Ok(43));
+    assert_eq!(tree.remove_if(&3, |_| true, &guard), Err(None));
+    assert_eq!(tree.get(&3, &guard), None);
+
+    // `None` as the expected value matches an absent key
+    assert_eq!(tree.compare_exchange(&3, None, None, &guard), Ok(None));
+    assert_eq!(tree.compare_exchange(&2, None, None, &guard), Err(Some(42)));
+    assert_eq!(
+        tree.compare_exchange(&3, None, Some(45), &guard),
+        Ok(Some(45))
+    );
+    assert_eq!(
+        tree.compare_exchange(&3, None, Some(46), &guard),
+        Err(Some(45))
+    );
+    assert_eq!(tree.compare_exchange(&3, &45, None, &guard), Ok(None));
+    assert_eq!(tree.compare_exchange(&3, &45, Some(47), &guard), Err(None));
+    assert_eq!(tree.len(), 2);
+}
+
+#[test]
+fn read_modify_write_concurrent() {
+    // every update is linearizable: the previous values of a counter returned to all threads are distinct,
+    // and exactly one thread wins the race to insert or to remove a key
+    let counters = 16;
+    let keys = 10_000;
+    let threads = 4;
+    let tree = Arc::new(Art::default());
+
+    let race = |remove: bool| {
+        let handlers: Vec<_> = (0..threads)
+            .map(|t| {
+                let tree = tree.clone();
+                thread::spawn(move || {
+                    let mut seen = vec![Vec::new(); counters];
+                    let mut won = Vec::new();
+                    for i in 0..keys {
+                        let guard = tree.pin();
+                        let c = i % counters;
+                        seen[c].push(tree.fetch_add(c, 1, &guard).unwrap().unwrap_or(0));
+
+                        let k = counters + i;
+                        let claimed = match (remove, i % 2 == 0) {
+                            (false, true) => tree.insert_if_absent(k, t, &guard).unwrap().is_none(),
+                            (false, false) => {
+                                tree.compare_exchange(&k, None, Some(t), &guard).is_ok()
+                            }
+                            (true, _) => tree
+                                .remove_if(&k, |_| true, &guard)
+                                .is_ok_and(|v| v < threads),
+                        };
+                        if claimed {
+                            won.push(k);
+                        }
+                    }
+                    (seen, won)
+                })
+            })
+            .collect();
+
+        let mut seen = vec![Vec::new(); counters];
+        let mut won = Vec::new();
+        for h in handlers.into_iter() {
+            let (s, w) = h.join().unwrap();
+            for (all, s) in seen.iter_mut().zip(s) {
+                all.extend(s);
+            }
+            won.extend(w);
+        }
+        won.sort();
+        assert!(won.into_iter().eq(counters..counters + keys));
+        seen
+    };
+
+    let increments = threads * keys / counters;
+    let seen = race(false);
+    let guard = tree.pin();
+    assert_eq!(tree.len(), counters + keys);
+    for (c, mut seen) in seen.into_iter().enumerate() {
+        assert_eq!(tree.get(&c, &guard), Some(increments));
+        seen.sort();
+        assert!(seen.into_iter().eq(0..increments));
+    }
+
+    race(true);
+    assert_eq!(tree.len(), counters);
+    for c in 0..counters {
+        assert_eq!(tree.get(&c, &guard), Some(2 * increments));
+    }
+}
+
+#[test]
+fn transaction() {
+    let tree = Art::default();
+    let guard = tree.pin();
+    let mut model = BTreeMap::new();
+
+    let mut commit = |batch: &[(usize, Option<usize>)]| {
+        tree.transaction(
+            |tx| {
+                for &(k, v) in batch {
+                    match v {
+                        Some(v) => tx.put(k, v),
+                        None => tx.remove(&k),
+                    }
+                }
+            },
+            &guard,
+        )
+        .unwrap();
+        for &(k, v) in batch {
+            match v {
+                Some(v) => model.insert(k, v),
+                None => model.remove(&k),
+            };
+        }
+        assert_eq!(tree.len(), model.len());
+        for (k, v) in model.iter() {
+            assert_eq!(tree.get(k, &guard), Some(*v));
+        }
+        for &(k, _) in batch {
+            assert_eq!(tree.get(&k, &guard), model.get(&k).copied());
+        }
+    };
+
+    // new subtrees, a growing last level node, a split compressed path, unlinked subtrees
+    commit(&(0..200).map(|k| (k, Some(k))).collect::<Vec<_>>());
+    commit(&[(0x1000, Some(1))]);
+    commit(&(0x1001..0x1020).map(|k| (k, Some(k))).collect::<Vec<_>>());
+    commit(&[
+        (0x12345678, Some(2)),
+        (0x12000000, Some(3)),
+        (0x12345600, Some(4)),
+    ]);
+    commit(&(0..0x1020).map(|k| (k, None)).collect::<Vec<_>>());
+    commit(&[(0x12345678, None), (0x12000000, None), (5, Some(5))]);
+    commit(&[(0x12345600, None), (5, None)]);
+    assert!(tree.is_empty());
+
+    let mut rng = StdRng::seed_from_u64(42);
+    for _ in 0..200 {
+        let batch: Vec<_> = (0..rng.gen_range(1..64))
+            .map(|_| {
+                let k = match rng.gen_range(0..3) {
+                    0 => rng.gen_range(0..0x400),
+                    1 => rng.gen_range(0..0x40000),
+                    _ => rng.gen::<usize>(),
+                };
+                let v = rng.gen_bool(0.7).then(|| rng.gen::<usize>());
+                (k, v)
+            })
+            .collect();
+        commit(&batch);
+    }
+
+    // reads see the writes of the same transaction, which only take effect on commit
+    let n = tree
+        .transaction(
+            |tx| {
+                tx.put(usize::MAX, 1);
+                tx.remove(&usize::MAX);
+                assert_eq!(tx.get(&usize::MAX), None);
+                tx.put(usize::MAX - 1, 2);
+                tx.get(&(usize::MAX - 1))
+            },
+            &guard,
+        )
+        .unwrap();
+    assert_eq!(n, Some(2));
+    assert_eq!(tree.get(&usize::MAX, &guard), None);
+}
+
+#[test]
+fn transaction_concurrent_transfers() {
+    // transfers between accounts keep the total, which every read-only transaction sees;
+    // an empty account is removed, so that last level nodes are unlinked and loaded again
+    let accounts = 64;
+    let balance = 1000;
+    let key = |i: usize| i * 97;
+    let tree = Arc::new(Art::default());
+    {
+        let guard = tree.pin();
+        for i in 0..accounts {
+            tree.insert(key(i), balance, &guard).unwrap();
+        }
+    }
+
+    let writers: Vec<_> = (0..4)
+        .map(|t| {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                let mut rng = StdRng::seed_from_u64(t);
+                for _ in 0..2000 {
+                    let from = key(rng.gen_range(0..accounts));
+                    let to = key(rng.gen_range(0..accounts));
+                    let share = rng.gen_range(1..=100);
+                    let guard = tree.pin();
+                    tree.transaction(
+                        |tx| {
+                            let from_balance = tx.get(&from).unwrap_or(0);
+                            if from == to || from_balance == 0 {
+                                return;
+                            }
+                            let amount = std::cmp::max(from_balance * share / 100, 1);
+                            if amount == from_balance {
+                                tx.remove(&from);
+                            } else {
+                                tx.put(from, from_balance - amount);
+                            }
+                            let to_balance = tx.get(&to).unwrap_or(0);
+                            tx.put(to, to_balance + amount);
+                        },
+                        &guard,
+                    )
+                    .unwrap();
+                }
+            })
+        })
+        .collect();
+
+    let reader = {
+        let tree = tree.clone();
+        thread::spawn(move || {
+            for _ in 0..500 {
+                let guard = tree.pin();
+                let total = tree
+                    .transaction(
+                        |tx| {
+                            (0..accounts)
+                                .map(|i| tx.get(&key(i)).unwrap_or(0))
+                                .sum::<usize>()
+                        },
+                        &guard,
+                    )
+                    .unwrap();
+                assert_eq!(total, accounts * balance);
+            }
+        })
+    };
+
+    for h in writers {
+        h.join().unwrap();
+    }
+    reader.join().unwrap();
+
+    let guard = tree.pin();
+    let mut total = 0;
+    let mut present = 0;
+    for i in 0..accounts {
+        if let Some(v) = tree.get(&key(i), &guard) {
+            total += v;
+            present += 1;
+        }
+    }
+    assert_eq!(total, accounts * balance);
+    assert_eq!(tree.len(), present);
+}
+
+#[test]
+fn rename() {
+    let tree = Art::default();
+    let guard = tree.pin();
+    for k in [1, 2, 0x100, 1 << 40] {
+        tree.insert(k, k, &guard).unwrap();
+    }
+
+    // within one last level node, to another one, onto a present key, to a new subtree
+    assert_eq!(tree.rename(&1, 3, &guard).unwrap(), Some(1));
+    assert_eq!(tree.rename(&3, 0x101, &guard).unwrap(), Some(1));
+    assert_eq!(tree.rename(&0x101, 2, &guard).unwrap(), Some(1));
+    assert_eq!(tree.rename(&2, 1 << 50, &guard).unwrap(), Some(1));
+    assert_eq!(tree.rename(&0x100, 0x100, &guard).unwrap(), Some(0x100));
+    assert_eq!(tree.rename(&7, 8, &guard).unwrap(), None);
+
+    assert_eq!(tree.len(), 3);
+    for k in [1, 2, 3, 0x101, 7, 8] {
+        assert_eq!(tree.get(&k, &guard), None);
+    }
+    assert_eq!(tree.get(&(1 << 50), &guard), Some(1));
+    assert_eq!(tree.get(&0x100, &guard), Some(0x100));
+    assert_eq!(tree.get(&(1 << 40), &guard), Some(1 << 40));
+}
+
+#[test]
+fn rename_concurrent() {
+    // every thread moves its records between its own slots, some in the same last level node;
+    // a read-only transaction always finds every record under exactly one slot
+    let threads = 4;
+    let slots = 64;
+    let records = 16;
+    let slot_key = move |t: usize, s: usize| (s * threads + t) * 37;
+    let tree = Arc::new(Art::default());
+    {
+        let guard = tree.pin();
+        for t in 0..threads {
+            for r in 0..records {
+                tree.insert(slot_key(t, r), t * records + r, &guard)
+                    .unwrap();
+            }
+        }
+    }
+
+    let writers: Vec<_> = (0..threads)
+        .map(|t| {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                let mut rng = StdRng::seed_from_u64(t as u64);
+                let mut occupied: Vec<bool> = (0..slots).map(|s| s < records).collect();
+                for _ in 0..2000 {
+                    let from = rng.gen_range(0..slots);
+                    let to = rng.gen_range(0..slots);
+                    if !occupied[from] || occupied[to] {
+                        continue;
+                    }
+                    let guard = tree.pin();
+                    let moved = tree
+                        .rename(&slot_key(t, from), slot_key(t, to), &guard)
+                        .unwrap();
+                    assert!(moved.is_some());
+                    occupied[from] = false;
+                    occupied[to] = true;
+                }
+            })
+        })
+        .collect();
+
+    let reader = {
+        let tree = tree.clone();
+        thread::spawn(move || {
+            for _ in 0..200 {
+                let guard = tree.pin();
+                let mut seen = tree
+                    .transaction(
+                        |tx| {
+                            (0..threads)
+                                .flat_map(|t| (0..slots).map(move |s| slot_key(t, s)))
+                                .filter_map(|k| tx.get(&k))
+                                .collect::<Vec<_>>()
+                        },
+                        &guard,
+                    )
+                    .unwrap();
+                seen.sort();
+                assert!(seen.into_iter().eq(0..threads * records));
+            }
+        })
+    };
+
+    for h in writers {
+        h.join().unwrap();
+    }
+    reader.join().unwrap();
+    assert_eq!(tree.len(), threads * records);
+}
+
+#[cfg(feature = "order_statistics")]
+#[test]
+fn order_statistics() {
+    let tree = Art::default();
+    let guard = tree.pin();
+    assert_eq!(tree.rank(&42, &guard), 0);
+    assert_eq!(tree.select(0, &guard), None);
+    assert_eq!(tree.count_range(.., &guard), 0);
+
+    // dense and sparse keys, some of them removed again
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut expected = BTreeMap::new();
+    for _ in 0..20_000 {
+        let k = rng.gen::<usize>() >> rng.gen_range(0..64);
+        if rng.gen_bool(0.2) {
+            assert_eq!(tree.remove(&k, &guard), expected.remove(&k));
+        } else {
+            tree.insert(k, k + 1, &guard).unwrap();
+            expected.insert(k, k + 1);
+        }
+    }
+    for k in 0..1_000 {
+        tree.insert(k, k + 1, &guard).unwrap();
+        expected.insert(k, k + 1);
+    }
+    assert_eq!(tree.remove_range(300..400, &guard), 100);
+    expected.retain(|k, _| !(300..400).contains(k));
+
+    let keys: Vec<usize> = expected.keys().copied().collect();
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(tree.rank(k, &guard), i);
+        assert_eq!(tree.select(i, &guard), Some((*k, k + 1)));
+    }
+    assert_eq!(tree.select(keys.len(), &guard), None);
+    assert_eq!(
+        tree.rank(&usize::MAX, &guard),
+        expected.range(..usize::MAX).count()
+    );
+
+    for _ in 0..1_000 {
+        let (a, b) = (
+            rng.gen::<usize>(),
+            rng.gen::<usize>() >> rng.gen_range(0..64),
+        );
+        assert_eq!(tree.rank(&a, &guard), expected.range(..a).count());
+        assert_eq!(
+            tree.count_range(b..a, &guard),
+            keys.iter().filter(|k| (b..a).contains(*k)).count()
+        );
+        assert_eq!(
+            tree.count_range(b..=a, &guard),
+            keys.iter().filter(|k| (b..=a).contains(*k)).count()
+        );
+        assert_eq!(tree.count_range(..b, &guard), expected.range(..b).count());
+        let after_b = (Bound::Excluded(b), Bound::Unbounded);
+        assert_eq!(
+            tree.count_range(after_b, &guard),
+            expected.range(after_b).count()
+        );
+    }
+    assert_eq!(tree.count_range(.., &guard), keys.len());
+}
+
+#[cfg(feature = "order_statistics")]
+#[test]
+fn order_statistics_concurrent_updates() {
+    // the stable keys stay, every thread inserts and removes one key between them at a time
+    let stable = 100;
+    let threads = 4;
+    let stable_key = |i: usize| i << 20;
+    let tree = Arc::new(Art::default());
+    {
+        let guard = tree.pin();
+        for i in 0..stable {
+            tree.insert(stable_key(i), stable_key(i), &guard).unwrap();
+        }
+    }
+
+    let handlers: Vec<_> = (0..threads)
+        .map(|t| {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                let mut rng = StdRng::seed_from_u64(t as u64);
+                for _ in 0..5_000 {
+                    let guard = tree.pin();
+                    let k = stable_key(rng.gen_range(0..stable)) + rng.gen_range(1..1 << 20);
+                    tree.insert(k, k, &guard).unwrap();
+                    tree.remove(&k, &guard);
+                }
+            })
+        })
+        .collect();
+
+    let guard = tree.pin();
+    for _ in 0..200 {
+        for i in (0..stable).step_by(7) {
+            let rank = tree.rank(&stable_key(i), &guard);
+            assert!((i..=i + threads).contains(&rank), "rank {rank} of key {i}");
+            let (k, v) = tree.select(i, &guard).unwrap();
+            assert_eq!(k, v);
+            assert!(k <= stable_key(i));
+        }
+        let count = tree.count_range(.., &guard);
+        assert!((stable..=stable + threads).contains(&count));
+    }
+
+    for h in handlers.into_iter() {
+        h.join().unwrap();
+    }
+    for i in 0..stable {
+        assert_eq!(tree.rank(&stable_key(i), &guard), i);
+        assert_eq!(tree.select(i, &guard), Some((stable_key(i), stable_key(i))));
+    }
+    assert_eq!(tree.count_range(.., &guard), stable);
+}